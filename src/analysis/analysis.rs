@@ -0,0 +1,36 @@
+use crate::analysis::cfg::ControlFlowGraph;
+use crate::analysis::uninitialized::find_uninitialized_reads;
+use crate::analysis::warning::*;
+use crate::ast::ast::*;
+
+// 関数実装ごとに制御フローグラフを作成し, 警告を出す
+fn analyze_function(ast: &Ast) {
+    let func_name = match &ast.kind {
+        AstKind::FunctionImplementation((func_name, _, _)) => func_name,
+        _ => return,
+    };
+    let cfg = ControlFlowGraph::new(ast);
+
+    for info in cfg.unreachable_statements() {
+        output_unreachablecode_warn(&info);
+    }
+
+    for (name, info) in find_uninitialized_reads(ast, &cfg) {
+        output_uninitialized_warn(&name, &info);
+    }
+
+    // mainは末尾に到達すると0を返すので対象外
+    let is_void = ast.type_.function.as_ref().unwrap().ret.is_none();
+    if !is_void && func_name != "main" && cfg.can_fall_off_end() {
+        // 関数本体の閉じ括弧の位置を警告位置とする
+        let body_info = ast.context.as_ref().unwrap().info;
+        output_missingreturn_warn(func_name, &body_info);
+    }
+}
+
+// 構文解析後のASTを解析して警告を出す
+pub fn analyze_asts(asts: &[Ast]) {
+    for ast in asts {
+        analyze_function(ast);
+    }
+}
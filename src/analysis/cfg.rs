@@ -0,0 +1,257 @@
+use crate::ast::ast::*;
+use crate::ast::constant::get_integer_constant;
+use crate::token::token::TokenInfo;
+
+// 基本ブロック
+// exprsにはブロック内で評価される式(文, if, while, forの条件式等)が実行順に入る
+pub struct BasicBlock<'a> {
+    pub exprs: Vec<&'a Ast>,
+    pub succs: Vec<usize>,
+    pub preds: Vec<usize>,
+}
+
+impl<'a> BasicBlock<'a> {
+    fn new() -> Self {
+        BasicBlock {
+            exprs: vec![],
+            succs: vec![],
+            preds: vec![],
+        }
+    }
+}
+
+// 関数実装の制御フローグラフ
+// entryから処理を開始し, returnと関数末尾への到達は後続のない出口ブロックへ合流する
+// statementsには文の開始ブロックと文の位置をソースコード順に記録する
+pub struct ControlFlowGraph<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub entry: usize,
+    // 関数本体の末尾に到達するブロック
+    pub end: usize,
+    statements: Vec<(usize, TokenInfo)>,
+}
+
+struct CfgBuilder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    exit: usize,
+    // 現在の(最後に文を追加する)ブロック
+    current: usize,
+    // breakで脱出する先のブロック
+    break_targets: Vec<usize>,
+    statements: Vec<(usize, TokenInfo)>,
+}
+
+impl<'a> CfgBuilder<'a> {
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::new());
+        self.blocks.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        self.blocks[from].succs.push(to);
+        self.blocks[to].preds.push(from);
+    }
+
+    fn push_expr(&mut self, ast: &'a Ast) {
+        self.blocks[self.current].exprs.push(ast);
+    }
+
+    // return, breakの後の文は新しい(どこからも到達しない)ブロックに入れる
+    fn jump_to(&mut self, target: usize) {
+        let current = self.current;
+        self.add_edge(current, target);
+        self.current = self.new_block();
+    }
+
+    fn build_statement(&mut self, ast: &'a Ast) {
+        match &ast.kind {
+            AstKind::Expressions => {
+                for expr in ast.exprs.as_ref().unwrap() {
+                    self.statements.push((self.current, statement_info(expr)));
+                    self.build_statement(expr);
+                }
+            }
+            AstKind::Control(Control::Return) => {
+                self.push_expr(ast);
+                let exit = self.exit;
+                self.jump_to(exit);
+            }
+            AstKind::Control(Control::Break) => {
+                if let Some(&target) = self.break_targets.last() {
+                    self.jump_to(target);
+                }
+            }
+            AstKind::Control(Control::If) => self.build_if(ast),
+            AstKind::Control(Control::For) => self.build_for(ast),
+            AstKind::Control(Control::While) => self.build_while(ast),
+            _ => self.push_expr(ast),
+        }
+    }
+
+    // if文の条件式はifの直前のブロックで評価し, true/falseそれぞれのブロックへ分岐する
+    fn build_if(&mut self, ast: &'a Ast) {
+        let condition = ast.context.as_ref().unwrap();
+        let if_context = ast.other.as_ref().unwrap();
+        self.push_expr(condition);
+        let cond_block = self.current;
+        let end_block = self.new_block();
+
+        let true_block = self.new_block();
+        self.add_edge(cond_block, true_block);
+        self.current = true_block;
+        self.build_statement(if_context[0].as_ref().unwrap());
+        let current = self.current;
+        self.add_edge(current, end_block);
+
+        if let Some(else_ast) = &if_context[1] {
+            let else_block = self.new_block();
+            self.add_edge(cond_block, else_block);
+            self.current = else_block;
+            self.build_statement(else_ast);
+            let current = self.current;
+            self.add_edge(current, end_block);
+        } else {
+            self.add_edge(cond_block, end_block);
+        }
+        self.current = end_block;
+    }
+
+    // 条件式ブロック, ループ内容ブロック, ループ後のブロックを作成する
+    // 条件式が0でない定数(または省略)の場合はループ後のブロックへはbreakでのみ到達する
+    fn build_loop(
+        &mut self,
+        condition: Option<&'a Ast>,
+        context: &'a Ast,
+        update: Option<&'a Ast>,
+    ) {
+        let cond_block = self.new_block();
+        let current = self.current;
+        self.add_edge(current, cond_block);
+        let end_block = self.new_block();
+        if let Some(condition) = condition {
+            self.blocks[cond_block].exprs.push(condition);
+        }
+        if !is_always_true(condition) {
+            self.add_edge(cond_block, end_block);
+        }
+
+        let body_block = self.new_block();
+        self.add_edge(cond_block, body_block);
+        self.current = body_block;
+        self.break_targets.push(end_block);
+        self.build_statement(context);
+        self.break_targets.pop();
+        if let Some(update) = update {
+            self.push_expr(update);
+        }
+        let current = self.current;
+        self.add_edge(current, cond_block);
+        self.current = end_block;
+    }
+
+    fn build_for(&mut self, ast: &'a Ast) {
+        let for_conditions = ast.other.as_ref().unwrap();
+        if let Some(initialize_ast) = &for_conditions[0] {
+            self.push_expr(initialize_ast);
+        }
+        let context = ast.context.as_ref().unwrap();
+        self.build_loop(
+            for_conditions[1].as_ref(),
+            context,
+            for_conditions[2].as_ref(),
+        );
+    }
+
+    fn build_while(&mut self, ast: &'a Ast) {
+        let condition = ast.context.as_ref().unwrap();
+        let context = &ast.exprs.as_ref().unwrap()[0];
+        self.build_loop(Some(condition), context, None);
+    }
+}
+
+// 条件式が省略されているか, 0でない整数定数式か
+// while (1)だけでなくwhile (1 + 1)のような定数式も常に真とする
+fn is_always_true(condition: Option<&Ast>) -> bool {
+    match condition {
+        None => true,
+        Some(ast) => get_integer_constant(ast).is_some_and(|value| value != 0),
+    }
+}
+
+// 文の先頭のトークン位置を求める
+// 二項演算のASTは演算子の位置を持つので, 左側の子をたどって最も前の位置を探す
+fn statement_info(ast: &Ast) -> TokenInfo {
+    let mut info = ast.info;
+    let first_child = match &ast.kind {
+        AstKind::Expressions => ast.exprs.as_ref().and_then(|exprs| exprs.first()),
+        AstKind::Control(_) => None,
+        _ => ast.left.as_deref().or(ast.operand.as_deref()),
+    };
+    if let Some(child) = first_child {
+        let child_info = statement_info(child);
+        if (child_info.line, child_info.pos) < (info.line, info.pos) {
+            info = child_info;
+        }
+    }
+    info
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    // 関数実装ASTから制御フローグラフを作成する
+    pub fn new(func_ast: &'a Ast) -> Self {
+        let mut builder = CfgBuilder {
+            blocks: vec![],
+            exit: 0,
+            current: 0,
+            break_targets: vec![],
+            statements: vec![],
+        };
+        let entry = builder.new_block();
+        builder.exit = builder.new_block();
+        builder.current = entry;
+        builder.build_statement(func_ast.context.as_ref().unwrap());
+        let end = builder.current;
+        builder.add_edge(end, builder.exit);
+
+        ControlFlowGraph {
+            blocks: builder.blocks,
+            entry,
+            end,
+            statements: builder.statements,
+        }
+    }
+
+    // entryから到達可能なブロックを求める
+    pub fn reachable_blocks(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![self.entry];
+        while let Some(block) = stack.pop() {
+            if reachable[block] {
+                continue;
+            }
+            reachable[block] = true;
+            stack.extend(self.blocks[block].succs.iter());
+        }
+        reachable
+    }
+
+    // 到達しない文の位置を返す
+    // 到達しない文が連続する場合は先頭の文のみを返す
+    pub fn unreachable_statements(&self) -> Vec<TokenInfo> {
+        let reachable = self.reachable_blocks();
+        let mut infos = vec![];
+        let mut prev_reachable = true;
+        for (block, info) in &self.statements {
+            if prev_reachable && !reachable[*block] {
+                infos.push(*info);
+            }
+            prev_reachable = reachable[*block];
+        }
+        infos
+    }
+
+    // returnせずに関数の末尾に到達する経路があるか
+    pub fn can_fall_off_end(&self) -> bool {
+        self.reachable_blocks()[self.end]
+    }
+}
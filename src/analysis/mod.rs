@@ -0,0 +1,4 @@
+pub mod analysis;
+mod cfg;
+mod uninitialized;
+pub mod warning;
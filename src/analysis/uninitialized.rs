@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::analysis::cfg::ControlFlowGraph;
+use crate::ast::ast::*;
+use crate::definition::types::PrimitiveType;
+use crate::definition::variables::{LocalVariable, Variable};
+use crate::token::token::TokenInfo;
+
+// 未初期化の可能性があるローカル変数の集合
+// 変数の番号をインデックスとし, trueなら未初期化の可能性がある
+type UninitSet = Vec<bool>;
+
+// 解析対象のローカル変数
+// 引数は呼び出し時に初期化されている. 配列, 構造体は要素単位の初期化を追えないので対象外とする
+struct TrackedLocals {
+    indices: HashMap<*const LocalVariable, usize>,
+    vals: Vec<Rc<LocalVariable>>,
+}
+
+impl TrackedLocals {
+    fn get_index(&self, ast: &Ast) -> Option<usize> {
+        match &ast.kind {
+            AstKind::Variable(Variable::LocalVal(val)) => {
+                self.indices.get(&Rc::as_ptr(val)).copied()
+            }
+            _ => None,
+        }
+    }
+
+    fn collect(&mut self, ast: &Ast) {
+        if let AstKind::Variable(Variable::LocalVal(val)) = &ast.kind {
+            let is_scalar = !val.type_.is_array() && !val.type_.is_struct();
+            let ptr = Rc::as_ptr(val);
+            if is_scalar && !self.indices.contains_key(&ptr) {
+                self.indices.insert(ptr, self.vals.len());
+                self.vals.push(val.clone());
+            }
+        }
+        for child in children(ast) {
+            self.collect(child);
+        }
+    }
+}
+
+// ASTの子を評価順に返す
+fn children(ast: &Ast) -> Vec<&Ast> {
+    let mut children: Vec<&Ast> = vec![];
+    children.extend(ast.operand.as_deref());
+    children.extend(ast.left.as_deref());
+    children.extend(ast.right.as_deref());
+    if let Some(exprs) = &ast.exprs {
+        children.extend(exprs.iter());
+    }
+    children.extend(ast.context.as_deref());
+    if let Some(other) = &ast.other {
+        children.extend(other.iter().flatten());
+    }
+    children
+}
+
+// 式を評価順にたどり, 変数の読み出しと書き込みに合わせて集合を更新する
+// reads には未初期化の可能性がある状態で読み出した変数と位置を記録する
+struct Transfer<'a> {
+    locals: &'a TrackedLocals,
+    reads: Vec<(usize, TokenInfo)>,
+}
+
+impl<'a> Transfer<'a> {
+    fn visit(&mut self, ast: &Ast, set: &mut UninitSet) {
+        match &ast.kind {
+            AstKind::Variable(_) => {
+                if let Some(index) = self.locals.get_index(ast) {
+                    if set[index] {
+                        self.reads.push((index, ast.info));
+                    }
+                }
+            }
+            AstKind::Operation(Operation::Assign) => {
+                self.visit(ast.right.as_ref().unwrap(), set);
+                self.visit_lvalue(ast.left.as_ref().unwrap(), set);
+            }
+            // アドレスを取られた変数はポインタ経由で書き込まれる可能性があるので, 初期化済みとみなす
+            AstKind::Address => self.visit_lvalue(ast.operand.as_ref().unwrap(), set),
+            // (void)x は値を使わないことを明示するための式なので読み出しとしない
+            AstKind::Cast
+                if ast.type_.primitive == Some(PrimitiveType::Void)
+                    && matches!(ast.operand.as_ref().unwrap().kind, AstKind::Variable(_)) => {}
+            // 右辺は評価されない場合があるので, 右辺での書き込みは以降に反映しない
+            AstKind::Operation(Operation::And | Operation::Or) => {
+                self.visit(ast.left.as_ref().unwrap(), set);
+                let mut right_set = set.clone();
+                self.visit(ast.right.as_ref().unwrap(), &mut right_set);
+            }
+            _ => {
+                for child in children(ast) {
+                    self.visit(child, set);
+                }
+            }
+        }
+    }
+
+    // 書き込み先(アドレスを求める式)をたどる
+    // 変数自体は読み出さず, ポインタの参照先を求める式は読み出す
+    fn visit_lvalue(&mut self, ast: &Ast, set: &mut UninitSet) {
+        match &ast.kind {
+            AstKind::Variable(_) => {
+                if let Some(index) = self.locals.get_index(ast) {
+                    set[index] = false;
+                }
+            }
+            AstKind::Member(_) => self.visit_lvalue(ast.operand.as_ref().unwrap(), set),
+            _ => self.visit(ast, set),
+        }
+    }
+
+    fn visit_block(&mut self, exprs: &[&Ast], set: &mut UninitSet) {
+        for expr in exprs {
+            self.visit(expr, set);
+        }
+    }
+}
+
+/// 未初期化の可能性があるローカル変数の読み出しを求める
+///
+/// 各ブロックの入口で未初期化の可能性がある変数の集合を, 先行ブロックの出口の集合の和として
+/// 不動点まで繰り返し計算する. 変数ごとに最初の読み出し位置を返す
+pub fn find_uninitialized_reads(
+    func_ast: &Ast,
+    cfg: &ControlFlowGraph,
+) -> Vec<(String, TokenInfo)> {
+    let mut locals = TrackedLocals {
+        indices: HashMap::new(),
+        vals: vec![],
+    };
+    locals.collect(func_ast.context.as_ref().unwrap());
+
+    // 関数の入口では引数以外のローカル変数が未初期化
+    let mut entry_set = vec![true; locals.vals.len()];
+    for arg_ast in func_ast.exprs.iter().flatten() {
+        if let Some(index) = locals.get_index(arg_ast) {
+            entry_set[index] = false;
+        }
+    }
+
+    let reachable = cfg.reachable_blocks();
+    let block_count = cfg.blocks.len();
+    let mut in_sets: Vec<UninitSet> = vec![vec![false; locals.vals.len()]; block_count];
+    let mut out_sets: Vec<UninitSet> = vec![vec![false; locals.vals.len()]; block_count];
+    in_sets[cfg.entry] = entry_set;
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in 0..block_count {
+            if !reachable[block] {
+                continue;
+            }
+            let mut set = in_sets[block].clone();
+            for &pred in &cfg.blocks[block].preds {
+                for (elem, pred_elem) in set.iter_mut().zip(out_sets[pred].iter()) {
+                    *elem |= *pred_elem;
+                }
+            }
+            in_sets[block] = set.clone();
+            let mut transfer = Transfer {
+                locals: &locals,
+                reads: vec![],
+            };
+            transfer.visit_block(&cfg.blocks[block].exprs, &mut set);
+            if set != out_sets[block] {
+                out_sets[block] = set;
+                changed = true;
+            }
+        }
+    }
+
+    let mut transfer = Transfer {
+        locals: &locals,
+        reads: vec![],
+    };
+    for block in 0..block_count {
+        if reachable[block] {
+            let mut set = in_sets[block].clone();
+            transfer.visit_block(&cfg.blocks[block].exprs, &mut set);
+        }
+    }
+
+    // 変数ごとにソースコード上で最初の読み出しのみを返す
+    let mut first_reads: HashMap<usize, TokenInfo> = HashMap::new();
+    for (index, info) in transfer.reads {
+        let first = first_reads.entry(index).or_insert(info);
+        if (info.line, info.pos) < (first.line, first.pos) {
+            *first = info;
+        }
+    }
+    let mut reads: Vec<(String, TokenInfo)> = first_reads
+        .into_iter()
+        .map(|(index, info)| (locals.vals[index].name.clone(), info))
+        .collect();
+    reads.sort_by_key(|(_, info)| (info.line, info.pos));
+    reads
+}
@@ -0,0 +1,161 @@
+use std::cell::Cell;
+use std::fmt;
+use std::process::exit;
+
+use crate::definition::variables::VariableWarning;
+use crate::token::token::TokenInfo;
+use crate::SOURCE_TXT;
+
+/// 警告オプション
+///
+/// member
+/// - unused_variable - 未使用のローカル変数を警告する(-Wunused-variable)
+/// - unused_parameter - 未使用の引数を警告する(-Wunused-parameter)
+/// - shadow - 外側の変数を隠す宣言を警告する(-Wshadow)
+/// - werror - 警告をエラーとして扱う(-Werror)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WarningOptions {
+    pub unused_variable: bool,
+    pub unused_parameter: bool,
+    pub shadow: bool,
+    pub werror: bool,
+}
+
+impl WarningOptions {
+    // "-W"で始まるオプションを解釈する. 不明なオプションの場合はErrを返す
+    // "-Wno-"で始まるオプションは対応する警告を無効にする(後に指定したオプションが優先される)
+    pub fn parse_option(&mut self, option: &str) -> Result<(), ()> {
+        let (name, enabled) = match option.strip_prefix("-Wno-") {
+            Some(name) => (name, false),
+            None => (option.strip_prefix("-W").ok_or(())?, true),
+        };
+        match name {
+            "unused-variable" => self.unused_variable = enabled,
+            "unused-parameter" => self.unused_parameter = enabled,
+            "shadow" => self.shadow = enabled,
+            "error" => self.werror = enabled,
+            _ => return Err(()),
+        }
+        Ok(())
+    }
+}
+
+// 警告オプションと出力した警告数はコンパイル全体で共有する
+thread_local! {
+    static WARNING_OPTIONS: Cell<WarningOptions> = Cell::new(WarningOptions::default());
+    static WARNING_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn set_warning_options(options: WarningOptions) {
+    WARNING_OPTIONS.with(|warning_options| warning_options.set(options));
+}
+
+fn get_warning_options() -> WarningOptions {
+    WARNING_OPTIONS.with(|warning_options| warning_options.get())
+}
+
+// -Werror指定時に警告を出していた場合はエラーとして終了する
+pub fn exit_if_warnings_are_errors() {
+    let count = WARNING_COUNT.with(|warning_count| warning_count.get());
+    if get_warning_options().werror && count > 0 {
+        eprintln!("error: {} warning(s) treated as errors", count);
+        exit(-1);
+    }
+}
+
+pub enum AnalysisWarning {
+    MissingReturn(String),
+    UnreachableCode,
+    Uninitialized(String),
+    UnusedVariable(String),
+    UnusedParameter(String),
+    Shadow(String),
+}
+
+impl fmt::Display for AnalysisWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnalysisWarning::MissingReturn(func_name) => {
+                write!(
+                    f,
+                    "control reaches end of non-void function '{}'",
+                    func_name
+                )
+            }
+            AnalysisWarning::UnreachableCode => {
+                write!(f, "will never be executed")
+            }
+            AnalysisWarning::Uninitialized(name) => {
+                write!(f, "'{}' may be used uninitialized", name)
+            }
+            AnalysisWarning::UnusedVariable(name) => {
+                write!(f, "unused variable '{}' [-Wunused-variable]", name)
+            }
+            AnalysisWarning::UnusedParameter(name) => {
+                write!(f, "unused parameter '{}' [-Wunused-parameter]", name)
+            }
+            AnalysisWarning::Shadow(name) => {
+                write!(
+                    f,
+                    "declaration of '{}' shadows a previous declaration [-Wshadow]",
+                    name
+                )
+            }
+        }
+    }
+}
+
+// 警告を出したトークンの行と位置を表示する
+// 警告ではコンパイルを中断しない(-Werror指定時は全ての警告を出した後に終了する)
+fn print_token_warning_info(warning: AnalysisWarning, info: &TokenInfo) {
+    let warning_line;
+    unsafe {
+        warning_line = &SOURCE_TXT[info.line];
+    }
+    eprintln!("{}", warning_line);
+    let mut warning_cur = " ".repeat(info.pos);
+    warning_cur.push('^');
+    eprintln!("{}", warning_cur);
+    let level = if get_warning_options().werror {
+        "error"
+    } else {
+        "warning"
+    };
+    eprintln!(
+        "line{}, pos{}, {}: {}",
+        info.line + 1,
+        info.pos + 1,
+        level,
+        warning
+    );
+    WARNING_COUNT.with(|warning_count| warning_count.set(warning_count.get() + 1));
+}
+
+pub fn output_missingreturn_warn(func_name: &str, info: &TokenInfo) {
+    print_token_warning_info(AnalysisWarning::MissingReturn(func_name.to_string()), info);
+}
+
+pub fn output_unreachablecode_warn(info: &TokenInfo) {
+    print_token_warning_info(AnalysisWarning::UnreachableCode, info);
+}
+
+pub fn output_uninitialized_warn(name: &str, info: &TokenInfo) {
+    print_token_warning_info(AnalysisWarning::Uninitialized(name.to_string()), info);
+}
+
+// 変数宣言の警告は対応するオプションが指定された場合のみ出す
+pub fn output_variable_warn(warning: &VariableWarning) {
+    let options = get_warning_options();
+    match warning {
+        VariableWarning::UnusedVariable(name, info) if options.unused_variable => {
+            print_token_warning_info(AnalysisWarning::UnusedVariable(name.clone()), info)
+        }
+        VariableWarning::UnusedParameter(name, info) if options.unused_parameter => {
+            print_token_warning_info(AnalysisWarning::UnusedParameter(name.clone()), info)
+        }
+        VariableWarning::Shadow(name, info) if options.shadow => {
+            print_token_warning_info(AnalysisWarning::Shadow(name.clone()), info)
+        }
+        _ => {}
+    }
+}
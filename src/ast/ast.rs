@@ -1,10 +1,11 @@
 use std::ops::Deref;
 
+use crate::analysis::warning::output_variable_warn;
 use crate::ast::controls::*;
 use crate::ast::declaration::*;
 use crate::ast::operations::*;
 use crate::definition::definitions::Definitions;
-use crate::definition::functions::Function;
+use crate::definition::linkage::{Linkage, StorageClass};
 use crate::definition::number::Number;
 use crate::definition::reservedwords::*;
 use crate::definition::symbols::*;
@@ -52,16 +53,22 @@ pub enum Control {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstKind {
-    FunctionImplementation((String, usize)),
+    FunctionImplementation((String, usize, Linkage)),
+    GlobalVariableDefinition(Variable),
     FuncionCall(String, Type),
+    IndirectCall(Type),      // 関数ポインタによる呼び出し, 関数型を持つ
+    FunctionAddress(String), // 関数指示子(関数ポインタに変換された関数名)
     Expressions,
     Operation(Operation),
     Control(Control),
     Variable(Variable),
     Address,
     Deref,
-    Index,
+    Member(usize), // 構造体メンバーのオフセット
+    Cast,
     ImmidiateInterger(Number),
+    VaStart(usize), // 可変長引数関数の名前付き引数の数
+    VaArg,
 }
 
 #[derive(Debug, Clone)]
@@ -134,14 +141,28 @@ impl Ast {
         }
     }
 
-    pub fn new_index_ast(info: TokenInfo, type_: Type, val: Ast, index: Ast) -> Ast {
+    pub fn new_cast_ast(info: TokenInfo, type_: Type, operand: Ast) -> Ast {
         Ast {
-            kind: AstKind::Index,
+            kind: AstKind::Cast,
             info,
             type_,
-            left: Some(Box::new(val)),
-            right: Some(Box::new(index)),
-            operand: None,
+            left: None,
+            right: None,
+            operand: Some(Box::new(operand)),
+            exprs: None,
+            context: None,
+            other: None,
+        }
+    }
+
+    pub fn new_member_ast(info: TokenInfo, type_: Type, offset: usize, operand: Ast) -> Ast {
+        Ast {
+            kind: AstKind::Member(offset),
+            info,
+            type_,
+            left: None,
+            right: None,
+            operand: Some(Box::new(operand)),
             exprs: None,
             context: None,
             other: None,
@@ -187,27 +208,45 @@ impl Ast {
         }
     }
 
+    // 可変長引数関数の場合はoperandにレジスタ退避領域の変数を持つ
+    #[allow(clippy::too_many_arguments)]
     pub fn new_function_implementation_ast(
         func_name: &str,
         info: TokenInfo,
         type_: Type,
         frame_size: usize,
+        linkage: Linkage,
         args_expr: Option<Vec<Ast>>,
+        reg_save_area: Option<Ast>,
         context: Ast,
     ) -> Ast {
         Ast {
-            kind: AstKind::FunctionImplementation((func_name.to_string(), frame_size)),
+            kind: AstKind::FunctionImplementation((func_name.to_string(), frame_size, linkage)),
             info,
             type_,
             left: None,
             right: None,
-            operand: None,
+            operand: reg_save_area.map(Box::new),
             exprs: args_expr,
             context: Some(Box::new(context)),
             other: None,
         }
     }
 
+    pub fn new_global_variable_definition_ast(val: Variable, info: TokenInfo, type_: Type) -> Ast {
+        Ast {
+            kind: AstKind::GlobalVariableDefinition(val),
+            info,
+            type_,
+            left: None,
+            right: None,
+            operand: None,
+            exprs: None,
+            context: None,
+            other: None,
+        }
+    }
+
     pub fn new_functioncall_ast(
         func_name: &str,
         info: TokenInfo,
@@ -228,6 +267,76 @@ impl Ast {
         }
     }
 
+    // 関数ポインタによる呼び出しはoperandに呼び出し先のアドレスを持つ
+    pub fn new_indirect_functioncall_ast(
+        info: TokenInfo,
+        functype: Type,
+        restype_: Type,
+        callee: Ast,
+        args: Option<Vec<Ast>>,
+    ) -> Ast {
+        Ast {
+            kind: AstKind::IndirectCall(functype),
+            info,
+            type_: restype_,
+            left: None,
+            right: None,
+            operand: Some(Box::new(callee)),
+            exprs: args,
+            context: None,
+            other: None,
+        }
+    }
+
+    pub fn new_function_address_ast(func_name: &str, info: TokenInfo, type_: Type) -> Ast {
+        Ast {
+            kind: AstKind::FunctionAddress(func_name.to_string()),
+            info,
+            type_,
+            left: None,
+            right: None,
+            operand: None,
+            exprs: None,
+            context: None,
+            other: None,
+        }
+    }
+
+    // va_startはoperandにva_list, rightにレジスタ退避領域を持つ
+    pub fn new_va_start_ast(
+        info: TokenInfo,
+        type_: Type,
+        named_arg_count: usize,
+        va_list: Ast,
+        reg_save_area: Ast,
+    ) -> Ast {
+        Ast {
+            kind: AstKind::VaStart(named_arg_count),
+            info,
+            type_,
+            left: None,
+            right: Some(Box::new(reg_save_area)),
+            operand: Some(Box::new(va_list)),
+            exprs: None,
+            context: None,
+            other: None,
+        }
+    }
+
+    pub fn new_va_arg_ast(info: TokenInfo, type_: Type, va_list: Ast) -> Ast {
+        Ast {
+            kind: AstKind::VaArg,
+            info,
+            type_,
+            left: None,
+            right: None,
+            operand: Some(Box::new(va_list)),
+            exprs: None,
+            context: None,
+            other: None,
+        }
+    }
+
     pub fn new_expressions_ast(
         info: TokenInfo,
         type_: Type,
@@ -281,6 +390,7 @@ pub fn ast_number(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 pub fn ast_variable(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     let (ident, info) = tokens.consume_identifier();
     if let Some(val) = definitions.get_variable(&ident) {
+        val.mark_used();
         let val_type = val.get_type();
         Ast::new_variable_ast(val, info, val_type)
     } else {
@@ -288,54 +398,62 @@ pub fn ast_variable(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     }
 }
 
-fn ast_index(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
-    tokens.consume_symbol(Symbol::LeftSquareBracket);
+// 配列アクセス
+// E1[E2]は*(E1 + E2)として扱うので, 配列型, ポインタ型の任意の式に添字を付けられる
+// 多次元配列a[i][j]は*(*(a + i) + j)となる
+pub fn ast_array_access(val_ast: Ast, tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let info = tokens.consume_symbol(Symbol::LeftSquareBracket);
     let index_ast = ast_formula(tokens, definitions);
-    if index_ast.type_.is_integer_type() {
-        // ここでlong型の値にする必要がある
-        tokens.consume_symbol(Symbol::RightSquareBracket);
-        index_ast
-    } else {
-        output_unindexiable_err(&index_ast.info)
+    tokens.consume_symbol(Symbol::RightSquareBracket);
+    let is_indexable = |ast: &Ast| ast.type_.is_array() || ast.type_.is_pointer();
+    if !is_indexable(&val_ast) && !is_indexable(&index_ast) {
+        output_unindexiable_err(&val_ast.info);
     }
+    new_subscript_ast(info, val_ast, index_ast, definitions)
 }
 
-// 配列アクセス
-pub fn ast_array_access(val_ast: Ast, tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
-    if val_ast.type_.is_array() {
-        let (_array_len, index_type) = val_ast.type_.array.as_ref().unwrap();
-        let index_type = index_type.deref().clone();
-        let index_ast = ast_index(tokens, definitions);
-        let mut array_access_ast = Ast::new_index_ast(val_ast.info, index_type, val_ast, index_ast);
-        // 2次元配列のindexingをできるようにする
-        while tokens.expect_symbol(Symbol::LeftSquareBracket) {
-            let (_array_len, index_type) = array_access_ast.type_.array.as_ref().unwrap();
-            let index_type = index_type.deref().clone();
-            let index_ast = ast_index(tokens, definitions);
-            array_access_ast = Ast::new_index_ast(
-                array_access_ast.info,
-                index_type,
-                array_access_ast,
-                index_ast,
-            );
+// 構造体メンバーアクセス
+// "->"はポインタの指す構造体を取得してから, "."と同様にメンバーにアクセスする
+fn ast_member_access(val_ast: Ast, tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let mut struct_ast = val_ast;
+    if tokens.expect_symbol(Symbol::Deref) {
+        let deref_info = tokens.consume_symbol(Symbol::Deref);
+        if let Some(pointer_type) = &struct_ast.type_.pointer {
+            let type_ = pointer_type.deref().clone();
+            struct_ast = Ast::new_deref_ast(deref_info, type_, struct_ast);
+        } else {
+            output_undereferensable_err(&deref_info);
         }
-        array_access_ast
     } else {
-        output_unindexiable_err(&val_ast.info);
+        tokens.consume_symbol(Symbol::Period);
+    }
+    let (member_name, member_info) = tokens.consume_identifier();
+    let struct_type = complete_struct_type(&struct_ast.type_, definitions);
+    if let Some((offset, member_type)) = struct_type.get_struct_member(&member_name) {
+        // 修飾された構造体のメンバーは同じ修飾を受ける
+        let member_type =
+            member_type.with_qualifiers(struct_type.is_const, struct_type.is_volatile);
+        Ast::new_member_ast(member_info, member_type, offset, struct_ast)
+    } else {
+        output_nomember_err(&member_info);
     }
 }
 
-// variable_op = variable | ( "."indet | -> indet | "[" formula "]")* | ("++" | "--" | "(" ")" )
+// postfix = ( "." ident | "->" ident | "[" formula "]" | "(" args ")" | "++" | "--" )*
 // val->val.val[10].val++ (primaryである必要)
 // val->val.val[10].val() (funcpointerである必要)
 // に対応できるようにする
-pub fn ast_variable_op(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
-    let mut val_ast = ast_variable(tokens, definitions);
+fn ast_postfix(primary_ast: Ast, tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let mut val_ast = primary_ast;
     loop {
         if tokens.expect_symbol(Symbol::LeftSquareBracket) {
             val_ast = ast_array_access(val_ast, tokens, definitions)
+        } else if tokens.expect_symbols(&[Symbol::Period, Symbol::Deref]) {
+            val_ast = ast_member_access(val_ast, tokens, definitions)
         } else if tokens.expect_symbols(&[Symbol::Increment, Symbol::Decrement]) {
             val_ast = ast_backward_increment(val_ast, tokens, definitions)
+        } else if tokens.expect_symbol(Symbol::LeftParenthesis) {
+            val_ast = ast_indirect_functioncall(val_ast, tokens, definitions)
         } else {
             break;
         }
@@ -343,11 +461,34 @@ pub fn ast_variable_op(tokens: &mut Tokens, definitions: &mut Definitions) -> As
     val_ast
 }
 
-// primary_op =  variable | functioncall
+// variable_op = variable postfix
+pub fn ast_variable_op(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let val_ast = ast_variable(tokens, definitions);
+    ast_postfix(val_ast, tokens, definitions)
+}
+
+// 関数名は呼び出し以外では関数ポインタに変換する
+fn ast_function_designator(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let (func_name, info) = tokens.consume_identifier();
+    let func_type = definitions.get_type(&func_name).unwrap();
+    Ast::new_function_address_ast(&func_name, info, Type::new_pointer(func_type))
+}
+
+// primary_op =  variable_op | (functioncall | function_designator) postfix | va_builtin
+// 関数と同名のローカル変数がある場合は変数を優先する
 fn ast_primary_op(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     if let TokenKind::Identifier(ident) = &tokens.get().unwrap().kind {
-        if let Some(_func) = definitions.get_function(ident) {
-            ast_functioncall(tokens, definitions)
+        if is_va_builtin(ident) && tokens.expect_next_symbol(Symbol::LeftParenthesis, 1) {
+            ast_va_builtin(tokens, definitions)
+        } else if definitions.get_variable(ident).is_none()
+            && definitions.get_function(ident).is_some()
+        {
+            let func_ast = if tokens.expect_next_symbol(Symbol::LeftParenthesis, 1) {
+                ast_functioncall(tokens, definitions)
+            } else {
+                ast_function_designator(tokens, definitions)
+            };
+            ast_postfix(func_ast, tokens, definitions)
         } else {
             ast_variable_op(tokens, definitions)
         }
@@ -356,10 +497,12 @@ fn ast_primary_op(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     }
 }
 
-// primary = num | primary_op | "(" formula ")" | | ("++" | "--") variable
+// primary = num postfix | primary_op | "(" formula ")" postfix | ("++" | "--") variable
 pub fn ast_primary(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     if tokens.expect_number() {
-        ast_number(tokens, definitions)
+        // 2[a] のように整数にも添字を付けられる
+        let num_ast = ast_number(tokens, definitions);
+        ast_postfix(num_ast, tokens, definitions)
     } else if tokens.expect_identifier() {
         ast_primary_op(tokens, definitions)
     } else if tokens.expect_symbol(Symbol::LeftParenthesis) {
@@ -367,7 +510,7 @@ pub fn ast_primary(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         tokens.consume_symbol(Symbol::LeftParenthesis);
         let formula_ast = ast_formula(tokens, definitions);
         tokens.consume_symbol(Symbol::RightParenthesis);
-        formula_ast
+        ast_postfix(formula_ast, tokens, definitions)
     } else if tokens.expect_symbols(&[Symbol::Increment, Symbol::Decrement]) {
         ast_forward_increment(tokens, definitions)
     } else {
@@ -413,8 +556,13 @@ fn ast_exprs(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
             output_unclosed_token_err(tokens);
         }
 
+        if tokens.expect_reserved(Reserved::StaticAssert) {
+            consume_static_assert(tokens, definitions);
+            continue;
+        }
+
         //ローカル変数宣言
-        if is_type_token(tokens, definitions) {
+        if is_declaration_token(tokens, definitions) {
             if let Some(initialize_ast) = local_val_declaration(tokens, definitions) {
                 exprs.push(initialize_ast);
             }
@@ -442,69 +590,17 @@ fn ast_exprs(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 
     // "}" の位置を複文の情報とする
     let exprs_info = tokens.consume_symbol(Symbol::RightCurlyBracket);
-    let exprs_type; // 複文が返す型情報
-    exprs_type = definitions.get_type("void").unwrap();
+    // 複文が返す型情報
+    let exprs_type = definitions.get_type("void").unwrap();
     Ast::new_expressions_ast(exprs_info, exprs_type, exprs, None)
 }
 
-// 関数の引数を取得
-// もし関数実装で引数名が与えられない場合はエラー
-fn get_func_args(
-    tokens: &mut Tokens,
-    definitions: &mut Definitions,
-) -> Option<(Vec<String>, Vec<Type>, Vec<TokenInfo>)> {
-    // 関数宣言か, 関数実装か判断する
-    let mut is_func_declaration = false;
-    let mut cur = 1;
-    while let Some(token) = tokens.get_next(cur) {
-        if token.expect_symbol(&Symbol::RightParenthesis) {
-            if let Some(token) = tokens.get_next(cur + 1) {
-                if token.expect_symbol(&Symbol::LeftCurlyBracket) {
-                    is_func_declaration = false;
-                }
-            }
-            break;
-        } else {
-            cur += 1;
-        }
-    }
-
-    let mut args_type: Vec<Type> = vec![];
-    let mut args_name: Vec<String> = vec![];
-    let mut args_info: Vec<TokenInfo> = vec![];
-
-    // consume "("
-    tokens.consume_symbol(Symbol::LeftParenthesis);
-    while !tokens.expect_symbol(Symbol::RightParenthesis) {
-        let (arg_type, arg_name, arg_tokeninfo) = cousume_type_token(tokens, definitions);
-
-        // 変数名なしかつ関数宣言でない
-        if arg_name.is_empty() && !is_func_declaration {
-            output_unexpected_token_err(tokens);
-        }
-
-        args_type.push(arg_type);
-        args_name.push(arg_name);
-        args_info.push(arg_tokeninfo);
-        if tokens.expect_symbol(Symbol::RightParenthesis) {
-            break;
-        }
-        tokens.consume_symbol(Symbol::Comma);
-    }
-
-    // consume ")"
-    tokens.consume_symbol(Symbol::RightParenthesis);
-    if args_info.is_empty() {
-        None
-    } else {
-        Some((args_name, args_type, args_info))
-    }
-}
-
+#[allow(clippy::too_many_arguments)]
 fn ast_funcution_implementaion(
     func_name: String,
     func_info: TokenInfo,
     func_type: Type,
+    linkage: Linkage,
     argnames: Option<Vec<String>>,
     args_info: Option<Vec<TokenInfo>>,
     tokens: &mut Tokens,
@@ -512,17 +608,27 @@ fn ast_funcution_implementaion(
 ) -> Ast {
     // 関数実装ASTを作成
     definitions.enter_function_implemetation(&func_name);
+    // 可変長引数関数はva_argで参照できるよう, 引数レジスタ6個分の退避領域をローカル変数として確保する
+    let mut reg_save_area: Option<Ast> = None;
+    if func_type.function.as_ref().unwrap().variadic {
+        let area_type = Type::new_array(6, definitions.get_type("long").unwrap());
+        let val = definitions
+            .declare_local_val(VA_REG_SAVE_AREA, area_type, func_info)
+            .unwrap();
+        // コンパイラが用意する変数なので未使用の警告対象外とする
+        val.mark_used();
+        let type_ = val.get_type();
+        reg_save_area = Some(Ast::new_variable_ast(val, func_info, type_));
+    }
     let mut args_expr: Option<Vec<Ast>> = None;
     // 引数がある場合
     if let Some(ref argtypes) = func_type.function.as_ref().unwrap().args {
         let mut expr_vec: Vec<Ast> = vec![];
-        for (arg_type, (argname, argtoken)) in argtypes.iter().zip(
-            argnames
-                .unwrap()
-                .into_iter()
-                .zip(args_info.unwrap().into_iter()),
-        ) {
-            if let Ok(val) = definitions.declare_local_val(&argname, arg_type.clone()) {
+        for (arg_type, (argname, argtoken)) in argtypes
+            .iter()
+            .zip(argnames.unwrap().into_iter().zip(args_info.unwrap()))
+        {
+            if let Ok(val) = definitions.declare_local_val(&argname, arg_type.clone(), argtoken) {
                 let type_ = val.get_type();
                 let ast = Ast::new_variable_ast(val, argtoken, type_);
                 expr_vec.push(ast);
@@ -535,76 +641,109 @@ fn ast_funcution_implementaion(
     let expfunc_context_ast = ast_exprs(tokens, definitions);
     let frame_size = definitions.get_local_val_frame_size();
     definitions.exit_function_implemetation();
+    for warning in definitions.take_variable_warnings() {
+        output_variable_warn(&warning);
+    }
     // 関数AST作成
     Ast::new_function_implementation_ast(
         &func_name,
         func_info,
         func_type,
         frame_size,
+        linkage,
         args_expr,
+        reg_save_area,
         expfunc_context_ast,
     )
 }
 
+// 関数宣言を行い, 関数実装の場合は関数実装ASTを返す
 fn ast_function(
     func_name: String,
     func_info: TokenInfo,
-    ret_type: Type,
+    func_type: Type,
+    func_params: FuncParams,
+    storage_class: StorageClass,
     tokens: &mut Tokens,
     definitions: &mut Definitions,
 ) -> Option<Ast> {
-    let args = get_func_args(tokens, definitions);
-    let mut arg_names = None;
-    let mut arg_types = None;
-    let mut arg_info = None;
-    if let Some(arg_vecs) = args {
-        arg_names = Some(arg_vecs.0);
-        arg_types = Some(arg_vecs.1);
-        arg_info = Some(arg_vecs.2);
-    }
-
-    let func_ret;
-    if ret_type == definitions.get_type("void").unwrap() {
-        func_ret = None;
-    } else {
-        func_ret = Some(ret_type);
+    let (func_type, linkage) = function_declaration(
+        &func_name,
+        &func_info,
+        &func_type,
+        storage_class,
+        definitions,
+    );
+    if !tokens.expect_symbol(Symbol::LeftCurlyBracket) {
+        return None;
     }
-
-    let func = Function::new(arg_types, func_ret);
-
-    if let Ok(func_type) = definitions.declare_function(&func_name, func) {
-        if tokens.expect_symbol(Symbol::SemiColon) {
-            tokens.consume_symbol(Symbol::SemiColon);
-            None
-        } else if tokens.expect_symbol(Symbol::LeftCurlyBracket) {
-            if !definitions.can_implement_function(&func_name) {
-                output_alreadyimplementedfunction_err(&func_info);
-            }
-            Some(ast_funcution_implementaion(
-                func_name,
-                func_info,
-                func_type,
-                arg_names,
-                arg_info,
-                tokens,
-                definitions,
-            ))
-        } else {
-            output_notsamefunction_err(&func_info);
-        }
-    } else {
-        output_unexpected_token_err(tokens);
+    if !definitions.can_implement_function(&func_name) {
+        output_alreadyimplementedfunction_err(&func_info);
     }
+    // 関数実装では引数名の省略はできない
+    if let Some(index) = func_params.names.iter().position(|name| name.is_empty()) {
+        unexpected_token_err(&func_params.infos[index]);
+    }
+    let (arg_names, arg_info) = if func_params.types.is_empty() {
+        (None, None)
+    } else {
+        (Some(func_params.names), Some(func_params.infos))
+    };
+    Some(ast_funcution_implementaion(
+        func_name,
+        func_info,
+        func_type,
+        linkage,
+        arg_names,
+        arg_info,
+        tokens,
+        definitions,
+    ))
 }
 
+// global = storage_class? declspec (declarator ("," declarator)*)? (";" | 関数実装)
 // グローバル変数定義, 関数宣言, 関数実装を行う
 fn ast_global(tokens: &mut Tokens, definitions: &mut Definitions) -> Option<Ast> {
-    let (type_, name, info) = cousume_type_token(tokens, definitions);
-    if tokens.expect_symbol(Symbol::LeftParenthesis) {
-        ast_function(name, info, type_, tokens, definitions)
-    } else {
-        output_unexpected_token_err(tokens);
+    if tokens.expect_reserved(Reserved::StaticAssert) {
+        consume_static_assert(tokens, definitions);
+        return None;
+    }
+    let storage_class = consume_storage_class(tokens);
+    let base_type = consume_declspec(tokens, definitions);
+    // 構造体型の宣言のみ
+    if base_type.is_struct() && tokens.expect_symbol(Symbol::SemiColon) {
+        tokens.consume_symbol(Symbol::SemiColon);
+        return None;
+    }
+    loop {
+        let (type_, name, info, func_params) =
+            consume_declarator(base_type.clone(), tokens, definitions);
+        if name.is_empty() {
+            output_unexpected_token_err(tokens);
+        }
+        if let Some(func_params) = func_params {
+            let func_ast = ast_function(
+                name,
+                info,
+                type_,
+                func_params,
+                storage_class,
+                tokens,
+                definitions,
+            );
+            if func_ast.is_some() {
+                return func_ast;
+            }
+        } else {
+            global_val_declaration(name, info, type_, storage_class, tokens, definitions);
+        }
+        if !tokens.expect_symbol(Symbol::Comma) {
+            break;
+        }
+        tokens.consume_symbol(Symbol::Comma);
     }
+    tokens.consume_symbol(Symbol::SemiColon);
+    None
 }
 
 pub fn make_asts(mut tokens: Tokens) -> Vec<Ast> {
@@ -615,5 +754,16 @@ pub fn make_asts(mut tokens: Tokens) -> Vec<Ast> {
             asts.push(func_ast);
         }
     }
+    // 仮定義を含め全ての宣言を読んだ後に, 静的記憶域期間を持つ変数の領域を確保する
+    // 変数定義はトークンと対応しないので, 最後のトークン位置を情報とする
+    if let Some(tail_token) = tokens.get_tail() {
+        let tail_info = tail_token.info;
+        for val in definitions.get_static_vals() {
+            let type_ = val.get_type();
+            asts.push(Ast::new_global_variable_definition_ast(
+                val, tail_info, type_,
+            ));
+        }
+    }
     asts
 }
@@ -0,0 +1,151 @@
+use crate::ast::ast::*;
+use crate::definition::number::Number;
+use crate::definition::types::Type;
+use crate::definition::variables::Variable;
+use crate::token::error::*;
+
+// 整数定数式の値を型の範囲に収める
+// 符号なし整数型は2^(サイズ*8)を法とした値に, 符号あり整数型は2の補数として解釈した値にする
+fn wrap_to_type(value: i128, type_: &Type) -> i128 {
+    if type_.is_bool() {
+        return (value != 0) as i128;
+    }
+    let bits = (type_.size * 8) as u32;
+    let modulo = 1i128 << bits;
+    let wrapped = value.rem_euclid(modulo);
+    if !type_.is_unsigned_integer() && wrapped >= modulo / 2 {
+        wrapped - modulo
+    } else {
+        wrapped
+    }
+}
+
+// 演算結果が型で表せるか確認する
+// 符号なし整数型の演算は桁あふれしても未定義動作ではないので, 型の範囲に収める
+fn check_overflow(value: i128, ast: &Ast) -> i128 {
+    let wrapped = wrap_to_type(value, &ast.type_);
+    if wrapped != value && !ast.type_.is_unsigned_integer() {
+        output_constantoverflow_err(&ast.info);
+    }
+    wrapped
+}
+
+fn eval_binary_operation(operation: &Operation, ast: &Ast) -> Option<i128> {
+    let left_ast = ast.left.as_ref().unwrap();
+    let right_ast = ast.right.as_ref().unwrap();
+    let left = eval_integer_constant(left_ast)?;
+    // 論理演算は左辺で結果が決まる場合は右辺を評価しない
+    match operation {
+        Operation::And if left == 0 => return Some(0),
+        Operation::Or if left != 0 => return Some(1),
+        _ => {}
+    }
+    let right = eval_integer_constant(right_ast)?;
+
+    let value = match operation {
+        Operation::Add => check_overflow(left + right, ast),
+        Operation::Sub => check_overflow(left - right, ast),
+        Operation::Mul => check_overflow(left * right, ast),
+        Operation::Div | Operation::Rem if right == 0 => output_divisionbyzero_err(&ast.info),
+        // 0方向への切り捨て(i128の除算と同じ)
+        Operation::Div => check_overflow(left / right, ast),
+        Operation::Rem => check_overflow(left % right, ast),
+        Operation::LeftShift | Operation::RightShift
+            if right < 0 || right >= (ast.type_.size * 8) as i128 =>
+        {
+            output_constantoverflow_err(&ast.info)
+        }
+        Operation::LeftShift if left < 0 => output_constantoverflow_err(&ast.info),
+        Operation::LeftShift => check_overflow(left << right, ast),
+        Operation::RightShift => left >> right,
+        Operation::BitAnd => wrap_to_type(left & right, &ast.type_),
+        Operation::BitOr => wrap_to_type(left | right, &ast.type_),
+        Operation::BitXor => wrap_to_type(left ^ right, &ast.type_),
+        Operation::Eq => (left == right) as i128,
+        Operation::NotEq => (left != right) as i128,
+        Operation::Lt => (left < right) as i128,
+        Operation::Le => (left <= right) as i128,
+        Operation::Gt => (left > right) as i128,
+        Operation::Ge => (left >= right) as i128,
+        Operation::And | Operation::Or => (right != 0) as i128,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// 整数定数式を評価する
+///
+/// 整数リテラル(sizeofを含む), キャスト, 単項・二項演算のみからなる式を定数式とし,
+/// 定数式でない場合はNoneを返す. 値は式の型で表した値(符号なし整数型は非負)とする
+/// 定数式中のゼロ除算, 符号あり整数型のオーバーフローはエラーとする
+pub fn eval_integer_constant(ast: &Ast) -> Option<i128> {
+    if !ast.type_.is_integer_type() {
+        return None;
+    }
+    match &ast.kind {
+        AstKind::ImmidiateInterger(Number::U64(num)) => {
+            Some(wrap_to_type(*num as i128, &ast.type_))
+        }
+        AstKind::Cast => {
+            let value = eval_integer_constant(ast.operand.as_ref().unwrap())?;
+            Some(wrap_to_type(value, &ast.type_))
+        }
+        AstKind::Operation(Operation::Not) => {
+            let value = eval_integer_constant(ast.operand.as_ref().unwrap())?;
+            Some((value == 0) as i128)
+        }
+        AstKind::Operation(Operation::BitNot) => {
+            let value = eval_integer_constant(ast.operand.as_ref().unwrap())?;
+            Some(wrap_to_type(!value, &ast.type_))
+        }
+        AstKind::Operation(operation) if ast.left.is_some() && ast.right.is_some() => {
+            eval_binary_operation(operation, ast)
+        }
+        _ => None,
+    }
+}
+
+// 整数定数式の値を8バイトのビット列として取得する
+pub fn get_integer_constant(ast: &Ast) -> Option<u64> {
+    eval_integer_constant(ast).map(|value| value as u64)
+}
+
+/// アドレス定数を評価する
+///
+/// 静的記憶域期間を持つ変数(その要素, メンバーを含む)のアドレスに整数定数式を加減算した式を
+/// アドレス定数とし, (ラベル, バイト単位のオフセット)を返す. アドレス定数でない場合はNoneを返す
+pub fn eval_address_constant(ast: &Ast) -> Option<(String, i64)> {
+    match &ast.kind {
+        // 配列は先頭要素へのポインタに変換される
+        AstKind::Variable(_) if ast.type_.is_array() => eval_lvalue_address(ast),
+        AstKind::Address => eval_lvalue_address(ast.operand.as_ref().unwrap()),
+        AstKind::Cast if ast.type_.is_pointer() => {
+            eval_address_constant(ast.operand.as_ref().unwrap())
+        }
+        AstKind::Operation(operation @ (Operation::Add | Operation::Sub))
+            if ast.type_.is_pointer() =>
+        {
+            let (label, offset) = eval_address_constant(ast.left.as_ref().unwrap())?;
+            let value = eval_integer_constant(ast.right.as_ref().unwrap())? as i64;
+            if *operation == Operation::Add {
+                Some((label, offset + value))
+            } else {
+                Some((label, offset - value))
+            }
+        }
+        _ => None,
+    }
+}
+
+// 左辺値のアドレスをアドレス定数として評価する
+fn eval_lvalue_address(ast: &Ast) -> Option<(String, i64)> {
+    match &ast.kind {
+        AstKind::Variable(Variable::GlobalVal(global_val)) => Some((global_val.label.clone(), 0)),
+        AstKind::Member(offset) => {
+            let (label, base) = eval_lvalue_address(ast.operand.as_ref().unwrap())?;
+            Some((label, base + *offset as i64))
+        }
+        AstKind::Deref => eval_address_constant(ast.operand.as_ref().unwrap()),
+        _ => None,
+    }
+}
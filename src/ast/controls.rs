@@ -1,14 +1,20 @@
 use crate::ast::ast::*;
+use crate::ast::declaration::cousume_type_token;
 use crate::ast::operations::*;
 use crate::definition::definitions::Definitions;
+use crate::definition::functions::Function;
+use crate::definition::number::Number;
 use crate::definition::reservedwords::*;
 use crate::definition::symbols::*;
-use crate::definition::types::Type;
 use crate::token::error::*;
 use crate::token::token::Tokens;
 
-// return = "return" assign
+// 可変長引数関数でレジスタ渡しの引数を退避するローカル変数名
+pub const VA_REG_SAVE_AREA: &str = "__va_reg_save_area";
+
+// return = "return" assign? ";"
 // return は returnする対象をもつ
+// 返り値は関数の返り値型に変換する. void関数は値を返せず, void以外の関数は値を省略できない
 pub fn ast_return(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     // consume "return"
     let current_funcname = definitions.get_curent_funcname().unwrap();
@@ -23,12 +29,18 @@ pub fn ast_return(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         let type_ = definitions.get_type("void").unwrap();
         Ast::new_control_ast(info, type_, Control::Return, None, None, None)
     } else {
-        let return_value = ast_assign(tokens, definitions);
-        let type_ = return_value.type_.clone();
-        if let Some(_res_type) = res_type {
-        } else {
-            output_different_returntype_err(&info)
+        let return_value = convert_array_to_pointer(ast_assign(tokens, definitions));
+        let res_type = match res_type {
+            Some(res_type) => res_type,
+            None => output_different_returntype_err(&info),
+        };
+        check_pointer_qualifiers(&res_type, &return_value.type_, &return_value.info);
+        if !can_assign_type(&res_type, &return_value) {
+            output_different_returntype_err(&return_value.info);
         }
+        let return_value = convert_assign_value(&res_type, return_value);
+        let return_value = convert_primitive_ast_type(return_value, &res_type.unqualified());
+        let type_ = res_type.unqualified();
         let context = vec![return_value];
         Ast::new_control_ast(info, type_, Control::Return, None, Some(context), None)
     }
@@ -81,7 +93,13 @@ pub fn ast_for(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     definitions.enter_new_local_scope();
 
     for i in 0..3 {
-        if tokens.expect_symbol(Symbol::SemiColon) {
+        // 更新式の後は")"が続く
+        let terminator = if i != 2 {
+            Symbol::SemiColon
+        } else {
+            Symbol::RightParenthesis
+        };
+        if tokens.expect_symbol(terminator) {
             for_contitions.push(None);
         } else {
             let inilaize_ast = ast_assign(tokens, definitions);
@@ -141,48 +159,50 @@ pub fn ast_break(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 }
 
 fn ast_function_args(
-    args_types: &Option<Vec<Type>>,
+    func: &Function,
     tokens: &mut Tokens,
     definitions: &mut Definitions,
 ) -> Option<Vec<Ast>> {
-    // 引数ありの場合
-    if let Some(arg_types) = args_types {
-        let mut args_ast: Vec<Ast> = vec![];
-        tokens.consume_symbol(Symbol::LeftParenthesis); // consume "("
+    let mut args_ast: Vec<Ast> = vec![];
+    tokens.consume_symbol(Symbol::LeftParenthesis); // consume "("
+
+    // 名前付き引数
+    if let Some(arg_types) = &func.args {
         for (count, arg_type) in arg_types.iter().enumerate() {
-            //let type_;
-            let ast;
-            if tokens.expect_number() {
-                ast = ast_number(tokens, definitions);
-            } else if tokens.expect_identifier() {
-                ast = ast_variable(tokens, definitions);
-            } else {
+            if count != 0 {
+                tokens.consume_symbol(Symbol::Comma);
+            }
+            if tokens.is_empty() {
                 output_unclosed_token_err(tokens);
             }
-            // 現在は不要だが方のチェックを行う
-            if ast.type_ != *arg_type {
+            let ast = convert_array_to_pointer(ast_formula(tokens, definitions));
+            check_pointer_qualifiers(arg_type, &ast.type_, &ast.info);
+            // 暗黙の型変換で渡せない型はエラー
+            if !can_assign_type(arg_type, &ast) {
                 output_defferenttype_err(tokens);
             }
-            args_ast.push(ast);
-            if count == arg_types.len() - 1 {
-                break;
-            } else {
-                tokens.consume_symbol(Symbol::Comma);
-            }
+            args_ast.push(convert_assign_value(arg_type, ast));
         }
+    }
 
-        if !tokens.expect_symbol(Symbol::RightParenthesis) {
-            output_incorrectarg_err(tokens);
-        }
-        tokens.consume(); // consume ")"
-        Some(args_ast)
-    } else {
-        tokens.consume_symbol(Symbol::LeftParenthesis); // consume "("
-        if !tokens.expect_symbol(Symbol::RightParenthesis) {
-            output_incorrectarg_err(tokens);
+    // 可変長引数部分は型チェックを行わず, 既定の実引数拡張を行う
+    if func.variadic {
+        while tokens.expect_symbol(Symbol::Comma) {
+            tokens.consume_symbol(Symbol::Comma);
+            let mut ast = convert_array_to_pointer(ast_formula(tokens, definitions));
+            promote_variadic_arg_type(&mut ast, definitions);
+            args_ast.push(ast);
         }
-        tokens.consume(); // consume ")"
+    }
+
+    if !tokens.expect_symbol(Symbol::RightParenthesis) {
+        output_incorrectarg_err(tokens);
+    }
+    tokens.consume(); // consume ")"
+    if args_ast.is_empty() {
         None
+    } else {
+        Some(args_ast)
     }
 }
 
@@ -199,9 +219,118 @@ pub fn ast_functioncall(tokens: &mut Tokens, definitions: &mut Definitions) -> A
             ret_type = definitions.get_type("void").unwrap();
         }
         // 引数を設定
-        let args = ast_function_args(&func.args, tokens, definitions);
+        let args = ast_function_args(&func, tokens, definitions);
         Ast::new_functioncall_ast(&funcname, info, type_, ret_type, args)
     } else {
         output_undefinedfunction_err(&info);
     }
 }
+
+// indirect_functioncall = formula "(" args ")"
+// 関数ポインタの指す関数を呼び出す
+pub fn ast_indirect_functioncall(
+    callee_ast: Ast,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> Ast {
+    let func_type = callee_ast
+        .type_
+        .pointer
+        .as_deref()
+        .filter(|pointer_type| pointer_type.function.is_some())
+        .cloned();
+    if let Some(func_type) = func_type {
+        let func = func_type.function.as_deref().unwrap();
+        let ret_type = if let Some(ret) = &func.ret {
+            ret.clone()
+        } else {
+            definitions.get_type("void").unwrap()
+        };
+        let args = ast_function_args(func, tokens, definitions);
+        let info = callee_ast.info;
+        Ast::new_indirect_functioncall_ast(info, func_type.clone(), ret_type, callee_ast, args)
+    } else {
+        output_unexecutable_err(&callee_ast.info);
+    }
+}
+
+pub fn is_va_builtin(ident: &str) -> bool {
+    matches!(ident, "va_start" | "va_arg" | "va_end")
+}
+
+// va_list型の変数を取得
+fn ast_va_list(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let va_list_ast = ast_variable(tokens, definitions);
+    if !va_list_ast.type_.is_va_list() {
+        output_unexecutable_err(&va_list_ast.info);
+    }
+    va_list_ast
+}
+
+// va_start = "va_start" "(" va_list "," ident ")"
+// 現在の関数の名前付き引数の数から, va_listの初期値を決定する
+fn ast_va_start(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let (_ident, info) = tokens.consume_identifier();
+    let current_funcname = definitions.get_curent_funcname().unwrap();
+    let current_func = definitions.get_function(current_funcname).unwrap();
+    if !current_func.variadic {
+        output_notvariadicfunction_err(&info);
+    }
+    tokens.consume_symbol(Symbol::LeftParenthesis);
+    let va_list_ast = ast_va_list(tokens, definitions);
+    tokens.consume_symbol(Symbol::Comma);
+    // 最後の名前付き引数はABI上参照しないので名前のみ確認する
+    ast_variable(tokens, definitions);
+    tokens.consume_symbol(Symbol::RightParenthesis);
+
+    let save_area = definitions.get_variable(VA_REG_SAVE_AREA).unwrap();
+    let save_area_type = save_area.get_type();
+    let save_area_ast = Ast::new_variable_ast(save_area, info, save_area_type);
+    let type_ = definitions.get_type("void").unwrap();
+    Ast::new_va_start_ast(
+        info,
+        type_,
+        current_func.get_arg_count(),
+        va_list_ast,
+        save_area_ast,
+    )
+}
+
+// va_arg = "va_arg" "(" va_list "," type ")"
+// 可変長引数は既定の実引数拡張によりint以上の整数型かポインタ型となるので,
+// 4バイト, 8バイトの整数型とポインタ型のみ取り出せる
+// 引数は8バイトの領域に置かれ, 4バイトの整数型は領域の下位4バイトを型に合わせて拡張して読み出す
+fn ast_va_arg(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let (_ident, info) = tokens.consume_identifier();
+    tokens.consume_symbol(Symbol::LeftParenthesis);
+    let va_list_ast = ast_va_list(tokens, definitions);
+    tokens.consume_symbol(Symbol::Comma);
+    let (type_, _name, type_info) = cousume_type_token(tokens, definitions);
+    let is_promoted_integer = type_.is_integer_type() && (type_.size == 4 || type_.size == 8);
+    if !(is_promoted_integer || type_.is_pointer()) {
+        output_unexecutable_err(&type_info);
+    }
+    tokens.consume_symbol(Symbol::RightParenthesis);
+    Ast::new_va_arg_ast(info, type_, va_list_ast)
+}
+
+// va_end = "va_end" "(" va_list ")"
+// System V ABIでは後処理は不要なので何もしない
+fn ast_va_end(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let (_ident, info) = tokens.consume_identifier();
+    tokens.consume_symbol(Symbol::LeftParenthesis);
+    ast_va_list(tokens, definitions);
+    tokens.consume_symbol(Symbol::RightParenthesis);
+    let type_ = definitions.get_type("long").unwrap();
+    Ast::new_integer_ast(Number::U64(0), info, type_)
+}
+
+pub fn ast_va_builtin(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let ident = tokens.get().unwrap().get_identifier().unwrap().clone();
+    match ident.as_str() {
+        "va_start" => ast_va_start(tokens, definitions),
+        "va_arg" => ast_va_arg(tokens, definitions),
+        "va_end" => ast_va_end(tokens, definitions),
+        _ => unreachable!(),
+    }
+}
@@ -1,102 +1,597 @@
-use super::ast::{Ast, Operation};
+use std::ops::Deref;
+
+use super::ast::Ast;
+use super::constant::eval_integer_constant;
+use super::initializer::{ast_initializer, make_local_initialize_ast, make_static_data};
 use super::operations::ast_formula;
 use crate::definition::definitions::Definitions;
+use crate::definition::functions::Function;
+use crate::definition::linkage::{Linkage, LinkageError, StorageClass};
+use crate::definition::reservedwords::{get_primitivetype_reserved_words, Reserved};
 use crate::definition::symbols::Symbol;
 use crate::definition::types::Type;
+use crate::definition::variables::StaticData;
 use crate::token::error::*;
 use crate::token::token::{TokenInfo, TokenKind, Tokens};
 
+// static_assert = "_Static_assert" "(" formula ("," 文字列)? ")" ";"
+// 整数定数式が0の場合はメッセージを表示してエラーとする
+pub fn consume_static_assert(tokens: &mut Tokens, definitions: &mut Definitions) {
+    let info = tokens.consume_reserved(Reserved::StaticAssert);
+    tokens.consume_symbol(Symbol::LeftParenthesis);
+    let condition_ast = ast_formula(tokens, definitions);
+    let message = if tokens.expect_symbol(Symbol::Comma) {
+        tokens.consume_symbol(Symbol::Comma);
+        // 引用符を除いた文字列をメッセージとする
+        let (rawstring, _info) = tokens.consume_rawstring();
+        rawstring.trim_matches('"').to_string()
+    } else {
+        String::new()
+    };
+    tokens.consume_symbol(Symbol::RightParenthesis);
+    tokens.consume_symbol(Symbol::SemiColon);
+    match eval_integer_constant(&condition_ast) {
+        Some(0) => output_staticassertfailed_err(&message, &info),
+        Some(_) => {}
+        None => output_notintegerconstant_err(&condition_ast.info),
+    }
+}
+
+pub fn is_storage_class_token(tokens: &Tokens) -> bool {
+    tokens.expect_reserved(Reserved::Static) || tokens.expect_reserved(Reserved::Extern)
+}
+
+// 宣言の開始トークン(記憶域クラス指定子か型)か
+pub fn is_declaration_token(tokens: &mut Tokens, definitions: &mut Definitions) -> bool {
+    is_storage_class_token(tokens) || is_type_token(tokens, definitions)
+}
+
+// 記憶域クラス指定子を読む
+pub fn consume_storage_class(tokens: &mut Tokens) -> StorageClass {
+    if tokens.expect_reserved(Reserved::Static) {
+        tokens.consume_reserved(Reserved::Static);
+        StorageClass::Static
+    } else if tokens.expect_reserved(Reserved::Extern) {
+        tokens.consume_reserved(Reserved::Extern);
+        StorageClass::Extern
+    } else {
+        StorageClass::Auto
+    }
+}
+
+pub fn output_linkage_err(err: LinkageError, info: &TokenInfo) -> ! {
+    match err {
+        LinkageError::ConflictingLinkage => output_conflictinglinkage_err(info),
+        LinkageError::ConflictingType => output_conflictingtype_err(info),
+        LinkageError::Redefinition => output_redefinition_err(info),
+    }
+}
+
 pub fn is_type_token(tokens: &mut Tokens, definitions: &mut Definitions) -> bool {
-    // 現在はプリミティブ型のみ対応
-    if tokens.expect_primitivetype() {
-        true
-    } else if tokens.expect_identifier() {
-        let token = tokens.get().unwrap();
-        if let TokenKind::Identifier(name) = &token.kind {
-            matches!(definitions.get_type(name), Some(_))
-        } else {
-            false
+    is_type_token_at(tokens, definitions, 0)
+}
+
+// 現在位置からstep個先のトークンが型名の開始トークンか
+pub fn is_type_token_at(tokens: &Tokens, definitions: &Definitions, step: usize) -> bool {
+    // 現在はプリミティブ型, 構造体型, 定義済みの型名のみ対応
+    if let Some(token) = tokens.get_next(step) {
+        match &token.kind {
+            TokenKind::Reserved(Reserved::Struct | Reserved::Const | Reserved::Volatile) => true,
+            TokenKind::Reserved(_) => token.expect_primitivetype(),
+            // 関数名も型情報に登録されているので, 関数型は除く
+            TokenKind::Identifier(name) => definitions
+                .get_type(name)
+                .is_some_and(|type_| type_.function.is_none()),
+            _ => false,
         }
     } else {
         false
     }
 }
 
-// 型, 変数名, 変数名トークン位置を返す
-// 関数宣言のみ変数名指定が不要なので, その場合の変数名は空文字列, トークン位置は変数名が期待される位置の直前とする
-// (関数宣言時のトークン位置は使用しないので問題ない)
-pub fn cousume_type_token(
-    tokens: &mut Tokens,
-    definitions: &mut Definitions,
-) -> (Type, String, TokenInfo) {
-    // 現在はプリミティブ型のみ対応
-    let mut type_: Type;
-    if let Ok(primitive_type) = tokens.get_primitivetype() {
-        type_ = definitions.get_primitive_type(&primitive_type);
+// 構造体型の型名
+pub fn get_struct_type_name(tag: &str) -> String {
+    format!("struct {}", tag)
+}
+
+// 不完全型(自己参照用に宣言中の構造体など)の場合は定義済みの構造体型を取得する
+pub fn complete_struct_type(type_: &Type, definitions: &Definitions) -> Type {
+    match &type_.struct_name {
+        Some(tag) if !tag.is_empty() => definitions
+            .get_type(&get_struct_type_name(tag))
+            .map(|struct_type| struct_type.with_qualifiers(type_.is_const, type_.is_volatile))
+            .unwrap_or_else(|| type_.clone()),
+        _ => type_.clone(),
+    }
+}
+
+// 定義されていないタグの構造体型(不完全型)か
+pub fn is_incomplete_struct_type(type_: &Type, definitions: &Definitions) -> bool {
+    match &type_.struct_name {
+        Some(tag) if !tag.is_empty() => definitions.get_type(&get_struct_type_name(tag)).is_none(),
+        _ => false,
+    }
+}
+
+// struct_type = "struct" ident? "{" (type valname ";")* "}" | "struct" ident
+// 定義中の構造体を指すポインタのため, 未定義のタグはメンバーを持たない不完全型とする
+fn consume_struct_type(tokens: &mut Tokens, definitions: &mut Definitions) -> Type {
+    tokens.consume_reserved(Reserved::Struct);
+    let mut tag = None;
+    if tokens.expect_identifier() {
+        tag = Some(tokens.consume_identifier());
+    }
+    if !tokens.expect_symbol(Symbol::LeftCurlyBracket) {
+        if let Some((tag, _info)) = tag {
+            let type_name = get_struct_type_name(&tag);
+            return definitions
+                .get_type(&type_name)
+                .unwrap_or_else(|| Type::new_stuct(&tag, vec![]));
+        } else {
+            output_unexpected_token_err(tokens);
+        }
+    }
+
+    // メンバー宣言
+    tokens.consume_symbol(Symbol::LeftCurlyBracket);
+    let mut members: Vec<(String, Type)> = vec![];
+    while !tokens.expect_symbol(Symbol::RightCurlyBracket) {
+        let base_type = consume_declspec(tokens, definitions);
+        loop {
+            let (member_type, member_name, member_info, _params) =
+                consume_declarator(base_type.clone(), tokens, definitions);
+            if member_name.is_empty() {
+                output_unexpected_token_err(tokens);
+            }
+            if members.iter().any(|(name, _type)| *name == member_name) {
+                output_alreadydeclared_variable_err(&member_info);
+            }
+            members.push((member_name, member_type));
+            if !tokens.expect_symbol(Symbol::Comma) {
+                break;
+            }
+            tokens.consume_symbol(Symbol::Comma);
+        }
+        tokens.consume_symbol(Symbol::SemiColon);
+    }
+    tokens.consume_symbol(Symbol::RightCurlyBracket);
+
+    let members = members
+        .iter()
+        .map(|(name, type_)| (name.as_str(), type_.clone()))
+        .collect();
+    if let Some((tag, info)) = tag {
+        let type_ = Type::new_stuct(&tag, members);
+        if definitions
+            .define_type(&get_struct_type_name(&tag), type_.clone())
+            .is_err()
+        {
+            output_redefinition_err(&info);
+        }
+        type_
     } else {
-        output_unexpected_token_err(tokens);
+        Type::new_stuct("", members)
+    }
+}
+
+// qualifiers = ("const" | "volatile")*
+// (const修飾, volatile修飾)を返す
+fn consume_qualifiers(tokens: &mut Tokens) -> (bool, bool) {
+    let mut is_const = false;
+    let mut is_volatile = false;
+    loop {
+        if tokens.expect_reserved(Reserved::Const) {
+            tokens.consume_reserved(Reserved::Const);
+            is_const = true;
+        } else if tokens.expect_reserved(Reserved::Volatile) {
+            tokens.consume_reserved(Reserved::Volatile);
+            is_volatile = true;
+        } else {
+            return (is_const, is_volatile);
+        }
     }
-    // ポインター型
+}
+
+// declspec = (qualifiers | primitive_type_word)* (struct_type | 定義済みの型名)? (qualifiers | primitive_type_word)*
+// 宣言の基本となる型を読む
+// 修飾子は型指定子の前後や間のどこに何度書いてもよい (例: "const unsigned const int")
+pub fn consume_declspec(tokens: &mut Tokens, definitions: &mut Definitions) -> Type {
+    let mut is_const = false;
+    let mut is_volatile = false;
+    // プリミティブ型の型指定子の並びと, 最初の型指定子の位置
+    let mut words: Vec<Reserved> = vec![];
+    let mut words_info: Option<TokenInfo> = None;
+    // 構造体型, 定義済みの型名(va_list等)
+    let mut named_type: Option<Type> = None;
     loop {
-        if tokens.expect_symbol(Symbol::Mul) {
-            tokens.consume_symbol(Symbol::Mul);
-            type_ = Type::new_pointer(type_);
+        let (qualified_const, qualified_volatile) = consume_qualifiers(tokens);
+        is_const |= qualified_const;
+        is_volatile |= qualified_volatile;
+        let no_specifier = words.is_empty() && named_type.is_none();
+        if named_type.is_none() && tokens.expect_primitivetype() {
+            let word = tokens.get().unwrap().get_reserved().unwrap().clone();
+            let info = tokens.consume();
+            words_info.get_or_insert(info);
+            words.push(word);
+        } else if no_specifier && tokens.expect_reserved(Reserved::Struct) {
+            named_type = Some(consume_struct_type(tokens, definitions));
+        } else if no_specifier && is_type_token(tokens, definitions) {
+            let (type_name, _info) = tokens.consume_identifier();
+            named_type = definitions.get_type(&type_name);
         } else {
             break;
         }
     }
-    // 変数名を取得
-    let valname: String;
-    let info: TokenInfo;
-    if tokens.expect_identifier() {
-        let tmp = tokens.consume_identifier();
-        valname = tmp.0;
-        info = tmp.1;
+    let type_ = if let Some(type_) = named_type {
+        type_
+    } else if let Some(info) = words_info {
+        match get_primitivetype_reserved_words(&words) {
+            Ok(primitive_type) => definitions.get_primitive_type(&primitive_type),
+            Err(_) => output_invalidtypespecifier_err(&info),
+        }
     } else {
-        valname = "".to_string();
-        info = tokens.get_prev(1).unwrap().info;
+        output_unexpected_token_err(tokens);
+    };
+    type_.with_qualifiers(is_const, is_volatile)
+}
+
+/// 関数宣言子の引数
+///
+/// 引数名が省略された場合は空文字列, トークン位置は引数名が期待される位置の直前とする
+pub struct FuncParams {
+    pub names: Vec<String>,
+    pub types: Vec<Type>,
+    pub infos: Vec<TokenInfo>,
+    pub variadic: bool,
+}
+
+// 宣言子による型の導出
+enum Derivation {
+    Pointer(bool, bool), // ポインタ自体の(const修飾, volatile修飾)
+    Array(usize),
+    Function(FuncParams),
+}
+
+// params = "(" ("void" | param ("," param)* ("," "...")?)? ")"
+// 配列型, 関数型の引数はポインタ型として扱う
+pub fn consume_func_params(tokens: &mut Tokens, definitions: &mut Definitions) -> FuncParams {
+    let mut params = FuncParams {
+        names: vec![],
+        types: vec![],
+        infos: vec![],
+        variadic: false,
+    };
+    tokens.consume_symbol(Symbol::LeftParenthesis);
+    // (void)は引数なし
+    if tokens.expect_reserved(Reserved::Void)
+        && tokens.expect_next_symbol(Symbol::RightParenthesis, 1)
+    {
+        tokens.consume_reserved(Reserved::Void);
     }
+    while !tokens.expect_symbol(Symbol::RightParenthesis) {
+        // "..."は1つ以上の引数の後に最後の引数としてのみ書ける
+        if tokens.expect_symbol(Symbol::Ellipsis) {
+            if params.types.is_empty() {
+                output_unexpected_token_err(tokens);
+            }
+            tokens.consume_symbol(Symbol::Ellipsis);
+            params.variadic = true;
+            break;
+        }
 
+        let base_type = consume_declspec(tokens, definitions);
+        let (mut arg_type, arg_name, arg_info, _params) =
+            consume_declarator(base_type, tokens, definitions);
+        if let Some((_count, elem_type)) = &arg_type.array {
+            arg_type = Type::new_pointer(elem_type.deref().clone());
+        } else if arg_type.function.is_some() {
+            arg_type = Type::new_pointer(arg_type);
+        }
+        params.names.push(arg_name);
+        params.types.push(arg_type);
+        params.infos.push(arg_info);
+        if tokens.expect_symbol(Symbol::RightParenthesis) {
+            break;
+        }
+        tokens.consume_symbol(Symbol::Comma);
+    }
+    tokens.consume_symbol(Symbol::RightParenthesis);
+    params
+}
+
+// type_suffix = ("[" formula? "]")* | params
+// 接尾辞は後ろのものから型に適用するので, 適用順に返す
+fn consume_type_suffix(tokens: &mut Tokens, definitions: &mut Definitions) -> Vec<Derivation> {
+    let mut suffixes: Vec<Derivation> = vec![];
+    if tokens.expect_symbol(Symbol::LeftParenthesis) {
+        let params = consume_func_params(tokens, definitions);
+        suffixes.push(Derivation::Function(params));
+    }
     // 配列型か判定 n次元配列に対応するためループ
-    let mut array_size_vec: Vec<usize> = vec![];
     while tokens.expect_symbol(Symbol::LeftSquareBracket) {
         tokens.consume_symbol(Symbol::LeftSquareBracket);
-        let (elem_num, info) = tokens.consume_integer();
-        if let Ok(elem_count) = elem_num.get_usize_value() {
-            array_size_vec.push(elem_count);
-        } else {
-            output_notinteger_err(&info);
+        // 要素数を省略した配列は要素数0とし, 初期化子から要素数を決める
+        if tokens.expect_symbol(Symbol::RightSquareBracket) {
+            suffixes.push(Derivation::Array(0));
+            tokens.consume_symbol(Symbol::RightSquareBracket);
+            continue;
+        }
+        // 要素数は正の整数定数式
+        let elem_num_ast = ast_formula(tokens, definitions);
+        match eval_integer_constant(&elem_num_ast) {
+            Some(elem_count) if elem_count > 0 => {
+                suffixes.push(Derivation::Array(elem_count as usize))
+            }
+            Some(_) => output_invalidarraysize_err(&elem_num_ast.info),
+            None => output_notintegerconstant_err(&elem_num_ast.info),
         }
         tokens.consume_symbol(Symbol::RightSquareBracket);
     }
+    suffixes.reverse();
+    suffixes
+}
 
-    for elem_count in array_size_vec.iter().rev() {
-        type_ = Type::new_array(*elem_count, type_);
+// "("の後が宣言子(関数の引数リストではない)か
+fn is_nested_declarator(tokens: &Tokens, definitions: &Definitions) -> bool {
+    if !tokens.expect_symbol(Symbol::LeftParenthesis) {
+        return false;
     }
-    (type_, valname, info)
+    if tokens.expect_next_symbols(&[Symbol::Mul, Symbol::LeftParenthesis], 1) {
+        return true;
+    }
+    tokens
+        .get_next(1)
+        .is_some_and(|token| token.expect_identifier())
+        && !is_type_token_at(tokens, definitions, 1)
 }
 
-pub fn local_val_declaration(tokens: &mut Tokens, definitions: &mut Definitions) -> Option<Ast> {
+// declarator = ("*" qualifiers)* ("(" declarator ")" | ident?) type_suffix
+// long (*fp)(long) のように括弧内の宣言子は, 外側の接尾辞を適用した型に対して適用する
+// そのため括弧内の導出を外側の導出の後に並べる
+fn consume_derivations(
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> (Vec<Derivation>, String, TokenInfo) {
+    let mut derivations: Vec<Derivation> = vec![];
+    while tokens.expect_symbol(Symbol::Mul) {
+        tokens.consume_symbol(Symbol::Mul);
+        let (is_const, is_volatile) = consume_qualifiers(tokens);
+        derivations.push(Derivation::Pointer(is_const, is_volatile));
+    }
+
+    let mut inner_derivations: Vec<Derivation> = vec![];
+    let name: String;
+    let info: TokenInfo;
+    if is_nested_declarator(tokens, definitions) {
+        tokens.consume_symbol(Symbol::LeftParenthesis);
+        let inner = consume_derivations(tokens, definitions);
+        tokens.consume_symbol(Symbol::RightParenthesis);
+        inner_derivations = inner.0;
+        name = inner.1;
+        info = inner.2;
+    } else if tokens.expect_identifier() {
+        (name, info) = tokens.consume_identifier();
+    } else {
+        // 抽象宣言子
+        name = "".to_string();
+        info = tokens.get_prev(1).unwrap().info;
+    }
+
+    derivations.extend(consume_type_suffix(tokens, definitions));
+    derivations.extend(inner_derivations);
+    (derivations, name, info)
+}
+
+// 型, 変数名, 変数名トークン位置と, 関数型の場合はその引数を返す
+// 関数宣言の引数や型名では変数名指定が不要なので, その場合の変数名は空文字列,
+// トークン位置は変数名が期待される位置の直前とする
+pub fn consume_declarator(
+    base_type: Type,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> (Type, String, TokenInfo, Option<FuncParams>) {
+    let (derivations, name, info) = consume_derivations(tokens, definitions);
+    let mut type_ = base_type;
+    let mut func_params = None;
+    for derivation in derivations {
+        func_params = None;
+        match derivation {
+            Derivation::Pointer(is_const, is_volatile) => {
+                type_ = Type::new_pointer(type_).with_qualifiers(is_const, is_volatile)
+            }
+            Derivation::Array(count) => type_ = Type::new_array(count, type_),
+            Derivation::Function(params) => {
+                let args = if params.types.is_empty() {
+                    None
+                } else {
+                    Some(params.types.clone())
+                };
+                let ret = if type_ == definitions.get_type("void").unwrap() {
+                    None
+                } else {
+                    Some(type_)
+                };
+                type_ = Type::new_fucntion(Function::new(args, ret, params.variadic));
+                func_params = Some(params);
+            }
+        }
+    }
+    (type_, name, info, func_params)
+}
+
+// 型, 変数名, 変数名トークン位置を返す
+pub fn cousume_type_token(
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> (Type, String, TokenInfo) {
+    let base_type = consume_declspec(tokens, definitions);
+    let (type_, name, info, _params) = consume_declarator(base_type, tokens, definitions);
+    (type_, name, info)
+}
+
+// type_name = declspec abstract_declarator
+// キャストなどで使用する, 変数名を持たない型名を読む
+pub fn consume_type_name(tokens: &mut Tokens, definitions: &mut Definitions) -> Type {
     let (type_, name, info) = cousume_type_token(tokens, definitions);
-    let defined_val = definitions.declare_local_val(&name, type_.clone());
-    if let Ok(val) = defined_val {
-        if tokens.expect_symbol(Symbol::Assign) {
-            let assgin_info = tokens.consume_symbol(Symbol::Assign);
-            let initial_value_ast = ast_formula(tokens, definitions);
-            let val_ast = Ast::new_variable_ast(val, info, type_.clone());
-            tokens.consume_symbol(Symbol::SemiColon);
-            Some(Ast::new_binary_operation_ast(
-                Operation::Assign,
-                assgin_info,
-                type_,
+    if !name.is_empty() {
+        unexpected_token_err(&info);
+    }
+    type_
+}
+
+// 関数を宣言し, 登録された関数型とリンケージを返す
+pub fn function_declaration(
+    name: &str,
+    info: &TokenInfo,
+    type_: &Type,
+    storage_class: StorageClass,
+    definitions: &mut Definitions,
+) -> (Type, Linkage) {
+    // 型名と同じ名前の関数は宣言できない
+    if let Some(defined) = definitions.get_type(name) {
+        if defined.function.is_none() {
+            output_redefinition_err(info);
+        }
+    }
+    let function = type_.function.as_deref().unwrap().clone();
+    let func_type = if let Ok(func_type) = definitions.declare_function(name, function) {
+        func_type
+    } else {
+        output_notsamefunction_err(info);
+    };
+    match definitions.declare_function_linkage(name, storage_class) {
+        Ok(linkage) => (func_type, linkage),
+        Err(err) => output_linkage_err(err, info),
+    }
+}
+
+// 静的記憶域期間を持つ変数の初期化子を読む
+// 初期化子によって型(配列の要素数)が決まる場合があるので, 型も返す
+fn get_static_initial_data(
+    type_: Type,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> (Type, Option<Vec<StaticData>>) {
+    if !tokens.expect_symbol(Symbol::Assign) {
+        return (type_, None);
+    }
+    tokens.consume_symbol(Symbol::Assign);
+    let (type_, initializer) = ast_initializer(type_, tokens, definitions);
+    let init = make_static_data(&initializer);
+    (type_, Some(init))
+}
+
+// グローバル変数宣言
+// 領域の確保は全ての宣言を読んだ後に行うので, ASTは作成しない
+pub fn global_val_declaration(
+    name: String,
+    info: TokenInfo,
+    type_: Type,
+    storage_class: StorageClass,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) {
+    if name.is_empty() {
+        output_unexpected_token_err(tokens);
+    }
+    let (type_, init) = get_static_initial_data(type_, tokens, definitions);
+    if let Err(err) = definitions.declare_global_val(&name, type_, storage_class, init) {
+        output_linkage_err(err, &info);
+    }
+}
+
+// ブロック内のstatic, extern宣言
+// 初期化は実行時に行わないのでASTは作成しない
+fn local_static_val_declaration(
+    name: String,
+    info: TokenInfo,
+    type_: Type,
+    storage_class: StorageClass,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) {
+    if storage_class == StorageClass::Static {
+        let (type_, init) = get_static_initial_data(type_, tokens, definitions);
+        if definitions
+            .declare_static_local_val(&name, type_, init)
+            .is_err()
+        {
+            output_alreadydeclared_variable_err(&info);
+        }
+    } else if let Err(err) = definitions.declare_extern_local_val(&name, type_) {
+        output_linkage_err(err, &info);
+    }
+}
+
+// ローカル変数宣言
+// 初期化子がある場合は初期化を行うASTを返す
+fn local_auto_val_declaration(
+    name: String,
+    info: TokenInfo,
+    type_: Type,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> Option<Ast> {
+    if tokens.expect_symbol(Symbol::Assign) {
+        // 初期化子によって配列の要素数が決まるので, 変数宣言は初期化子を読んだ後に行う
+        let assgin_info = tokens.consume_symbol(Symbol::Assign);
+        let (type_, initializer) = ast_initializer(type_, tokens, definitions);
+        if let Ok(val) = definitions.declare_local_val(&name, type_.clone(), info) {
+            let val_ast = Ast::new_variable_ast(val, info, type_);
+            Some(make_local_initialize_ast(
+                initializer,
                 val_ast,
-                initial_value_ast,
+                assgin_info,
+                definitions,
             ))
         } else {
-            tokens.consume_symbol(Symbol::SemiColon);
-            None
+            output_alreadydeclared_variable_err(&info);
         }
+    } else if definitions.declare_local_val(&name, type_, info).is_ok() {
+        None
     } else {
         output_alreadydeclared_variable_err(&info);
     }
 }
+
+// declaration = storage_class? declspec (declarator ("=" initializer)? ("," declarator ("=" initializer)?)*)? ";"
+// 複数の変数の初期化がある場合は複文のASTにまとめる
+pub fn local_val_declaration(tokens: &mut Tokens, definitions: &mut Definitions) -> Option<Ast> {
+    let storage_class = consume_storage_class(tokens);
+    let base_type = consume_declspec(tokens, definitions);
+    let mut initialize_asts: Vec<Ast> = vec![];
+    // 構造体型の宣言のみの場合は宣言子がない
+    while !tokens.expect_symbol(Symbol::SemiColon) {
+        let (type_, name, info, _params) =
+            consume_declarator(base_type.clone(), tokens, definitions);
+        if name.is_empty() {
+            output_unexpected_token_err(tokens);
+        }
+        if type_.function.is_some() {
+            // ブロック内の関数宣言
+            function_declaration(&name, &info, &type_, storage_class, definitions);
+        } else if storage_class != StorageClass::Auto {
+            local_static_val_declaration(name, info, type_, storage_class, tokens, definitions);
+        } else if let Some(initialize_ast) =
+            local_auto_val_declaration(name, info, type_, tokens, definitions)
+        {
+            initialize_asts.push(initialize_ast);
+        }
+        if !tokens.expect_symbol(Symbol::Comma) {
+            break;
+        }
+        tokens.consume_symbol(Symbol::Comma);
+    }
+    let declaration_info = tokens.consume_symbol(Symbol::SemiColon);
+    if initialize_asts.len() > 1 {
+        let exprs_type = definitions.get_type("void").unwrap();
+        Some(Ast::new_expressions_ast(
+            declaration_info,
+            exprs_type,
+            initialize_asts,
+            None,
+        ))
+    } else {
+        initialize_asts.pop()
+    }
+}
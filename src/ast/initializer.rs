@@ -0,0 +1,363 @@
+use std::ops::Deref;
+
+use super::ast::{Ast, AstKind, Operation};
+use super::constant::{eval_address_constant, get_integer_constant};
+use super::declaration::complete_struct_type;
+use super::operations::{
+    ast_formula, check_pointer_qualifiers, check_struct_assign, convert_array_to_pointer,
+    convert_assign_value, new_subscript_ast,
+};
+use crate::definition::definitions::Definitions;
+use crate::definition::number::Number;
+use crate::definition::symbols::Symbol;
+use crate::definition::types::Type;
+use crate::definition::variables::StaticData;
+use crate::token::error::*;
+use crate::token::token::{TokenInfo, Tokens};
+
+/// 初期化子
+///
+/// member
+/// - expr - スカラー型の初期値(与えられていない場合は0で初期化する)
+/// - children - 配列は要素ごと, 構造体はメンバーごと(宣言順)の初期化子
+/// - flexible - 要素数を省略した配列であり, 初期化子の要素数から配列の要素数を決める
+pub struct Initializer {
+    type_: Type,
+    expr: Option<Ast>,
+    children: Vec<Initializer>,
+    flexible: bool,
+}
+
+impl Initializer {
+    fn new(type_: Type, definitions: &Definitions) -> Self {
+        let type_ = complete_struct_type(&type_, definitions);
+        let mut children = vec![];
+        if let Some((count, elem_type)) = &type_.array {
+            for _ in 0..*count {
+                children.push(Initializer::new(elem_type.deref().clone(), definitions));
+            }
+        } else if type_.is_struct() {
+            for (_name, _offset, member_type) in type_.get_struct_members_in_order() {
+                children.push(Initializer::new(member_type, definitions));
+            }
+        }
+        Initializer {
+            type_,
+            expr: None,
+            children,
+            flexible: false,
+        }
+    }
+
+    // index番目の要素の初期化子を取得する
+    // 要素数を省略した配列の場合は要素を追加する
+    fn get_child(
+        &mut self,
+        index: usize,
+        info: &TokenInfo,
+        definitions: &Definitions,
+    ) -> &mut Initializer {
+        if self.flexible {
+            let (_count, elem_type) = self.type_.array.as_ref().unwrap();
+            let elem_type = elem_type.deref().clone();
+            while self.children.len() <= index {
+                self.children
+                    .push(Initializer::new(elem_type.clone(), definitions));
+            }
+        }
+        if index >= self.children.len() {
+            output_excessinitializer_err(info);
+        }
+        &mut self.children[index]
+    }
+}
+
+// 初期化子リストの終わり("}" または ",}")か
+fn is_list_end(tokens: &Tokens) -> bool {
+    tokens.expect_symbol(Symbol::RightCurlyBracket)
+        || (tokens.expect_symbol(Symbol::Comma)
+            && tokens.expect_next_symbol(Symbol::RightCurlyBracket, 1))
+}
+
+fn consume_list_end(tokens: &mut Tokens) {
+    if tokens.expect_symbol(Symbol::Comma) {
+        tokens.consume_symbol(Symbol::Comma);
+    }
+    tokens.consume_symbol(Symbol::RightCurlyBracket);
+}
+
+fn is_designator(tokens: &Tokens) -> bool {
+    tokens.expect_symbols(&[Symbol::Period, Symbol::LeftSquareBracket])
+}
+
+// 指示子("[" 定数 "]" | "." メンバー名)が指す要素のインデックスを返す
+fn consume_designator(
+    init: &Initializer,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> usize {
+    if init.type_.is_array() && tokens.expect_symbol(Symbol::LeftSquareBracket) {
+        tokens.consume_symbol(Symbol::LeftSquareBracket);
+        let index_ast = ast_formula(tokens, definitions);
+        tokens.consume_symbol(Symbol::RightSquareBracket);
+        if let Some(index) = get_integer_constant(&index_ast) {
+            index as usize
+        } else {
+            output_notconstant_err(&index_ast.info);
+        }
+    } else if init.type_.is_struct() && tokens.expect_symbol(Symbol::Period) {
+        tokens.consume_symbol(Symbol::Period);
+        let (member_name, member_info) = tokens.consume_identifier();
+        let members = init.type_.get_struct_members_in_order();
+        if let Some(index) = members
+            .iter()
+            .position(|(name, _offset, _type)| *name == member_name)
+        {
+            index
+        } else {
+            output_nomember_err(&member_info);
+        }
+    } else {
+        output_unexpected_token_err(tokens);
+    }
+}
+
+// designation = ("[" 定数 "]" | "." メンバー名)+ "=" initializer
+// 指定した要素のインデックスを返す
+fn parse_designation(
+    init: &mut Initializer,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> usize {
+    let info = tokens.get().unwrap().info;
+    let index = consume_designator(init, tokens, definitions);
+    let child = init.get_child(index, &info, definitions);
+    if is_designator(tokens) {
+        parse_designation(child, tokens, definitions);
+    } else {
+        tokens.consume_symbol(Symbol::Assign);
+        parse_initializer(child, tokens, definitions);
+    }
+    index
+}
+
+// "{" initializer_list "}"
+// 指示子がない要素は直前の要素の次の要素を初期化する
+fn parse_braced_list(init: &mut Initializer, tokens: &mut Tokens, definitions: &mut Definitions) {
+    tokens.consume_symbol(Symbol::LeftCurlyBracket);
+    let mut index = 0;
+    let mut is_first = true;
+    while !is_list_end(tokens) {
+        if !is_first {
+            tokens.consume_symbol(Symbol::Comma);
+        }
+        is_first = false;
+        if is_designator(tokens) {
+            index = parse_designation(init, tokens, definitions);
+        } else {
+            let info = tokens.get().unwrap().info;
+            let child = init.get_child(index, &info, definitions);
+            parse_initializer(child, tokens, definitions);
+        }
+        index += 1;
+    }
+    consume_list_end(tokens);
+}
+
+// "{"を省略した配列, 構造体の初期化子
+// exprは読み込み済みの最初の要素の式で, 同じ構造体型であれば構造体全体の初期値とする
+// それ以外は外側の初期化子リストの要素を先頭から順に割り当てる
+fn parse_elided_list(
+    init: &mut Initializer,
+    expr: Ast,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) {
+    if !(init.type_.is_array() || init.type_.is_struct()) || init.type_.is_same_struct(&expr.type_)
+    {
+        init.expr = Some(expr);
+        return;
+    }
+    if init.children.is_empty() {
+        output_excessinitializer_err(&expr.info);
+    }
+    parse_elided_list(&mut init.children[0], expr, tokens, definitions);
+    for index in 1..init.children.len() {
+        if is_list_end(tokens) {
+            break;
+        }
+        // 指示子付きの要素は外側の初期化子リストで処理する
+        if !tokens.expect_symbol(Symbol::Comma)
+            || tokens.expect_next_symbols(&[Symbol::Period, Symbol::LeftSquareBracket], 1)
+        {
+            break;
+        }
+        tokens.consume_symbol(Symbol::Comma);
+        parse_initializer(&mut init.children[index], tokens, definitions);
+    }
+}
+
+// initializer = "{" initializer_list "}" | formula
+fn parse_initializer(init: &mut Initializer, tokens: &mut Tokens, definitions: &mut Definitions) {
+    if (init.type_.is_array() || init.type_.is_struct())
+        && tokens.expect_symbol(Symbol::LeftCurlyBracket)
+    {
+        parse_braced_list(init, tokens, definitions);
+    } else if tokens.expect_symbol(Symbol::LeftCurlyBracket) {
+        // スカラーの初期値も{}で囲むことができる
+        tokens.consume_symbol(Symbol::LeftCurlyBracket);
+        parse_initializer(init, tokens, definitions);
+        consume_list_end(tokens);
+    } else {
+        let expr = ast_formula(tokens, definitions);
+        parse_elided_list(init, expr, tokens, definitions);
+    }
+}
+
+// 初期化子を読み, 初期化する変数の型と初期化子を返す
+// 要素数を省略した配列(要素数0)は初期化子の要素数から型を決める
+pub fn ast_initializer(
+    type_: Type,
+    tokens: &mut Tokens,
+    definitions: &mut Definitions,
+) -> (Type, Initializer) {
+    let mut init = Initializer::new(type_, definitions);
+    if let Some((0, _elem_type)) = &init.type_.array {
+        init.flexible = true;
+    }
+    parse_initializer(&mut init, tokens, definitions);
+    if init.flexible {
+        let (_count, elem_type) = init.type_.array.as_ref().unwrap();
+        init.type_ = Type::new_array(init.children.len(), elem_type.deref().clone());
+    }
+    (init.type_.clone(), init)
+}
+
+// 現在のデータ末尾からoffsetまでを0で埋める
+fn pad_static_data(data: &mut Vec<StaticData>, data_size: &mut usize, offset: usize) {
+    if offset <= *data_size {
+        return;
+    }
+    let padding = offset - *data_size;
+    if let Some(StaticData::Zero(zero_size)) = data.last_mut() {
+        *zero_size += padding;
+    } else {
+        data.push(StaticData::Zero(padding));
+    }
+    *data_size = offset;
+}
+
+fn push_static_data(
+    init: &Initializer,
+    offset: usize,
+    data: &mut Vec<StaticData>,
+    data_size: &mut usize,
+) {
+    if let Some((_count, elem_type)) = &init.type_.array {
+        for (index, child) in init.children.iter().enumerate() {
+            push_static_data(child, offset + index * elem_type.size, data, data_size);
+        }
+    } else if init.type_.is_struct() && init.expr.is_none() {
+        let members = init.type_.get_struct_members_in_order();
+        for ((_name, member_offset, _type), child) in members.iter().zip(init.children.iter()) {
+            push_static_data(child, offset + member_offset, data, data_size);
+        }
+    } else if let Some(expr) = &init.expr {
+        if !init.type_.is_integer_type() && !init.type_.is_pointer() {
+            output_notconstant_err(&expr.info);
+        }
+        if let AstKind::FunctionAddress(func_name) = &expr.kind {
+            pad_static_data(data, data_size, offset);
+            data.push(StaticData::Address(func_name.clone(), 0));
+            *data_size += init.type_.size;
+        } else if let Some((label, addend)) = eval_address_constant(expr) {
+            // アドレスはポインタ型にしか格納できない
+            if !init.type_.is_pointer() {
+                output_notconstant_err(&expr.info);
+            }
+            pad_static_data(data, data_size, offset);
+            data.push(StaticData::Address(label, addend));
+            *data_size += init.type_.size;
+        } else if let Some(value) = get_integer_constant(expr) {
+            // _Bool型は0以外の値を1とする
+            let value = if init.type_.is_bool() {
+                (value != 0) as u64
+            } else {
+                value
+            };
+            pad_static_data(data, data_size, offset);
+            data.push(StaticData::Integer(init.type_.size, value));
+            *data_size += init.type_.size;
+        } else {
+            output_notconstant_err(&expr.info);
+        }
+    }
+}
+
+// 初期化子を.dataに出力する値の列に変換する
+// 初期値を与えられていない要素, パディングは0で埋める
+pub fn make_static_data(init: &Initializer) -> Vec<StaticData> {
+    let mut data: Vec<StaticData> = vec![];
+    let mut data_size = 0;
+    push_static_data(init, 0, &mut data, &mut data_size);
+    pad_static_data(&mut data, &mut data_size, init.type_.size);
+    data
+}
+
+fn push_initialize_asts(
+    init: Initializer,
+    lvalue_ast: Ast,
+    info: TokenInfo,
+    definitions: &Definitions,
+    asts: &mut Vec<Ast>,
+) {
+    let long_type = definitions.get_type("long").unwrap();
+    if init.type_.is_array() {
+        for (index, child) in init.children.into_iter().enumerate() {
+            let index_ast =
+                Ast::new_integer_ast(Number::U64(index as u64), info, long_type.clone());
+            let elem_ast = new_subscript_ast(info, lvalue_ast.clone(), index_ast, definitions);
+            push_initialize_asts(child, elem_ast, info, definitions, asts);
+        }
+    } else if init.type_.is_struct() && init.expr.is_none() {
+        let members = init.type_.get_struct_members_in_order();
+        for ((_name, offset, _type), child) in members.into_iter().zip(init.children) {
+            let member_ast =
+                Ast::new_member_ast(info, child.type_.clone(), offset, lvalue_ast.clone());
+            push_initialize_asts(child, member_ast, info, definitions, asts);
+        }
+    } else {
+        let value_ast = init
+            .expr
+            .map(convert_array_to_pointer)
+            .unwrap_or_else(|| Ast::new_integer_ast(Number::U64(0), info, long_type));
+        check_pointer_qualifiers(&init.type_, &value_ast.type_, &value_ast.info);
+        check_struct_assign(&init.type_, &value_ast.type_, &value_ast.info);
+        let value_ast = convert_assign_value(&init.type_, value_ast);
+        asts.push(Ast::new_binary_operation_ast(
+            Operation::Assign,
+            info,
+            init.type_,
+            lvalue_ast,
+            value_ast,
+        ));
+    }
+}
+
+// ローカル変数の初期化子を代入式の列に変換する
+// 初期値を与えられていない要素には0を代入する
+pub fn make_local_initialize_ast(
+    init: Initializer,
+    val_ast: Ast,
+    info: TokenInfo,
+    definitions: &Definitions,
+) -> Ast {
+    let mut assign_asts: Vec<Ast> = vec![];
+    push_initialize_asts(init, val_ast, info, definitions, &mut assign_asts);
+    if assign_asts.len() == 1 {
+        assign_asts.pop().unwrap()
+    } else {
+        let exprs_type = definitions.get_type("void").unwrap();
+        Ast::new_expressions_ast(info, exprs_type, assign_asts, None)
+    }
+}
@@ -1,5 +1,7 @@
 pub mod ast;
+pub mod constant;
 mod controls;
 mod declaration;
 pub mod error;
+mod initializer;
 mod operations;
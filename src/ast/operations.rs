@@ -1,4 +1,8 @@
 use crate::ast::ast::*;
+use crate::ast::constant::get_integer_constant;
+use crate::ast::declaration::{
+    complete_struct_type, consume_type_name, is_incomplete_struct_type, is_type_token_at,
+};
 use crate::definition::definitions::Definitions;
 use crate::definition::number::Number;
 use crate::definition::reservedwords::Reserved;
@@ -32,16 +36,275 @@ fn can_execute_arithmetic_operation() {}
 // 論理演算が可能なASTかチェックする
 fn can_execute_logical_operation() {}
 
-// 2引数の演算ではより大きな型に拡張して行う必要があるため,
-// 型が異なる場合は型変換のASTを挟むようにする
-pub fn convert_primitive_ast_type(ast: &mut Ast, _convert_type: PrimitiveType) -> Type {
-    ast.type_.clone()
+// 整数拡張 intより小さい整数型はint型として演算する
+fn promote_integer_type(type_: &Type, definitions: &Definitions) -> Type {
+    if type_.is_integer_type() && type_.size < 4 {
+        definitions.get_type("int").unwrap()
+    } else {
+        type_.unqualified()
+    }
+}
+
+// 2引数の演算ではより大きな型に拡張して行う必要がある (通常の算術型変換)
+// 整数拡張後, サイズの大きい方の型に合わせ, 同じサイズで一方が符号なしなら符号なしの型にする
+pub fn expand_binary_operation_type(left: &Ast, right: &Ast, definitions: &Definitions) -> Type {
+    if !left.type_.is_integer_type() || !right.type_.is_integer_type() {
+        return left.type_.clone();
+    }
+    let left_type = promote_integer_type(&left.type_, definitions);
+    let right_type = promote_integer_type(&right.type_, definitions);
+    match left_type.size.cmp(&right_type.size) {
+        std::cmp::Ordering::Greater => left_type,
+        std::cmp::Ordering::Less => right_type,
+        std::cmp::Ordering::Equal if right_type.is_unsigned_integer() => right_type,
+        std::cmp::Ordering::Equal => left_type,
+    }
 }
 
-// 2引数の演算ではより大きな型に拡張して行う必要があるため,
 // 型が異なる場合は型変換のASTを挟むようにする
-pub fn expand_binary_operation_type(left: &Ast, _right: &Ast) -> Type {
-    left.type_.clone()
+pub fn convert_primitive_ast_type(ast: Ast, convert_type: &Type) -> Ast {
+    if !ast.type_.is_integer_type() || ast.type_.primitive == convert_type.primitive {
+        return ast;
+    }
+    let info = ast.info;
+    Ast::new_cast_ast(info, convert_type.clone(), ast)
+}
+
+// 算術演算, ビット演算のASTを作成する
+// 両辺を通常の算術型変換後の型に変換してから演算する
+fn new_arithmetic_operation_ast(
+    operation: Operation,
+    info: TokenInfo,
+    left_ast: Ast,
+    right_ast: Ast,
+    definitions: &Definitions,
+) -> Ast {
+    let type_ = expand_binary_operation_type(&left_ast, &right_ast, definitions);
+    let left_ast = convert_primitive_ast_type(left_ast, &type_);
+    let right_ast = convert_primitive_ast_type(right_ast, &type_);
+    Ast::new_binary_operation_ast(operation, info, type_, left_ast, right_ast)
+}
+
+// 可変長引数部分に渡す値は既定の実引数拡張を行う
+// intより小さい整数型はint型に, float型はdouble型に拡張する
+// (値は常に8バイトで扱っているので型情報のみ変更する)
+pub fn promote_variadic_arg_type(ast: &mut Ast, definitions: &Definitions) {
+    match ast.type_.primitive {
+        Some(PrimitiveType::I8 | PrimitiveType::U8 | PrimitiveType::I16 | PrimitiveType::U16) => {
+            ast.type_ = definitions.get_type("int").unwrap();
+        }
+        Some(PrimitiveType::F32) => {
+            ast.type_ = definitions.get_type("double").unwrap();
+        }
+        _ => {}
+    }
+}
+
+// 暗黙の型変換で代入(引数渡し)できる型かチェックする
+// 整数型同士, void*と他のポインタ型の間は変換できる
+// ポインタの変換では指す先の型の修飾子を落とすことはできない
+// 値が0の整数定数式は空ポインタ定数として任意のポインタ型に変換できる
+pub fn can_assign_type(assignee: &Type, value_ast: &Ast) -> bool {
+    let value = &value_ast.type_;
+    if assignee.is_pointer() && value.is_integer_type() {
+        return get_integer_constant(value_ast) == Some(0);
+    }
+    if let (Some(assignee_pointee), Some(value_pointee)) = (&assignee.pointer, &value.pointer) {
+        return assignee_pointee.has_qualifiers_of(value_pointee)
+            && (assignee_pointee == value_pointee
+                || assignee.is_void_pointer()
+                || value.is_void_pointer());
+    }
+    if assignee == value {
+        return true;
+    }
+    // ポインタは_Bool型に変換できる
+    if assignee.is_bool() && value.is_pointer() {
+        return true;
+    }
+    assignee.is_integer_type() && value.is_integer_type()
+}
+
+// 代入する値を代入先の型に変換する
+// _Bool型への代入は値を0か1に正規化する必要があるので, キャストのASTを挟む
+// (_Bool型の演算結果は0か1とは限らないので, 値の型が_Bool型でも正規化する)
+pub fn convert_assign_value(assignee_type: &Type, value_ast: Ast) -> Ast {
+    let is_normalized = value_ast.kind == AstKind::Cast && value_ast.type_.is_bool();
+    if assignee_type.is_bool() && !is_normalized {
+        let info = value_ast.info;
+        Ast::new_cast_ast(info, assignee_type.unqualified(), value_ast)
+    } else {
+        value_ast
+    }
+}
+
+// 代入先が変更可能かチェックする
+pub fn check_modifiable(assignee: &Ast, info: &TokenInfo) {
+    if assignee.type_.is_const {
+        output_readonly_err(info);
+    }
+}
+
+// 代入で指す先の型の修飾子を落とすポインタの変換をしていないかチェックする
+// 2段目以降の指す先は修飾子が一致しなければならない
+// (long **にconst long **を代入できると, const longをlong *経由で書き換えられてしまう)
+pub fn check_pointer_qualifiers(assignee: &Type, value: &Type, info: &TokenInfo) {
+    if let (Some(assignee_pointee), Some(value_pointee)) = (&assignee.pointer, &value.pointer) {
+        if !assignee_pointee.has_qualifiers_of(value_pointee) {
+            output_discardedqualifier_err(info);
+        }
+        let mut assignee_pointee = assignee_pointee;
+        let mut value_pointee = value_pointee;
+        while let (Some(assignee_next), Some(value_next)) =
+            (&assignee_pointee.pointer, &value_pointee.pointer)
+        {
+            if assignee_next.is_const != value_next.is_const
+                || assignee_next.is_volatile != value_next.is_volatile
+            {
+                output_discardedqualifier_err(info);
+            }
+            assignee_pointee = assignee_next;
+            value_pointee = value_next;
+        }
+    }
+}
+
+// 構造体は同じ構造体同士でしか代入できない
+pub fn check_struct_assign(assignee: &Type, value: &Type, info: &TokenInfo) {
+    if (assignee.is_struct() || value.is_struct()) && !assignee.is_same_struct(value) {
+        output_incompatibleassignment_err(info);
+    }
+}
+
+// 右辺値となる配列は先頭要素へのポインタに変換する
+// 配列の値は先頭要素のアドレスなので, 配列のアドレスを取るASTとする
+pub fn convert_array_to_pointer(ast: Ast) -> Ast {
+    if let Some((_count, elem_type)) = &ast.type_.array {
+        let type_ = Type::new_pointer(elem_type.deref().clone());
+        let info = ast.info;
+        Ast::new_address_ast(info, type_, ast)
+    } else {
+        ast
+    }
+}
+
+// ポインタ演算で使用する, ポインタの指す型のサイズ
+// void*は1バイト単位で扱う
+fn get_pointee_size(pointer_type: &Type) -> u64 {
+    let pointee_type = pointer_type.pointer.as_ref().unwrap();
+    std::cmp::max(pointee_type.size, 1) as u64
+}
+
+// ポインタに加減算する整数をポインタの指す型のサイズ倍する
+fn scale_pointer_offset(
+    offset_ast: Ast,
+    pointer_type: &Type,
+    info: TokenInfo,
+    definitions: &Definitions,
+) -> Ast {
+    if !offset_ast.type_.is_integer_type() {
+        output_unexecutable_err(&info);
+    }
+    let size = get_pointee_size(pointer_type);
+    if size == 1 {
+        return offset_ast;
+    }
+    let long_type = definitions.get_type("long").unwrap();
+    let size_ast = Ast::new_integer_ast(Number::U64(size), info, long_type.clone());
+    Ast::new_binary_operation_ast(Operation::Mul, info, long_type, offset_ast, size_ast)
+}
+
+// 加減算のASTを作成する
+// ポインタ ± 整数は整数をポインタの指す型のサイズ倍して計算し, ポインタ型を返す
+// ポインタ - ポインタはアドレスの差を要素のサイズで割った要素数をlong型で返す
+pub fn new_add_operation_ast(
+    operation: Operation,
+    info: TokenInfo,
+    left_ast: Ast,
+    right_ast: Ast,
+    definitions: &Definitions,
+) -> Ast {
+    let left_ast = convert_array_to_pointer(left_ast);
+    let right_ast = convert_array_to_pointer(right_ast);
+    match (left_ast.type_.is_pointer(), right_ast.type_.is_pointer()) {
+        (true, true) => {
+            // 指す先の型の修飾子は異なっていても良い
+            if operation != Operation::Sub || left_ast.type_.pointer != right_ast.type_.pointer {
+                output_unexecutable_err(&info);
+            }
+            let size = get_pointee_size(&left_ast.type_);
+            let long_type = definitions.get_type("long").unwrap();
+            let diff_ast = Ast::new_binary_operation_ast(
+                Operation::Sub,
+                info,
+                long_type.clone(),
+                left_ast,
+                right_ast,
+            );
+            if size == 1 {
+                return diff_ast;
+            }
+            let size_ast = Ast::new_integer_ast(Number::U64(size), info, long_type.clone());
+            Ast::new_binary_operation_ast(Operation::Div, info, long_type, diff_ast, size_ast)
+        }
+        (true, false) => {
+            let type_ = left_ast.type_.clone();
+            let offset_ast = scale_pointer_offset(right_ast, &type_, info, definitions);
+            Ast::new_binary_operation_ast(operation, info, type_, left_ast, offset_ast)
+        }
+        // 整数 + ポインタはポインタ + 整数として扱う
+        (false, true) if operation == Operation::Add => {
+            let type_ = right_ast.type_.clone();
+            let offset_ast = scale_pointer_offset(left_ast, &type_, info, definitions);
+            Ast::new_binary_operation_ast(operation, info, type_, right_ast, offset_ast)
+        }
+        (false, true) => output_unexecutable_err(&info),
+        (false, false) => {
+            new_arithmetic_operation_ast(operation, info, left_ast, right_ast, definitions)
+        }
+    }
+}
+
+// 添字アクセス E1[E2] のASTを *(E1 + E2) として作成する
+pub fn new_subscript_ast(
+    info: TokenInfo,
+    val_ast: Ast,
+    index_ast: Ast,
+    definitions: &Definitions,
+) -> Ast {
+    let pointer_ast = new_add_operation_ast(Operation::Add, info, val_ast, index_ast, definitions);
+    if let Some(elem_type) = &pointer_ast.type_.pointer {
+        let type_ = elem_type.deref().clone();
+        Ast::new_deref_ast(info, type_, pointer_ast)
+    } else {
+        output_unindexiable_err(&info);
+    }
+}
+
+// 比較演算のASTを作成する
+// ポインタ同士の比較は同じ型のポインタか, 一方がvoid*の場合のみ行える
+fn new_comparison_ast(
+    operation: Operation,
+    info: TokenInfo,
+    left_ast: Ast,
+    right_ast: Ast,
+    definitions: &Definitions,
+) -> Ast {
+    let left_ast = convert_array_to_pointer(left_ast);
+    let right_ast = convert_array_to_pointer(right_ast);
+    if left_ast.type_.is_pointer()
+        && right_ast.type_.is_pointer()
+        && !can_assign_type(&left_ast.type_, &right_ast)
+        && !can_assign_type(&right_ast.type_, &left_ast)
+    {
+        output_unexecutable_err(&info);
+    }
+    // 整数同士は通常の算術型変換後の型で比較する
+    let common_type = expand_binary_operation_type(&left_ast, &right_ast, definitions);
+    let left_ast = convert_primitive_ast_type(left_ast, &common_type);
+    let right_ast = convert_primitive_ast_type(right_ast, &common_type);
+    let type_ = definitions.get_type("int").unwrap();
+    Ast::new_binary_operation_ast(operation, info, type_, left_ast, right_ast)
 }
 
 // 代入では被代入側の型に合わせる必要がある
@@ -51,40 +314,47 @@ pub fn expand_assign_operation_type(left: &Ast, _right: &Ast) -> Type {
 }
 
 fn get_increment_type(tokens: &mut Tokens) -> (String, TokenInfo) {
-    let operation;
-    if tokens.expect_symbol(Symbol::Increment) {
-        operation = "add";
+    let operation = if tokens.expect_symbol(Symbol::Increment) {
+        "add"
     } else {
-        operation = "sub";
-    }
+        "sub"
+    };
     let info = tokens.consume_symbols(&[Symbol::Increment, Symbol::Decrement]);
     (operation.to_string(), info)
 }
 
-fn increment_ast(variable_ast: Ast, operation: String, incinfo: TokenInfo) -> (Ast, Ast) {
+// ポインタのインクリメントはポインタの指す型のサイズ分アドレスを進める
+fn increment_ast(
+    variable_ast: Ast,
+    operation: String,
+    incinfo: TokenInfo,
+    definitions: &Definitions,
+) -> (Ast, Ast) {
     let increment_type = variable_ast.type_.clone();
-    if !variable_ast.type_.is_primitive_type() {
+    if !variable_ast.type_.is_primitive_type() && !variable_ast.type_.is_pointer() {
         output_unexecutable_err(&variable_ast.info)
     }
-    let num_1_ast = Ast::new_integer_ast(Number::U64(1), incinfo, increment_type.clone());
-    let increment_ast;
-    if operation == "add" {
-        increment_ast = Ast::new_binary_operation_ast(
+    check_modifiable(&variable_ast, &incinfo);
+    let long_type = definitions.get_type("long").unwrap();
+    let num_1_ast = Ast::new_integer_ast(Number::U64(1), incinfo, long_type);
+    let increment_ast = if operation == "add" {
+        new_add_operation_ast(
             Operation::Add,
             incinfo,
-            increment_type.clone(),
             variable_ast.clone(),
             num_1_ast,
-        );
+            definitions,
+        )
     } else {
-        increment_ast = Ast::new_binary_operation_ast(
+        new_add_operation_ast(
             Operation::Sub,
             incinfo,
-            increment_type.clone(),
             variable_ast.clone(),
             num_1_ast,
-        );
-    }
+            definitions,
+        )
+    };
+    let increment_ast = convert_assign_value(&increment_type, increment_ast);
     let assign_ast = Ast::new_binary_operation_ast(
         Operation::Assign,
         incinfo,
@@ -99,7 +369,7 @@ fn increment_ast(variable_ast: Ast, operation: String, incinfo: TokenInfo) -> (A
 pub fn ast_forward_increment(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     let (operation, info) = get_increment_type(tokens);
     let variable_ast = ast_variable_op(tokens, definitions);
-    let (assign_ast, _variable_ast) = increment_ast(variable_ast, operation, info);
+    let (assign_ast, _variable_ast) = increment_ast(variable_ast, operation, info, definitions);
     Ast::new_single_operation_ast(
         Operation::ForwardIncrement,
         assign_ast.info,
@@ -112,10 +382,10 @@ pub fn ast_forward_increment(tokens: &mut Tokens, definitions: &mut Definitions)
 pub fn ast_backward_increment(
     val_ast: Ast,
     tokens: &mut Tokens,
-    _definitions: &mut Definitions,
+    definitions: &mut Definitions,
 ) -> Ast {
     let (operation, info) = get_increment_type(tokens);
-    let (assign_ast, val_ast) = increment_ast(val_ast, operation, info);
+    let (assign_ast, val_ast) = increment_ast(val_ast, operation, info, definitions);
     Ast::new_binary_operation_ast(
         Operation::BackwardIncrement,
         assign_ast.info,
@@ -137,31 +407,47 @@ fn ast_bitnot(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     // drop "~" token
     let bitnot_info = tokens.consume_symbol(Symbol::BitNot);
     let operand_ast = ast_unary(tokens, definitions);
-    let type_ = operand_ast.type_.clone();
+    let type_ = promote_integer_type(&operand_ast.type_, definitions);
+    let operand_ast = convert_primitive_ast_type(operand_ast, &type_);
     Ast::new_single_operation_ast(Operation::BitNot, bitnot_info, type_, operand_ast)
 }
 
+// アドレスを取得できる左辺値か
+// 変数, ポインタの参照先(添字アクセスを含む), 左辺値の構造体メンバーが左辺値となる
+pub fn is_lvalue(ast: &Ast) -> bool {
+    match &ast.kind {
+        AstKind::Variable(_) | AstKind::Deref => true,
+        AstKind::Member(_) => is_lvalue(ast.operand.as_ref().unwrap()),
+        _ => false,
+    }
+}
+
+// address = "&" unary
+// 関数指示子と左辺値のみアドレスにすることができる
 fn ast_address(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     // drop "&" token
     let address_info = tokens.consume_symbol(Symbol::BitAnd);
     let operand_ast = ast_unary(tokens, definitions);
-    let type_;
-    // 変数とプリミティブ型のみアドレスにすることができる
-    if let AstKind::Variable(_) = &operand_ast.kind {
-        type_ = Type::new_pointer(operand_ast.type_.clone());
-    } else if operand_ast.type_.primitive.is_some() {
-        type_ = Type::new_pointer(definitions.get_type("void").unwrap());
-    } else {
+    // 関数指示子はすでに関数のアドレスとなっている
+    if let AstKind::FunctionAddress(_) = &operand_ast.kind {
+        return operand_ast;
+    }
+    if !is_lvalue(&operand_ast) {
         output_unaddressable_err(&address_info);
     }
+    let type_ = Type::new_pointer(operand_ast.type_.clone());
     Ast::new_address_ast(address_info, type_, operand_ast)
 }
 
 fn ast_deref_pointer(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     // drop "*" token
     let deref_info = tokens.consume_symbol(Symbol::Mul);
-    let operand_ast = ast_unary(tokens, definitions);
+    let operand_ast = convert_array_to_pointer(ast_unary(tokens, definitions));
     if let Some(deref_type) = &operand_ast.type_.pointer {
+        // 関数ポインタの参照先の関数は再び関数ポインタに変換されるので, 値は変わらない
+        if deref_type.function.is_some() {
+            return operand_ast;
+        }
         let type_ = deref_type.deref().clone();
         Ast::new_deref_ast(deref_info, type_, operand_ast)
     } else {
@@ -169,17 +455,49 @@ fn ast_deref_pointer(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast
     }
 }
 
-// sizeof = "sizeof" "(" formula ")"
+// sizeof = "sizeof" ( "(" type_name ")" | unary )
+// オペランドの式は型を求めるためだけに使用し, 評価はしない
+// 配列は先頭要素へのポインタに変換せず, 配列全体のサイズとなる
 fn ast_sizeof(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     let sizeof_info = tokens.consume_reserved(Reserved::Sizeof);
-    let size_ast = ast_formula(tokens, definitions);
-    let size = size_ast.type_.size;
-    let type_ = definitions.get_type("long").unwrap();
-    let num = Number::U64(size as u64);
+    let size_type = if tokens.expect_symbol(Symbol::LeftParenthesis)
+        && is_type_token_at(tokens, definitions, 1)
+    {
+        tokens.consume_symbol(Symbol::LeftParenthesis);
+        let type_ = consume_type_name(tokens, definitions);
+        tokens.consume_symbol(Symbol::RightParenthesis);
+        type_
+    } else {
+        ast_unary(tokens, definitions).type_
+    };
+    let size_type = complete_struct_type(&size_type, definitions);
+    // 定義されていない構造体型はサイズが決まらない
+    if is_incomplete_struct_type(&size_type, definitions) {
+        output_incompletetype_err(&sizeof_info);
+    }
+    let type_ = definitions.get_type("unsigned long").unwrap();
+    let num = Number::U64(size_type.size as u64);
     Ast::new_integer_ast(num, sizeof_info, type_)
 }
 
-// unary = primary |  "+" unary |  "-" unary | "!" unary |  "~" unary | "&" unary |  "*" unary | "sizeof" "(" formula ")"
+// cast = "(" type_name ")" unary
+// スカラー型とvoid型へのキャストのみ対応
+fn ast_cast(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
+    let cast_info = tokens.consume_symbol(Symbol::LeftParenthesis);
+    let type_ = consume_type_name(tokens, definitions);
+    tokens.consume_symbol(Symbol::RightParenthesis);
+    let operand_ast = ast_unary(tokens, definitions);
+    let is_void = type_ == definitions.get_type("void").unwrap();
+    if !is_void && !type_.is_primitive_type() && !type_.is_pointer() {
+        output_notcastable_err(&cast_info);
+    }
+    if !is_void && operand_ast.type_.is_struct() {
+        output_notcastable_err(&operand_ast.info);
+    }
+    Ast::new_cast_ast(cast_info, type_, operand_ast)
+}
+
+// unary = primary | cast |  "+" unary |  "-" unary | "!" unary |  "~" unary | "&" unary |  "*" unary | sizeof
 // この部分の規格は不明
 pub fn ast_unary(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
     if tokens.expect_symbol(Symbol::Add) {
@@ -190,9 +508,9 @@ pub fn ast_unary(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         // drop "-" token
         let sub_info = tokens.consume_symbol(Symbol::Sub);
         let primary_ast = ast_primary(tokens, definitions);
-        let type_ = primary_ast.type_.clone();
-        let zero_ast = Ast::new_integer_ast(Number::U64(0), sub_info, type_.clone());
-        Ast::new_binary_operation_ast(Operation::Sub, sub_info, type_, zero_ast, primary_ast)
+        let int_type = definitions.get_type("int").unwrap();
+        let zero_ast = Ast::new_integer_ast(Number::U64(0), sub_info, int_type);
+        new_arithmetic_operation_ast(Operation::Sub, sub_info, zero_ast, primary_ast, definitions)
     } else if tokens.expect_symbol(Symbol::Not) {
         ast_not(tokens, definitions)
     } else if tokens.expect_symbol(Symbol::BitNot) {
@@ -203,6 +521,10 @@ pub fn ast_unary(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         ast_deref_pointer(tokens, definitions)
     } else if tokens.expect_reserved(Reserved::Sizeof) {
         ast_sizeof(tokens, definitions)
+    } else if tokens.expect_symbol(Symbol::LeftParenthesis)
+        && is_type_token_at(tokens, definitions, 1)
+    {
+        ast_cast(tokens, definitions)
     } else {
         ast_primary(tokens, definitions)
     }
@@ -226,8 +548,8 @@ fn ast_mul(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 
         let ast_info = tokens.consume();
         let right_ast = ast_unary(tokens, definitions);
-        let type_ = expand_binary_operation_type(&mul_ast, &right_ast);
-        mul_ast = Ast::new_binary_operation_ast(operation, ast_info, type_, mul_ast, right_ast);
+        mul_ast =
+            new_arithmetic_operation_ast(operation, ast_info, mul_ast, right_ast, definitions);
     }
 }
 
@@ -247,8 +569,7 @@ fn ast_add(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 
         let ast_info = tokens.consume();
         let right_ast = ast_mul(tokens, definitions);
-        let type_ = expand_binary_operation_type(&add_ast, &right_ast);
-        add_ast = Ast::new_binary_operation_ast(operation, ast_info, type_, add_ast, right_ast);
+        add_ast = new_add_operation_ast(operation, ast_info, add_ast, right_ast, definitions);
     }
 }
 
@@ -272,10 +593,8 @@ fn ast_relational(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
 
         let ast_info = tokens.consume();
         let right_ast = ast_add(tokens, definitions);
-        // とりあえず型は8バイトにしておく
-        let type_ = definitions.get_type("long").unwrap();
         relational_ast =
-            Ast::new_binary_operation_ast(operation, ast_info, type_, relational_ast, right_ast);
+            new_comparison_ast(operation, ast_info, relational_ast, right_ast, definitions);
     }
 }
 
@@ -294,11 +613,9 @@ pub fn ast_equality(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         }
 
         let ast_info = tokens.consume();
-        let right_ast = ast_add(tokens, definitions);
-        // とりあえず和の型は8バイトにしておく
-        let type_ = definitions.get_type("long").unwrap();
+        let right_ast = ast_relational(tokens, definitions);
         equality_ast =
-            Ast::new_binary_operation_ast(operation, ast_info, type_, equality_ast, right_ast);
+            new_comparison_ast(operation, ast_info, equality_ast, right_ast, definitions);
     }
 }
 
@@ -337,14 +654,12 @@ fn ast_bit_operation(
             Symbol::BitAnd => ast_equality(tokens, definitions),
             _ => unreachable!(),
         };
-        // とりあえずビット演算の型は8バイトにしておく
-        let type_ = definitions.get_type("long").unwrap();
-        bit_operation_ast = Ast::new_binary_operation_ast(
+        bit_operation_ast = new_arithmetic_operation_ast(
             bit_operation,
             ast_info,
-            type_,
             bit_operation_ast,
             right_ast,
+            definitions,
         );
     }
 }
@@ -416,13 +731,23 @@ fn ast_assign_op(
     }
     let assing_op_info = tokens.consume();
     let formula_ast = ast_formula(tokens, definitions);
-    let op_ast = Ast::new_binary_operation_ast(
-        ope_kind,
-        assing_op_info,
-        formula_ast.type_.clone(),
-        asiggnee_ast,
-        formula_ast,
-    );
+    let op_ast = if let Operation::Add | Operation::Sub = ope_kind {
+        new_add_operation_ast(
+            ope_kind,
+            assing_op_info,
+            asiggnee_ast,
+            formula_ast,
+            definitions,
+        )
+    } else {
+        new_arithmetic_operation_ast(
+            ope_kind,
+            assing_op_info,
+            asiggnee_ast,
+            formula_ast,
+            definitions,
+        )
+    };
     (assing_op_info, op_ast)
 }
 
@@ -437,12 +762,16 @@ pub fn ast_assign(tokens: &mut Tokens, definitions: &mut Definitions) -> Ast {
         let ast_assigner;
         if tokens.expect_symbol(Symbol::Assign) {
             ast_info = tokens.consume_symbol(Symbol::Assign);
-            ast_assigner = ast_formula(tokens, definitions);
+            ast_assigner = convert_array_to_pointer(ast_formula(tokens, definitions));
+            check_pointer_qualifiers(&assignee_ast.type_, &ast_assigner.type_, &ast_info);
+            check_struct_assign(&assignee_ast.type_, &ast_assigner.type_, &ast_info);
         } else {
             let tmp = ast_assign_op(assignee_ast.clone(), tokens, definitions);
             ast_info = tmp.0;
             ast_assigner = tmp.1;
         }
+        check_modifiable(&assignee_ast, &ast_info);
+        let ast_assigner = convert_assign_value(&assignee_ast.type_, ast_assigner);
         // とりあえず代入の型は8バイトにしておく
         let type_ = definitions.get_type("long").unwrap();
         assignee_ast = Ast::new_binary_operation_ast(
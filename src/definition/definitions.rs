@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
-use crate::definition::{functions::*, number::*, types::*, variables::*};
+use crate::definition::{functions::*, linkage::*, number::*, types::*, variables::*};
+use crate::token::token::TokenInfo;
 
 pub struct Definitions {
     type_: TypesDefinitions,
@@ -61,12 +62,60 @@ impl Definitions {
         }
     }
 
-    pub fn declare_global_val(&mut self, name: &str, type_: Type) -> Result<Variable, ()> {
-        self.variable.declare_global_val(name, type_)
+    pub fn declare_function_linkage(
+        &mut self,
+        name: &str,
+        storage_class: StorageClass,
+    ) -> Result<Linkage, LinkageError> {
+        self.function.declare_function_linkage(name, storage_class)
     }
 
-    pub fn declare_local_val(&mut self, name: &str, type_: Type) -> Result<Variable, ()> {
-        self.variable.declare_local_val(name, type_)
+    pub fn declare_global_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+        storage_class: StorageClass,
+        init: Option<Vec<StaticData>>,
+    ) -> Result<Variable, LinkageError> {
+        self.variable
+            .declare_global_val(name, type_, storage_class, init)
+    }
+
+    // 関数内static変数のラベルには関数名を含める
+    pub fn declare_static_local_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+        init: Option<Vec<StaticData>>,
+    ) -> Result<Variable, ()> {
+        let func_name = self.currentfunction.clone().unwrap();
+        self.variable
+            .declare_static_local_val(name, &func_name, type_, init)
+    }
+
+    pub fn declare_extern_local_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+    ) -> Result<Variable, LinkageError> {
+        self.variable.declare_extern_local_val(name, type_)
+    }
+
+    pub fn get_static_vals(&self) -> Vec<Variable> {
+        self.variable.get_static_vals()
+    }
+
+    pub fn declare_local_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+        info: TokenInfo,
+    ) -> Result<Variable, ()> {
+        self.variable.declare_local_val(name, type_, info)
+    }
+
+    pub fn take_variable_warnings(&mut self) -> Vec<VariableWarning> {
+        self.variable.take_warnings()
     }
 
     pub fn get_variable(&self, name: &str) -> Option<Variable> {
@@ -80,6 +129,7 @@ impl Definitions {
 
     pub fn exit_function_implemetation(&mut self) {
         self.currentfunction = None;
+        self.variable.check_unused_args();
         self.variable.clear_local_val_scope();
     }
 
@@ -1,40 +1,52 @@
+use super::linkage::{resolve_linkage, Linkage, LinkageError, StorageClass};
 use super::types::Type;
-use std::{collections::HashMap, ops::Deref};
+use std::collections::HashMap;
 
+// variadic - 最後の引数が"..."の可変長引数関数か
 #[derive(Debug, Clone)]
 pub struct Function {
     pub args: Option<Vec<Type>>,
     pub ret: Option<Type>,
+    pub variadic: bool,
 }
 
 impl Function {
-    pub fn new(args: Option<Vec<Type>>, ret: Option<Type>) -> Self {
-        Function { args, ret }
+    pub fn new(args: Option<Vec<Type>>, ret: Option<Type>, variadic: bool) -> Self {
+        Function {
+            args,
+            ret,
+            variadic,
+        }
+    }
+
+    pub fn get_arg_count(&self) -> usize {
+        self.args.as_ref().map_or(0, |args| args.len())
     }
 }
 
 impl PartialEq for Function {
     fn eq(&self, rhs: &Self) -> bool {
+        if self.variadic != rhs.variadic {
+            return false;
+        }
+
         // 引数の数が異なる
-        if self.args.is_some() && rhs.args.is_some() {
-            let self_args = self.args.as_ref().unwrap();
-            let rhs_args = rhs.args.as_ref().unwrap();
+        if let (Some(self_args), Some(rhs_args)) = (&self.args, &rhs.args) {
             if self_args.len() != rhs_args.len() {
                 return false;
             }
 
             for (self_arg, rhs_arg) in self_args.iter().zip(rhs_args.iter()) {
-                if *self_arg.deref() != *rhs_arg.deref() {
+                if self_arg != rhs_arg {
                     return false;
                 }
             }
         }
 
-        if self.ret.is_some()
-            && rhs.ret.is_some()
-            && self.ret.as_ref().unwrap() != rhs.ret.as_ref().unwrap()
-        {
-            return false;
+        if let (Some(self_ret), Some(rhs_ret)) = (&self.ret, &rhs.ret) {
+            if self_ret != rhs_ret {
+                return false;
+            }
         }
         true
     }
@@ -42,15 +54,34 @@ impl PartialEq for Function {
 
 pub struct FunctionDefinitions {
     pub dict: HashMap<String, Function>,
+    linkages: HashMap<String, Linkage>,
 }
 
 impl FunctionDefinitions {
     pub fn new() -> Self {
         FunctionDefinitions {
             dict: HashMap::new(),
+            linkages: HashMap::new(),
         }
     }
 
+    pub fn get_function_linkage(&self, name: &str) -> Option<Linkage> {
+        self.linkages.get(name).copied()
+    }
+
+    // 関数のリンケージを登録する
+    // 外部リンケージで宣言済みの関数をstaticで宣言し直すことはできない
+    pub fn declare_function_linkage(
+        &mut self,
+        name: &str,
+        storage_class: StorageClass,
+    ) -> Result<Linkage, LinkageError> {
+        let prev_linkage = self.get_function_linkage(name);
+        let linkage = resolve_linkage(storage_class, prev_linkage, true)?;
+        self.linkages.insert(name.to_string(), linkage);
+        Ok(linkage)
+    }
+
     pub fn get_function(&self, name: &str) -> Option<Function> {
         self.dict.get(name).cloned()
     }
@@ -0,0 +1,46 @@
+// 記憶域クラス指定子
+// Auto - 指定子なし
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageClass {
+    Auto,
+    Static,
+    Extern,
+}
+
+// 識別子のリンケージ
+// External - 他の翻訳単位から参照できる(.globlで公開する)
+// Internal - 翻訳単位内でのみ参照できる
+// None - リンケージなし. ブロック内のstatic変数など, 名前での参照はそのスコープに限られる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linkage {
+    External,
+    Internal,
+    None,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LinkageError {
+    ConflictingLinkage, // staticと非staticの宣言が混在している
+    ConflictingType,    // 以前の宣言と型が異なる
+    Redefinition,       // 初期値付きの定義が複数ある
+}
+
+// ファイルスコープの識別子のリンケージを求める
+// staticは内部リンケージ, externは以前の宣言のリンケージを引き継ぐ(無ければ外部リンケージ)
+// 指定子なしの場合, 関数はexternと同じ扱いで, 変数は外部リンケージとなる
+pub fn resolve_linkage(
+    storage_class: StorageClass,
+    prev_linkage: Option<Linkage>,
+    is_function: bool,
+) -> Result<Linkage, LinkageError> {
+    let linkage = match storage_class {
+        StorageClass::Static => Linkage::Internal,
+        StorageClass::Extern => prev_linkage.unwrap_or(Linkage::External),
+        StorageClass::Auto if is_function => prev_linkage.unwrap_or(Linkage::External),
+        StorageClass::Auto => Linkage::External,
+    };
+    match prev_linkage {
+        Some(prev_linkage) if prev_linkage != linkage => Err(LinkageError::ConflictingLinkage),
+        _ => Ok(linkage),
+    }
+}
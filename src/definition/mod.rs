@@ -1,5 +1,6 @@
 pub mod definitions;
 pub mod functions;
+pub mod linkage;
 pub mod number;
 pub mod reservedwords;
 pub mod symbols;
@@ -46,7 +46,7 @@ pub fn string_to_number(string: &str) -> Result<Number, ()> {
                             num = num * 10.0 + digit as f64;
                             order += 1;
                         } else {
-                            num += digit as f64 * 10f64.powi(order as i32);
+                            num += digit as f64 * 10f64.powi(order);
                             order -= 1;
                         }
                     }
@@ -1,12 +1,14 @@
 use super::types::{PrimitiveType, PrimitiveTypeError};
 
+// _Static_assertが長いため, rustfmtが1行1要素に展開しないようにする
+#[rustfmt::skip]
 macro_rules! reserved_words_array {
     () => {
         [
             "auto", "break", "case", "char", "const", "continue", "default", "do", "double",
             "else", "enum", "extern", "float", "for", "goto", "if", "int", "long", "register",
             "return", "signed", "sizeof", "short", "static", "struct", "switch", "typedef",
-            "union", "unsigned", "void", "volatile", "while",
+            "union", "unsigned", "void", "volatile", "while", "_Bool", "_Static_assert",
         ]
     };
 }
@@ -45,6 +47,8 @@ pub enum Reserved {
     Void,
     Volatile,
     While,
+    Bool,
+    StaticAssert,
 }
 
 fn get_reserved_word_type(indentifiler: &str) -> Reserved {
@@ -81,6 +85,8 @@ fn get_reserved_word_type(indentifiler: &str) -> Reserved {
         "void" => Reserved::Void,
         "volatile" => Reserved::Volatile,
         "while" => Reserved::While,
+        "_Bool" => Reserved::Bool,
+        "_Static_assert" => Reserved::StaticAssert,
         _ => unreachable!(),
     }
 }
@@ -102,22 +108,52 @@ pub fn check_primitivetype_reserved_word(reserved: &Reserved) -> bool {
             | Reserved::Short
             | Reserved::Char
             | Reserved::Void
+            | Reserved::Signed
             | Reserved::Unsigned
+            | Reserved::Bool
     )
 }
 
-pub fn get_primitivetype_reserved_word(
-    reserved: &Reserved,
+// 連続した型指定子の並びからプリミティブ型を決める
+// 型指定子の順序は問わない (例: "long unsigned long int" は unsigned long long)
+// long long は long と同じ64bitの型として扱う
+pub fn get_primitivetype_reserved_words(
+    words: &[Reserved],
 ) -> Result<PrimitiveType, PrimitiveTypeError> {
-    match reserved {
-        Reserved::Void => Ok(PrimitiveType::Void),
-        Reserved::Char => Ok(PrimitiveType::I8),
-        Reserved::Short => Ok(PrimitiveType::I16),
-        Reserved::Int => Ok(PrimitiveType::I32),
-        Reserved::Long => Ok(PrimitiveType::I64),
-        Reserved::Double => Ok(PrimitiveType::F32),
-        Reserved::Float => Ok(PrimitiveType::F64),
-        Reserved::Unsigned => Err(PrimitiveTypeError::UnsignedError),
-        _ => Err(PrimitiveTypeError::NotPrimitiveTypeErr),
+    let count = |target: Reserved| words.iter().filter(|word| **word == target).count();
+    let (void, bool_, char_, short, int, long, signed, unsigned) = (
+        count(Reserved::Void),
+        count(Reserved::Bool),
+        count(Reserved::Char),
+        count(Reserved::Short),
+        count(Reserved::Int),
+        count(Reserved::Long),
+        count(Reserved::Signed),
+        count(Reserved::Unsigned),
+    );
+    if words.is_empty()
+        || words.len() != void + bool_ + char_ + short + int + long + signed + unsigned
+    {
+        return Err(PrimitiveTypeError::NotPrimitiveTypeErr);
+    }
+    // signedとunsignedは同時に指定できず, それぞれ1回まで
+    if signed + unsigned > 1 || int > 1 {
+        return Err(PrimitiveTypeError::InvalidCombination);
+    }
+    let is_unsigned = unsigned == 1;
+    let sign_specified = signed + unsigned == 1;
+
+    match (void, bool_, char_, short, int, long) {
+        (1, 0, 0, 0, 0, 0) if !sign_specified => Ok(PrimitiveType::Void),
+        (0, 1, 0, 0, 0, 0) if !sign_specified => Ok(PrimitiveType::Bool),
+        (0, 0, 1, 0, 0, 0) if is_unsigned => Ok(PrimitiveType::U8),
+        (0, 0, 1, 0, 0, 0) => Ok(PrimitiveType::I8),
+        (0, 0, 0, 1, _, 0) if is_unsigned => Ok(PrimitiveType::U16),
+        (0, 0, 0, 1, _, 0) => Ok(PrimitiveType::I16),
+        (0, 0, 0, 0, _, 0) if is_unsigned => Ok(PrimitiveType::U32),
+        (0, 0, 0, 0, _, 0) => Ok(PrimitiveType::I32),
+        (0, 0, 0, 0, _, 1 | 2) if is_unsigned => Ok(PrimitiveType::U64),
+        (0, 0, 0, 0, _, 1 | 2) => Ok(PrimitiveType::I64),
+        _ => Err(PrimitiveTypeError::InvalidCombination),
     }
 }
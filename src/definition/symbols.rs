@@ -44,6 +44,7 @@ pub enum Symbol {
     LeftCurlyBracket,   // {
     RightCurlyBracket,  // }
     Deref,              // ->
+    Ellipsis,           // ...
 }
 
 pub fn get_token_symbol(token: String) -> Symbol {
@@ -92,6 +93,7 @@ pub fn get_token_symbol(token: String) -> Symbol {
         "{" => Symbol::LeftCurlyBracket,
         "}" => Symbol::RightCurlyBracket,
         "->" => Symbol::Deref,
+        "..." => Symbol::Ellipsis,
         _ => unreachable!(),
     }
 }
@@ -5,6 +5,7 @@ use std::{collections::HashMap, marker::PhantomData, rc::Rc};
 #[derive(PartialEq, Clone, Debug)]
 pub enum PrimitiveType {
     Void,
+    Bool,
     U8,
     I8,
     U16,
@@ -18,13 +19,15 @@ pub enum PrimitiveType {
 }
 
 pub enum PrimitiveTypeError {
-    UnsignedError,
+    InvalidCombination,
     NotPrimitiveTypeErr,
 }
 
 #[derive(Debug, Clone)]
 // 型定義
 // 配列型でインデックスアクセスを行わない場合はポインタ型に変換されるようにする
+// is_const - 代入できない型(const修飾)
+// is_volatile - 読み書きを省略, 統合してはならない型(volatile修飾)
 pub struct Type {
     pub size: usize,
     pub primitive: Option<PrimitiveType>,
@@ -33,11 +36,14 @@ pub struct Type {
     pub struct_name: Option<String>,
     pub struct_members: Option<Rc<HashMap<String, (usize, Type)>>>,
     pub function: Option<Rc<Function>>,
+    pub is_const: bool,
+    pub is_volatile: bool,
     _private: PhantomData<()>, // コンストラクタからのみ作成できるようにする
 }
 
 // 定義済みの関数と新たに宣言した関数が同じ引数型, 戻り値型の関数であるかの比較に使用するため,
 // ポインタ型, 配列型, 関数型の指す先はすでに定義済みのプリミティブ型か構造体型に行き着く
+// 型自体の修飾子は比較しないが, ポインタの指す先の型の修飾子は比較する
 impl PartialEq for Type {
     fn eq(&self, rhs: &Self) -> bool {
         if self.size != rhs.size {
@@ -45,30 +51,32 @@ impl PartialEq for Type {
         }
 
         // お互いにプリミティブ型の場合
-        if self.primitive.is_some() && rhs.primitive.is_some() {
-            return self.primitive.as_ref().unwrap() == rhs.primitive.as_ref().unwrap();
+        if let (Some(self_primitive), Some(rhs_primitive)) = (&self.primitive, &rhs.primitive) {
+            return self_primitive == rhs_primitive;
         }
 
         // お互いにポインタ型の場合
-        if self.pointer.is_some() && self.pointer.is_some() {
-            return self.pointer.as_ref().unwrap() == rhs.pointer.as_ref().unwrap();
+        if let (Some(self_pointer), Some(rhs_pointer)) = (&self.pointer, &rhs.pointer) {
+            return self_pointer.is_const == rhs_pointer.is_const
+                && self_pointer.is_volatile == rhs_pointer.is_volatile
+                && self_pointer == rhs_pointer;
         }
 
         // お互いに配列型の場合
-        if self.array.is_some() && self.array.is_some() {
-            return self.array.as_ref().unwrap() == rhs.array.as_ref().unwrap();
+        if let (Some(self_array), Some(rhs_array)) = (&self.array, &rhs.array) {
+            return self_array == rhs_array;
         }
 
         // お互いに構造体型の場合
         // 同じ構造体名であれば良い
         // 無名構造体の比較は関数の引数チェックでは行われない
-        if self.struct_name.is_some() && self.struct_name.is_some() {
-            return self.struct_name.as_ref().unwrap() == rhs.struct_name.as_ref().unwrap();
+        if let (Some(self_name), Some(rhs_name)) = (&self.struct_name, &rhs.struct_name) {
+            return self_name == rhs_name;
         }
 
         // お互いに関数型の場合
-        if self.function.is_some() && self.function.is_some() {
-            return self.function.as_ref().unwrap() == rhs.function.as_ref().unwrap();
+        if let (Some(self_function), Some(rhs_function)) = (&self.function, &rhs.function) {
+            return self_function == rhs_function;
         }
 
         // それ以外の場合はfalse
@@ -87,6 +95,8 @@ impl Type {
             struct_name: None,
             struct_members: None,
             function: None,
+            is_const: false,
+            is_volatile: false,
             _private: PhantomData,
         }
     }
@@ -100,6 +110,8 @@ impl Type {
             struct_name: None,
             struct_members: None,
             function: None,
+            is_const: false,
+            is_volatile: false,
             _private: PhantomData,
         }
     }
@@ -116,6 +128,8 @@ impl Type {
             struct_name: None,
             struct_members: None,
             function: None,
+            is_const: false,
+            is_volatile: false,
             _private: PhantomData,
         }
     }
@@ -123,26 +137,30 @@ impl Type {
     // 無名構造体は空文字列を渡す
     pub fn new_stuct(name: &str, members: Vec<(&str, Type)>) -> Self {
         let mut offset: usize = 0;
+        let mut struct_align: usize = 1;
         let mut member_vec: HashMap<String, (usize, Type)> = HashMap::new();
         for (name, member) in members {
             let member_size = member.size;
-            // このメンバーを加えることでアライメント境界を超える場合はオフセットをアライメント境界まで動かす
+            // メンバーのオフセットはそのメンバーのアライメント境界まで動かす
             // すでにアライメント境界のときは何もしない
-            if offset % 8 != 0 && offset / 8 != (offset + member_size) / 8 {
-                offset += 8 - (offset % 8);
-            }
+            let member_align = member.get_align();
+            offset = align_to(offset, member_align);
+            struct_align = std::cmp::max(struct_align, member_align);
 
             member_vec.insert(name.to_string(), (offset, member));
             offset += member_size;
         }
         Type {
-            size: offset,
+            // 構造体の配列でも各要素がアライメント境界に並ぶよう, サイズはアライメントの倍数にする
+            size: align_to(offset, struct_align),
             primitive: None,
             pointer: None,
             array: None,
             struct_name: Some(name.to_string()),
             struct_members: Some(Rc::new(member_vec)),
             function: None,
+            is_const: false,
+            is_volatile: false,
             _private: PhantomData,
         }
     }
@@ -159,22 +177,58 @@ impl Type {
             struct_name: None,
             struct_members: None,
             function: Some(Rc::new(function)),
+            is_const: false,
+            is_volatile: false,
             _private: PhantomData,
         }
     }
 
+    // 修飾子を追加した型
+    pub fn with_qualifiers(&self, is_const: bool, is_volatile: bool) -> Self {
+        let mut type_ = self.clone();
+        type_.is_const |= is_const;
+        type_.is_volatile |= is_volatile;
+        type_
+    }
+
+    // 修飾子を除いた型
+    pub fn unqualified(&self) -> Self {
+        let mut type_ = self.clone();
+        type_.is_const = false;
+        type_.is_volatile = false;
+        type_
+    }
+
+    // otherの修飾子を全て持っているか
+    pub fn has_qualifiers_of(&self, other: &Type) -> bool {
+        (self.is_const || !other.is_const) && (self.is_volatile || !other.is_volatile)
+    }
+
+    // 型のアライメント
+    // プリミティブ型はそのサイズ, 配列は要素型, 構造体はメンバーの最大アライメントとなる
+    pub fn get_align(&self) -> usize {
+        if let Some((_count, elem_type)) = &self.array {
+            elem_type.get_align()
+        } else if let Some(members) = &self.struct_members {
+            members
+                .values()
+                .map(|(_offset, member)| member.get_align())
+                .max()
+                .unwrap_or(1)
+        } else {
+            std::cmp::max(self.size, 1)
+        }
+    }
+
     pub fn is_primitive_type(&self) -> bool {
         self.primitive.is_some()
     }
 
     pub fn is_integer_type(&self) -> bool {
-        if self.primitive.is_none() {
-            false
+        if let Some(primitive) = &self.primitive {
+            !matches!(primitive, PrimitiveType::F32 | PrimitiveType::F64)
         } else {
-            !matches!(
-                self.primitive.as_ref().unwrap(),
-                PrimitiveType::F32 | PrimitiveType::F64
-            )
+            false
         }
     }
 
@@ -182,9 +236,100 @@ impl Type {
         self.pointer.is_some()
     }
 
+    pub fn is_void_pointer(&self) -> bool {
+        self.pointer
+            .as_ref()
+            .is_some_and(|pointer_type| pointer_type.primitive == Some(PrimitiveType::Void))
+    }
+
     pub fn is_array(&self) -> bool {
         self.array.is_some()
     }
+
+    pub fn is_struct(&self) -> bool {
+        self.struct_members.is_some()
+    }
+
+    // 同じ構造体型か
+    // 無名構造体は構造体名が空文字列なので, メンバーも比較する
+    pub fn is_same_struct(&self, other: &Type) -> bool {
+        self.is_struct()
+            && other.is_struct()
+            && self.struct_name == other.struct_name
+            && self.struct_members == other.struct_members
+    }
+
+    pub fn is_unsigned_integer(&self) -> bool {
+        matches!(
+            self.primitive,
+            Some(
+                PrimitiveType::Bool
+                    | PrimitiveType::U8
+                    | PrimitiveType::U16
+                    | PrimitiveType::U32
+                    | PrimitiveType::U64
+            )
+        )
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.primitive == Some(PrimitiveType::Bool)
+    }
+
+    // 構造体メンバーのオフセットと型を取得する
+    pub fn get_struct_member(&self, name: &str) -> Option<(usize, Type)> {
+        self.struct_members
+            .as_ref()
+            .and_then(|members| members.get(name).cloned())
+    }
+
+    // 構造体メンバーを宣言順(オフセット順)に取得する
+    pub fn get_struct_members_in_order(&self) -> Vec<(String, usize, Type)> {
+        let mut members: Vec<(String, usize, Type)> = self
+            .struct_members
+            .as_ref()
+            .map(|members| {
+                members
+                    .iter()
+                    .map(|(name, (offset, type_))| (name.clone(), *offset, type_.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        members.sort_by_key(|(_name, offset, _type)| *offset);
+        members
+    }
+
+    // va_list型, または引数として渡されたva_list型(要素へのポインタ)であるか
+    pub fn is_va_list(&self) -> bool {
+        let tag_type = if let Some((_count, elem_type)) = &self.array {
+            elem_type
+        } else if let Some(pointer_type) = &self.pointer {
+            pointer_type
+        } else {
+            return false;
+        };
+        tag_type.struct_name.as_deref() == Some("__va_list_tag")
+    }
+}
+
+// offsetをalignの倍数に切り上げる
+pub fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+// System V ABIのva_list型
+// { gp_offset, fp_offset, overflow_arg_area, reg_save_area } の構造体を要素数1の配列としたもの
+fn new_va_list_type(type_u32: &Type, type_void: &Type) -> Type {
+    let va_list_tag = Type::new_stuct(
+        "__va_list_tag",
+        vec![
+            ("gp_offset", type_u32.clone()),
+            ("fp_offset", type_u32.clone()),
+            ("overflow_arg_area", Type::new_pointer(type_void.clone())),
+            ("reg_save_area", Type::new_pointer(type_void.clone())),
+        ],
+    );
+    Type::new_array(1, va_list_tag)
 }
 
 pub struct TypesDefinitions {
@@ -197,6 +342,7 @@ impl TypesDefinitions {
             dict: HashMap::new(),
         };
         let type_void = Type::new_primitive(PrimitiveType::Void, 0);
+        let type_bool = Type::new_primitive(PrimitiveType::Bool, 1);
         let type_u8 = Type::new_primitive(PrimitiveType::U8, 1);
         let type_u16 = Type::new_primitive(PrimitiveType::U16, 2);
         let type_u32 = Type::new_primitive(PrimitiveType::U32, 4);
@@ -207,7 +353,9 @@ impl TypesDefinitions {
         let type_i64 = Type::new_primitive(PrimitiveType::I64, 8);
         let type_f32 = Type::new_primitive(PrimitiveType::F32, 4);
         let type_f64 = Type::new_primitive(PrimitiveType::F64, 8);
+        let type_va_list = new_va_list_type(&type_u32, &type_void);
         types.register_type("void", type_void);
+        types.register_type("_Bool", type_bool);
         types.register_type("unsigned char", type_u8);
         types.register_type("unsigned short", type_u16);
         types.register_type("unsigned int", type_u32);
@@ -218,6 +366,8 @@ impl TypesDefinitions {
         types.register_type("long", type_i64);
         types.register_type("float", type_f32);
         types.register_type("double", type_f64);
+        // <stdarg.h>を読み込めないので, va_listは組み込みの型名とする
+        types.register_type("va_list", type_va_list);
         types
     }
 
@@ -227,7 +377,10 @@ impl TypesDefinitions {
 
     pub fn get_number_type(&self, num_type: &Number) -> Type {
         match num_type {
-            Number::U64(_) => self.dict["long"].clone(),
+            // 整数定数はintで表せればint, longで表せればlong, それ以外はunsigned longとする
+            Number::U64(num) if *num <= i32::MAX as u64 => self.dict["int"].clone(),
+            Number::U64(num) if *num <= i64::MAX as u64 => self.dict["long"].clone(),
+            Number::U64(_) => self.dict["unsigned long"].clone(),
             Number::F64(_) => self.dict["double"].clone(),
         }
     }
@@ -235,8 +388,17 @@ impl TypesDefinitions {
     pub fn get_primitive_type(&self, primitive_type: &PrimitiveType) -> Type {
         match primitive_type {
             PrimitiveType::Void => self.dict["void"].clone(),
+            PrimitiveType::Bool => self.dict["_Bool"].clone(),
+            PrimitiveType::U8 => self.dict["unsigned char"].clone(),
+            PrimitiveType::I8 => self.dict["char"].clone(),
+            PrimitiveType::U16 => self.dict["unsigned short"].clone(),
+            PrimitiveType::I16 => self.dict["short"].clone(),
+            PrimitiveType::U32 => self.dict["unsigned int"].clone(),
+            PrimitiveType::I32 => self.dict["int"].clone(),
+            PrimitiveType::U64 => self.dict["unsigned long"].clone(),
             PrimitiveType::I64 => self.dict["long"].clone(),
-            _ => unreachable!(),
+            PrimitiveType::F32 => self.dict["float"].clone(),
+            PrimitiveType::F64 => self.dict["double"].clone(),
         }
     }
 
@@ -1,18 +1,64 @@
-use crate::definition::types::Type;
-use std::{collections::HashMap, ops::Deref, rc::Rc};
+use crate::definition::linkage::{resolve_linkage, Linkage, LinkageError, StorageClass};
+use crate::definition::types::{align_to, Type};
+use crate::token::token::TokenInfo;
+use std::{cell::Cell, collections::HashMap, ops::Deref, rc::Rc};
 
+// 静的記憶域期間を持つ変数の初期値
+// 初期値のない変数は.bssに, 初期値のある変数は.dataに配置する
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaticData {
+    Integer(usize, u64),  // (サイズ, 値)
+    Zero(usize),          // 0で埋めるバイト数
+    Address(String, i64), // (ラベル, オフセット) 関数や変数のアドレス(8バイト)
+}
+
+/// 静的記憶域期間を持つ変数(グローバル変数, 関数内static変数)
+///
+/// member
+/// - label - アセンブリ上のラベル  
+///   関数内static変数は他の変数と衝突しないように "関数名.変数名.通し番号" とする
+/// - defined - この翻訳単位で領域を確保するか(extern宣言のみの場合はfalse)
 #[derive(Debug, PartialEq)]
 pub struct GlobalVariable {
     pub name: String,
+    pub label: String,
     pub type_: Type,
+    pub linkage: Linkage,
+    pub defined: bool,
+    pub init: Option<Vec<StaticData>>,
 }
 
+/// ローカル変数(関数の引数を含む)
+///
+/// member
+/// - info - 宣言した位置
+/// - used - 式中で参照されたか(未使用変数の警告に使用する)
 #[derive(Debug, PartialEq)]
 pub struct LocalVariable {
     scope_depth: usize,
     pub name: String,
     pub frame_offset: usize,
     pub type_: Type,
+    pub info: TokenInfo,
+    used: Cell<bool>,
+}
+
+// 変数の宣言に関する警告
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableWarning {
+    UnusedVariable(String, TokenInfo),
+    UnusedParameter(String, TokenInfo),
+    Shadow(String, TokenInfo),
+}
+
+impl VariableWarning {
+    pub fn get_info(&self) -> &TokenInfo {
+        match self {
+            VariableWarning::UnusedVariable(_, info)
+            | VariableWarning::UnusedParameter(_, info)
+            | VariableWarning::Shadow(_, info) => info,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -59,6 +105,13 @@ impl Variable {
             Variable::LocalVal(local_val) => local_val.type_.is_array(),
         }
     }
+
+    // ローカル変数が式中で参照されたことを記録する
+    pub fn mark_used(&self) {
+        if let Variable::LocalVal(local_val) = self {
+            local_val.used.set(true);
+        }
+    }
 }
 
 struct LocalScope {
@@ -69,29 +122,41 @@ struct LocalScope {
 /// 変数宣言情報
 ///
 /// member
+/// - global_val_names - グローバル変数の宣言順(出力順を宣言順にするため)
+/// - static_local_vals - 関数内static変数
+/// - local_vals - ブロックスコープで参照できる変数  
+///   関数内static変数, ブロック内extern宣言はグローバル変数として登録される
 /// - hidden_local - より深いスコープで同名のローカル変数が宣言された場合に,
-/// 宣言済みのローカル変数を退避させるためのテーブル  
-/// キーが変数名, 値が退避ローカル変数ベクトル(ベクトル後方ほど深いスコープで宣言された退避ローカル変数)
+///   宣言済みのローカル変数を退避させるためのテーブル  
+///   キーが変数名, 値が退避ローカル変数ベクトル(ベクトル後方ほど深いスコープで宣言された退避ローカル変数)
+/// - warnings - 未使用の変数, 引数と変数の隠蔽の警告  
+///   変数は参照されないままスコープを抜けた時点で未使用とする
 pub struct VariableDeclarations {
     global_vals: HashMap<String, Rc<GlobalVariable>>,
-    local_vals: HashMap<String, Rc<LocalVariable>>,
+    global_val_names: Vec<String>,
+    static_local_vals: Vec<Rc<GlobalVariable>>,
+    local_vals: HashMap<String, Variable>,
     local_scopes: Vec<LocalScope>,
     current_frame_offset: usize,
     max_frame_offset: usize,
     local_scope_depth: usize,
-    hidden_local: HashMap<String, Vec<Rc<LocalVariable>>>,
+    hidden_local: HashMap<String, Vec<Variable>>,
+    warnings: Vec<VariableWarning>,
 }
 
 impl VariableDeclarations {
     pub fn new() -> Self {
         let mut val_declarations = VariableDeclarations {
             global_vals: HashMap::new(),
+            global_val_names: vec![],
+            static_local_vals: vec![],
             local_vals: HashMap::new(),
             local_scopes: vec![],
             current_frame_offset: 8, // rbp分加わる
             max_frame_offset: 8,     // rbp分加わる
             local_scope_depth: 0,
             hidden_local: HashMap::new(),
+            warnings: vec![],
         };
         let args_scope = LocalScope {
             frame_offset: val_declarations.current_frame_offset,
@@ -106,13 +171,46 @@ impl VariableDeclarations {
     }
 
     // グローバル変数を宣言
-    pub fn declare_global_val(&mut self, name: &str, type_: Type) -> Result<Variable, ()> {
-        if self.global_vals.get(name).is_some() {
-            return Err(());
+    // 同じ変数の複数回宣言は, 型とリンケージが一致する場合のみ許可する
+    // 初期値付きの定義は1度のみ可能
+    pub fn declare_global_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+        storage_class: StorageClass,
+        init: Option<Vec<StaticData>>,
+    ) -> Result<Variable, LinkageError> {
+        let prev_val = self.global_vals.get(name).cloned();
+        let linkage = resolve_linkage(
+            storage_class,
+            prev_val.as_ref().map(|prev_val| prev_val.linkage),
+            false,
+        )?;
+
+        let mut defined = storage_class != StorageClass::Extern || init.is_some();
+        let mut init = init;
+        if let Some(prev_val) = prev_val {
+            if prev_val.type_ != type_ {
+                return Err(LinkageError::ConflictingType);
+            }
+            if init.is_some() && prev_val.init.is_some() {
+                return Err(LinkageError::Redefinition);
+            }
+            defined |= prev_val.defined;
+            if init.is_none() {
+                init = prev_val.init.clone();
+            }
+        } else {
+            self.global_val_names.push(name.to_string());
         }
+
         let new_globalval = Rc::new(GlobalVariable {
             name: name.to_string(),
+            label: name.to_string(),
             type_,
+            linkage,
+            defined,
+            init,
         });
         self.global_vals.insert(name.to_string(), new_globalval);
         Ok(Variable::GlobalVal(
@@ -120,58 +218,137 @@ impl VariableDeclarations {
         ))
     }
 
-    // ローカル変数を現在のスコープで宣言
-    pub fn declare_local_val(&mut self, name: &str, type_: Type) -> Result<Variable, ()> {
-        // すでに同じローカル変数名が登録されている場合はそのローカル変数をhidden_localに対比させる
-        if let Some(same_name_val) = self.local_vals.remove(name) {
-            // 現在のスコープですでに宣言されている場合はエラー
-            if self.local_scope_depth == same_name_val.scope_depth {
-                return Err(());
+    // 関数内static変数を現在のスコープで宣言
+    // 実体はグローバル変数として確保するが, 変数名で参照できるのは宣言したスコープ内のみ
+    pub fn declare_static_local_val(
+        &mut self,
+        name: &str,
+        func_name: &str,
+        type_: Type,
+        init: Option<Vec<StaticData>>,
+    ) -> Result<Variable, ()> {
+        let label = format!("{}.{}.{}", func_name, name, self.static_local_vals.len());
+        let static_val = Rc::new(GlobalVariable {
+            name: name.to_string(),
+            label,
+            type_,
+            linkage: Linkage::None,
+            defined: true,
+            init,
+        });
+        let val = Variable::GlobalVal(static_val.clone());
+        self.register_local_name(name, val.clone())?;
+        self.static_local_vals.push(static_val);
+        Ok(val)
+    }
+
+    // ブロック内でextern宣言された変数を現在のスコープで宣言
+    // 同じスコープで同じ変数がextern宣言されている場合は許可する
+    pub fn declare_extern_local_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+    ) -> Result<Variable, LinkageError> {
+        let val = self.declare_global_val(name, type_, StorageClass::Extern, None)?;
+        if self.is_declared_in_current_scope(name) {
+            if self.local_vals.get(name) == Some(&val) {
+                return Ok(val);
             } else {
-                // すでに同じ変数名が複数宣言され, 秘匿済みの場合
-                if let Some(same_name_vals) = self.hidden_local.get_mut(name) {
-                    // 新たに追加するローカル変数のスコープを抜けたら再度追加できるように最後尾に追加しする
-                    same_name_vals.push(same_name_val);
-                } else {
-                    let hidden_vec = vec![same_name_val];
-                    self.hidden_local.insert(name.to_string(), hidden_vec);
-                }
+                return Err(LinkageError::ConflictingLinkage);
             }
         }
+        self.register_local_name(name, val.clone()).unwrap();
+        Ok(val)
+    }
 
-        // ローカル変数をスタックに追加すると8バイトアライメントを超えてしまう場合は,
-        // スタックフレームをアライメント境界まで増やしてからローカル変数を追加する
-        // すでにアライメント境界のときは何もしない
-        if self.current_frame_offset % 8 != 0
-            && self.current_frame_offset / 8 != (self.current_frame_offset + type_.size) / 8
-        {
-            self.current_frame_offset += 8 - (self.current_frame_offset % 8);
-        }
+    // 静的記憶域期間を持ち, この翻訳単位で領域を確保する変数を宣言順に取得する
+    pub fn get_static_vals(&self) -> Vec<Variable> {
+        let global_vals = self
+            .global_val_names
+            .iter()
+            .map(|name| self.global_vals.get(name).unwrap());
+        global_vals
+            .chain(self.static_local_vals.iter())
+            .filter(|val| val.defined)
+            .map(|val| Variable::GlobalVal(val.clone()))
+            .collect()
+    }
 
-        // ローカル変数を必要な情報を追加して登録
-        let val_size = type_.size;
+    fn is_declared_in_current_scope(&self, name: &str) -> bool {
+        self.local_scopes[self.local_scope_depth]
+            .scope_val_names
+            .iter()
+            .any(|val_name| val_name == name)
+    }
 
+    // 変数名を現在のスコープに登録する
+    fn register_local_name(&mut self, name: &str, val: Variable) -> Result<(), ()> {
+        // 現在のスコープですでに宣言されている場合はエラー
+        if self.is_declared_in_current_scope(name) {
+            return Err(());
+        }
+        // すでに同じローカル変数名が登録されている場合はそのローカル変数をhidden_localに対比させる
+        if let Some(same_name_val) = self.local_vals.remove(name) {
+            // すでに同じ変数名が複数宣言され, 秘匿済みの場合
+            if let Some(same_name_vals) = self.hidden_local.get_mut(name) {
+                // 新たに追加するローカル変数のスコープを抜けたら再度追加できるように最後尾に追加しする
+                same_name_vals.push(same_name_val);
+            } else {
+                let hidden_vec = vec![same_name_val];
+                self.hidden_local.insert(name.to_string(), hidden_vec);
+            }
+        }
         self.local_scopes[self.local_scope_depth]
             .scope_val_names
             .push(name.to_string());
-        let local_val = LocalVariable {
+        self.local_vals.insert(name.to_string(), val);
+        Ok(())
+    }
+
+    // ローカル変数を現在のスコープで宣言
+    // 外側のスコープの変数やグローバル変数を隠す場合は警告を記録する
+    pub fn declare_local_val(
+        &mut self,
+        name: &str,
+        type_: Type,
+        info: TokenInfo,
+    ) -> Result<Variable, ()> {
+        if self.is_declared_in_current_scope(name) {
+            return Err(());
+        }
+        if self.get_variable(name).is_some() {
+            self.warnings
+                .push(VariableWarning::Shadow(name.to_string(), info));
+        }
+
+        // ローカル変数の先頭アドレスは rbp - frame_offset とし,
+        // 配列や構造体は先頭アドレスからrbpに向かって配置する
+        // 先頭アドレスが変数のアライメント境界となるようにスタックフレームを増やす
+        // (rbpは16バイト境界にある)
+        let frame_offset = align_to(
+            self.current_frame_offset - 8 + type_.size,
+            type_.get_align(),
+        );
+
+        // ローカル変数を必要な情報を追加して登録
+        let local_val = Variable::LocalVal(Rc::new(LocalVariable {
             scope_depth: self.local_scope_depth,
             name: name.to_string(),
-            frame_offset: self.current_frame_offset,
+            frame_offset,
             type_,
-        };
-        self.local_vals.insert(name.to_string(), Rc::new(local_val));
-        self.current_frame_offset += val_size;
+            info,
+            used: Cell::new(false),
+        }));
+        self.register_local_name(name, local_val.clone())?;
+        self.current_frame_offset = frame_offset + 8;
         self.max_frame_offset = std::cmp::max(self.max_frame_offset, self.current_frame_offset);
-        Ok(Variable::LocalVal(
-            self.local_vals.get(name).unwrap().clone(),
-        ))
+        Ok(local_val)
     }
 
     // 変数を取得
     pub fn get_variable(&self, name: &str) -> Option<Variable> {
         if let Some(local_val) = self.local_vals.get(name) {
-            Some(Variable::LocalVal(local_val.clone()))
+            Some(local_val.clone())
         } else {
             self.global_vals
                 .get(name)
@@ -196,7 +373,9 @@ impl VariableDeclarations {
             self.current_frame_offset = exit_scope.frame_offset;
             // 脱出するスコープに登録されているローカル変数をローカル変数テーブルから削除する
             for local_val in exit_scope.scope_val_names {
-                self.local_vals.remove(&local_val);
+                if let Some(Variable::LocalVal(val)) = self.local_vals.remove(&local_val) {
+                    self.check_unused_local_val(&val);
+                }
                 // 同じ変数名がより浅いスコープに登録されていた場合
                 if let Some(same_name_hidden_locals) = self.hidden_local.get_mut(&local_val) {
                     if let Some(deepest_same_name_hidden_local) = same_name_hidden_locals.pop() {
@@ -214,6 +393,37 @@ impl VariableDeclarations {
         }
     }
 
+    // 参照されなかったローカル変数の警告を記録する
+    // 引数スコープ(最も浅いスコープ)で宣言された変数は引数とする
+    fn check_unused_local_val(&mut self, val: &LocalVariable) {
+        if val.used.get() {
+            return;
+        }
+        let warning = if val.scope_depth == 0 {
+            VariableWarning::UnusedParameter(val.name.clone(), val.info)
+        } else {
+            VariableWarning::UnusedVariable(val.name.clone(), val.info)
+        };
+        self.warnings.push(warning);
+    }
+
+    // 関数の引数スコープに残っている引数の使用状況を確認する
+    pub fn check_unused_args(&mut self) {
+        let arg_names = self.local_scopes[0].scope_val_names.clone();
+        for arg_name in arg_names {
+            if let Some(Variable::LocalVal(val)) = self.local_vals.get(&arg_name).cloned() {
+                self.check_unused_local_val(&val);
+            }
+        }
+    }
+
+    // 記録した警告を宣言位置の順に取り出す
+    pub fn take_warnings(&mut self) -> Vec<VariableWarning> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| (warning.get_info().line, warning.get_info().pos));
+        warnings
+    }
+
     pub fn clear_local_val_scope(&mut self) {
         self.local_vals.clear();
         self.local_scopes.clear();
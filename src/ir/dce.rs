@@ -0,0 +1,299 @@
+use crate::ir::ir::*;
+use crate::ir::verify::get_reachable_blocks;
+
+// 条件が定数の分岐と, 分岐先が同じ分岐をジャンプにする
+fn fold_branches(func: &mut IrFunction) {
+    let constants = func.get_constants();
+    for block in func.blocks.iter_mut() {
+        if let Some(Terminator::Branch {
+            cond,
+            then_block,
+            else_block,
+        }) = block.terminator
+        {
+            let target = match constants[cond.0] {
+                Some(0) => else_block,
+                Some(_) => then_block,
+                None if then_block == else_block => then_block,
+                None => continue,
+            };
+            block.terminator = Some(Terminator::Jump(target));
+        }
+    }
+}
+
+// 入口から到達できないブロックを削除し, 残ったブロックの番号を振り直す
+// ブロックの順序は変えない
+fn remove_unreachable_blocks(func: &mut IrFunction) {
+    let reachable = get_reachable_blocks(func);
+    let mut new_index = vec![0; func.blocks.len()];
+    let mut count = 0;
+    for (index, is_reachable) in reachable.iter().enumerate() {
+        if *is_reachable {
+            new_index[index] = count;
+            count += 1;
+        }
+    }
+    let blocks = std::mem::take(&mut func.blocks);
+    for (mut block, is_reachable) in blocks.into_iter().zip(reachable) {
+        if !is_reachable {
+            continue;
+        }
+        let renumber = |block: &mut BlockId| *block = BlockId(new_index[block.0]);
+        match &mut block.terminator {
+            Some(Terminator::Jump(target)) => renumber(target),
+            Some(Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            }) => {
+                renumber(then_block);
+                renumber(else_block);
+            }
+            _ => {}
+        }
+        func.blocks.push(block);
+    }
+}
+
+// 結果が使われなければ削除できる命令
+// volatileなload, 関数呼び出し, va_argは副作用を持つので削除しない
+fn is_pure(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Load { volatile, .. } => !volatile,
+        Instruction::Store { .. }
+        | Instruction::Call { .. }
+        | Instruction::VaStart { .. }
+        | Instruction::VaArg { .. } => false,
+        _ => true,
+    }
+}
+
+fn count_uses(func: &IrFunction) -> Vec<usize> {
+    let mut use_counts = vec![0; func.vreg_types.len()];
+    for block in &func.blocks {
+        for instruction in &block.instructions {
+            for vreg in instruction.get_uses() {
+                use_counts[vreg.0] += 1;
+            }
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            use_counts[vreg.0] += 1;
+        }
+    }
+    use_counts
+}
+
+// 結果が読み出されない純粋な命令を削除する
+// 結果が読み出されない関数呼び出しは, 戻り値を受け取らないようにする
+fn remove_unused_instructions(func: &mut IrFunction) -> bool {
+    let use_counts = count_uses(func);
+    let mut changed = false;
+    for block in func.blocks.iter_mut() {
+        let count = block.instructions.len();
+        block
+            .instructions
+            .retain(|instruction| match instruction.get_dst() {
+                Some(dst) => use_counts[dst.0] != 0 || !is_pure(instruction),
+                None => true,
+            });
+        changed |= count != block.instructions.len();
+        for instruction in block.instructions.iter_mut() {
+            if let Instruction::Call { dst, .. } = instruction {
+                if dst.is_some_and(|dst| use_counts[dst.0] == 0) {
+                    *dst = None;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+// ローカル変数へのアクセス
+// offsetはrbpからのオフセットで, rbp - offset から size バイトを読み書きする
+fn overlaps((offset, size): (usize, usize), (other_offset, other_size): (usize, usize)) -> bool {
+    other_offset < offset + other_size && offset < other_offset + size
+}
+
+// 読み出されないローカル変数へのstoreを削除する
+//
+// アドレスがload, storeのアドレス以外に使われたローカル変数は, ポインタ経由で読み出される可能性がある
+// ポインタはオブジェクトの先頭からrbp方向にしか進まないので,
+// そのようなアドレスのうち最もrbpから遠い位置より, rbp側にかかるstoreは残す
+fn remove_dead_stores(func: &mut IrFunction) -> bool {
+    let vreg_count = func.vreg_types.len();
+    let mut local_addresses = vec![None; vreg_count];
+    let mut def_counts = vec![0; vreg_count];
+    for instruction in func.blocks.iter().flat_map(|block| &block.instructions) {
+        if let Some(dst) = instruction.get_dst() {
+            def_counts[dst.0] += 1;
+        }
+        if let Instruction::LocalAddress { dst, offset } = instruction {
+            local_addresses[dst.0] = Some(*offset);
+        }
+    }
+    for (offset, count) in local_addresses.iter_mut().zip(def_counts) {
+        if count != 1 {
+            *offset = None;
+        }
+    }
+
+    let mut loads = vec![];
+    let mut escaped_offset = None;
+    let mut escape = |vreg: VReg| {
+        if let Some(offset) = local_addresses[vreg.0] {
+            escaped_offset =
+                Some(escaped_offset.map_or(offset, |escaped: usize| escaped.max(offset)));
+        }
+    };
+    for block in &func.blocks {
+        for instruction in &block.instructions {
+            match instruction {
+                Instruction::Load { dst, address, .. } => {
+                    if let Some(offset) = local_addresses[address.0] {
+                        loads.push((offset, func.get_vreg_type(*dst).size()));
+                    }
+                }
+                Instruction::Store { src, .. } => escape(*src),
+                _ => instruction.get_uses().into_iter().for_each(&mut escape),
+            }
+        }
+        block
+            .terminator
+            .iter()
+            .flat_map(|term| term.get_uses())
+            .for_each(&mut escape);
+    }
+
+    let is_dead = |instruction: &Instruction| match instruction {
+        Instruction::Store {
+            type_,
+            address,
+            volatile: false,
+            ..
+        } => local_addresses[address.0].is_some_and(|offset| {
+            let access = (offset, type_.size());
+            !loads.iter().any(|load| overlaps(access, *load))
+                && escaped_offset.is_none_or(|escaped| escaped + type_.size() <= offset)
+        }),
+        _ => false,
+    };
+    let mut changed = false;
+    for block in func.blocks.iter_mut() {
+        let count = block.instructions.len();
+        block
+            .instructions
+            .retain(|instruction| !is_dead(instruction));
+        changed |= count != block.instructions.len();
+    }
+    changed
+}
+
+fn renumber_instruction(instruction: &mut Instruction, new_vregs: &[usize]) {
+    let renumber = |vreg: &mut VReg| *vreg = VReg(new_vregs[vreg.0]);
+    match instruction {
+        Instruction::Const { dst, .. }
+        | Instruction::LocalAddress { dst, .. }
+        | Instruction::GlobalAddress { dst, .. }
+        | Instruction::FunctionAddress { dst, .. }
+        | Instruction::Param { dst, .. } => renumber(dst),
+        Instruction::Copy { dst, src }
+        | Instruction::Unary { dst, src, .. }
+        | Instruction::Cast { dst, src } => {
+            renumber(dst);
+            renumber(src);
+        }
+        Instruction::Binary {
+            dst, left, right, ..
+        }
+        | Instruction::Compare {
+            dst, left, right, ..
+        } => {
+            renumber(dst);
+            renumber(left);
+            renumber(right);
+        }
+        Instruction::Load { dst, address, .. } => {
+            renumber(dst);
+            renumber(address);
+        }
+        Instruction::Store { address, src, .. } => {
+            renumber(address);
+            renumber(src);
+        }
+        Instruction::Call {
+            dst, callee, args, ..
+        } => {
+            dst.iter_mut().for_each(renumber);
+            if let Callee::Indirect(address) = callee {
+                renumber(address);
+            }
+            args.iter_mut().for_each(renumber);
+        }
+        Instruction::VaStart {
+            va_list,
+            reg_save_area,
+            ..
+        } => {
+            renumber(va_list);
+            renumber(reg_save_area);
+        }
+        Instruction::VaArg { dst, va_list } => {
+            renumber(dst);
+            renumber(va_list);
+        }
+    }
+}
+
+// 命令から削除された仮想レジスタを詰めて番号を振り直す
+fn compact_vregs(func: &mut IrFunction) {
+    let mut used = vec![false; func.vreg_types.len()];
+    for block in &func.blocks {
+        for instruction in &block.instructions {
+            for vreg in instruction
+                .get_uses()
+                .into_iter()
+                .chain(instruction.get_dst())
+            {
+                used[vreg.0] = true;
+            }
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            used[vreg.0] = true;
+        }
+    }
+    let mut new_vregs = vec![0; used.len()];
+    let mut vreg_types = vec![];
+    for (vreg, is_used) in used.iter().enumerate() {
+        if *is_used {
+            new_vregs[vreg] = vreg_types.len();
+            vreg_types.push(func.vreg_types[vreg]);
+        }
+    }
+    func.vreg_types = vreg_types;
+    for block in func.blocks.iter_mut() {
+        for instruction in block.instructions.iter_mut() {
+            renumber_instruction(instruction, &new_vregs);
+        }
+        match &mut block.terminator {
+            Some(Terminator::Branch { cond: vreg, .. }) | Some(Terminator::Return(Some(vreg))) => {
+                *vreg = VReg(new_vregs[vreg.0])
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 不要なコードの削除
+///
+/// 条件が定数の分岐をジャンプにして到達できないブロックを削除し,
+/// 読み出されないローカル変数へのstoreと結果が使われない純粋な命令を削除する
+/// volatileなload, storeは削除しない
+pub fn eliminate_dead_code(func: &mut IrFunction) {
+    fold_branches(func);
+    remove_unreachable_blocks(func);
+    // storeの削除で値が不要になり, 値の削除でアドレスの使用がなくなるため, 変化がなくなるまで繰り返す
+    while remove_dead_stores(func) | remove_unused_instructions(func) {}
+    compact_vregs(func);
+}
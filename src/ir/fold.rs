@@ -0,0 +1,326 @@
+use crate::ir::ir::*;
+
+// 値を型に合わせて切り詰め, 符号拡張またはゼロ拡張する
+// _Bool型は0以外の値を1とする
+fn normalize(value: u64, type_: IrType) -> u64 {
+    let bits = 8 * type_.size() as u32;
+    match type_ {
+        IrType::Bool => (value != 0) as u64,
+        _ if bits == 64 => value,
+        _ if type_.is_unsigned() => value & ((1 << bits) - 1),
+        _ => (((value << (64 - bits)) as i64) >> (64 - bits)) as u64,
+    }
+}
+
+// 型に合わせて符号ありまたは符号なしの値として解釈する
+fn to_exact(value: u64, type_: IrType) -> i128 {
+    if type_.is_unsigned() {
+        value as i128
+    } else {
+        value as i64 as i128
+    }
+}
+
+// 符号あり整数型で表現できる値か
+// 符号あり整数型のオーバーフローは未定義動作なので, 表現できない結果は畳み込まない
+fn fits_signed(value: i128, type_: IrType) -> bool {
+    let bits = 8 * type_.size() as u32;
+    let max = (1i128 << (bits - 1)) - 1;
+    (-max - 1..=max).contains(&value)
+}
+
+// 定数同士の二項演算を計算する
+// ゼロ除算, 符号あり整数型のオーバーフロー, 型の幅以上のシフトなど未定義動作となる演算はNoneを返す
+fn fold_binary(
+    op: BinaryOp,
+    dst_type: IrType,
+    (left, left_type): (u64, IrType),
+    (right, right_type): (u64, IrType),
+) -> Option<u64> {
+    let signed = !dst_type.is_unsigned();
+    let bits = 8 * dst_type.size() as i128;
+    let exact_left = to_exact(left, left_type);
+    let exact_right = to_exact(right, right_type);
+    let result = match op {
+        BinaryOp::Add => exact_left + exact_right,
+        BinaryOp::Sub => exact_left - exact_right,
+        BinaryOp::Mul if signed => exact_left.checked_mul(exact_right)?,
+        BinaryOp::Mul => left.wrapping_mul(right) as i128,
+        BinaryOp::Div | BinaryOp::Rem if right == 0 => return None,
+        BinaryOp::Div | BinaryOp::Rem if signed => {
+            // 商が表現できない場合(最小値 / -1)は剰余も未定義動作となる
+            let quotient = exact_left / exact_right;
+            if !fits_signed(quotient, dst_type) {
+                return None;
+            }
+            if op == BinaryOp::Div {
+                quotient
+            } else {
+                exact_left % exact_right
+            }
+        }
+        BinaryOp::Div => (left / right) as i128,
+        BinaryOp::Rem => (left % right) as i128,
+        BinaryOp::BitAnd => (left & right) as i128,
+        BinaryOp::BitOr => (left | right) as i128,
+        BinaryOp::BitXor => (left ^ right) as i128,
+        BinaryOp::Shl | BinaryOp::Shr if !(0..bits).contains(&exact_right) => return None,
+        // 負の値の左シフトは未定義動作
+        BinaryOp::Shl if signed && exact_left < 0 => return None,
+        BinaryOp::Shl if signed => exact_left << exact_right,
+        BinaryOp::Shl => (left << exact_right) as i128,
+        BinaryOp::Shr if signed => ((left as i64) >> exact_right) as i128,
+        BinaryOp::Shr => (left >> exact_right) as i128,
+    };
+    if signed && !fits_signed(result, dst_type) {
+        return None;
+    }
+    Some(normalize(result as u64, dst_type))
+}
+
+fn fold_compare(op: CompareOp, left: u64, right: u64) -> u64 {
+    let (signed_left, signed_right) = (left as i64, right as i64);
+    let result = match op {
+        CompareOp::Eq => left == right,
+        CompareOp::Ne => left != right,
+        CompareOp::Lt => signed_left < signed_right,
+        CompareOp::Le => signed_left <= signed_right,
+        CompareOp::Gt => signed_left > signed_right,
+        CompareOp::Ge => signed_left >= signed_right,
+        CompareOp::ULt => left < right,
+        CompareOp::ULe => left <= right,
+        CompareOp::UGt => left > right,
+        CompareOp::UGe => left >= right,
+    };
+    result as u64
+}
+
+// 全ての読み出し元が定数の命令を定数命令にする
+fn fold_instruction(
+    instruction: &Instruction,
+    func: &IrFunction,
+    constants: &[Option<u64>],
+) -> Option<Instruction> {
+    let constant = |vreg: VReg| constants[vreg.0].map(|value| (value, func.get_vreg_type(vreg)));
+    let dst = instruction.get_dst()?;
+    let dst_type = func.get_vreg_type(dst);
+    let value = match instruction {
+        Instruction::Copy { src, .. } => constant(*src)?.0,
+        Instruction::Cast { src, .. } => normalize(constant(*src)?.0, dst_type),
+        Instruction::Binary {
+            op, left, right, ..
+        } => fold_binary(*op, dst_type, constant(*left)?, constant(*right)?)?,
+        Instruction::Compare {
+            op, left, right, ..
+        } => normalize(
+            fold_compare(*op, constant(*left)?.0, constant(*right)?.0),
+            dst_type,
+        ),
+        Instruction::Unary {
+            op: UnaryOp::BitNot,
+            src,
+            ..
+        } => normalize(!constant(*src)?.0, dst_type),
+        Instruction::Unary {
+            op: UnaryOp::Not,
+            src,
+            ..
+        } => normalize((constant(*src)?.0 == 0) as u64, dst_type),
+        _ => return None,
+    };
+    Some(Instruction::Const { dst, value })
+}
+
+// 2のべき乗であれば指数を返す
+// 符号ありの型では正の値のみを対象とする
+fn get_power_of_two(value: u64, type_: IrType) -> Option<u32> {
+    let exact = to_exact(value, type_);
+    if exact > 0 && (exact as u64).is_power_of_two() {
+        Some((exact as u64).trailing_zeros())
+    } else {
+        None
+    }
+}
+
+struct Simplifier<'a> {
+    func: &'a mut IrFunction,
+    constants: Vec<Option<u64>>,
+    instructions: Vec<Instruction>,
+}
+
+impl Simplifier<'_> {
+    fn new_vreg(&mut self, type_: IrType) -> VReg {
+        self.func.vreg_types.push(type_);
+        VReg(self.func.vreg_types.len() - 1)
+    }
+
+    fn push_const(&mut self, type_: IrType, value: u64) -> VReg {
+        let dst = self.new_vreg(type_);
+        let value = normalize(value, type_);
+        self.instructions.push(Instruction::Const { dst, value });
+        dst
+    }
+
+    fn push_binary(&mut self, op: BinaryOp, dst: VReg, left: VReg, right: VReg) {
+        self.instructions.push(Instruction::Binary {
+            op,
+            dst,
+            left,
+            right,
+        });
+    }
+
+    // 値をそのまま使う. 型が異なる場合は結果の型に切り詰める
+    fn push_move(&mut self, dst: VReg, src: VReg) {
+        if self.func.get_vreg_type(dst) == self.func.get_vreg_type(src) {
+            self.instructions.push(Instruction::Copy { dst, src });
+        } else {
+            self.instructions.push(Instruction::Cast { dst, src });
+        }
+    }
+
+    // 符号ありの除算, 剰余のための補正した被除数
+    // 負の値は0方向に切り捨てるため, シフトの前に 2^k - 1 を加える
+    fn push_signed_dividend(&mut self, type_: IrType, left: VReg, shift: u32) -> VReg {
+        let sign_shift = self.push_const(type_, 8 * type_.size() as u64 - 1);
+        let sign = self.new_vreg(type_);
+        self.push_binary(BinaryOp::Shr, sign, left, sign_shift);
+        let bias_mask = self.push_const(type_, (1 << shift) - 1);
+        let bias = self.new_vreg(type_);
+        self.push_binary(BinaryOp::BitAnd, bias, sign, bias_mask);
+        let dividend = self.new_vreg(type_);
+        self.push_binary(BinaryOp::Add, dividend, left, bias);
+        dividend
+    }
+
+    // 片方が定数の二項演算の恒等式と, 2のべき乗の乗除算のシフト, ビット演算への置き換え
+    // 読み出し元の仮想レジスタの計算は別の命令で行うため, x * 0 を0としても副作用は失われない
+    fn simplify_binary(&mut self, op: BinaryOp, dst: VReg, left: VReg, right: VReg) -> bool {
+        let dst_type = self.func.get_vreg_type(dst);
+        let commutative = matches!(
+            op,
+            BinaryOp::Add | BinaryOp::Mul | BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor
+        );
+        let (x, constant) = match (self.constants[left.0], self.constants[right.0]) {
+            (_, Some(value)) => (left, (value, self.func.get_vreg_type(right))),
+            (Some(value), None) if commutative => (right, (value, self.func.get_vreg_type(left))),
+            _ => return false,
+        };
+        let exact = to_exact(constant.0, constant.1);
+        match op {
+            BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Shl
+            | BinaryOp::Shr
+                if exact == 0 =>
+            {
+                self.push_move(dst, x)
+            }
+            BinaryOp::Mul | BinaryOp::Div if exact == 1 => self.push_move(dst, x),
+            BinaryOp::Mul | BinaryOp::BitAnd if exact == 0 => {
+                self.instructions.push(Instruction::Const { dst, value: 0 })
+            }
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+                // 符号の扱いを変えないように, 型が揃っている整数の演算のみを置き換える
+                let x_type = self.func.get_vreg_type(x);
+                if !dst_type.is_integer()
+                    || dst_type == IrType::Bool
+                    || x_type != dst_type
+                    || constant.1 != dst_type
+                {
+                    return false;
+                }
+                let Some(shift) = get_power_of_two(constant.0, constant.1) else {
+                    return false;
+                };
+                self.reduce_strength(op, dst, x, shift);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // x * 2^k => x << k
+    // 符号なし: x / 2^k => x >> k, x % 2^k => x & (2^k - 1)
+    // 符号あり: 負の値を0方向に切り捨てるように補正してからシフト, マスクする
+    fn reduce_strength(&mut self, op: BinaryOp, dst: VReg, x: VReg, shift: u32) {
+        let type_ = self.func.get_vreg_type(dst);
+        match op {
+            BinaryOp::Mul => {
+                let count = self.push_const(type_, shift as u64);
+                self.push_binary(BinaryOp::Shl, dst, x, count);
+            }
+            BinaryOp::Div if type_.is_unsigned() => {
+                let count = self.push_const(type_, shift as u64);
+                self.push_binary(BinaryOp::Shr, dst, x, count);
+            }
+            BinaryOp::Rem if type_.is_unsigned() => {
+                let mask = self.push_const(type_, (1 << shift) - 1);
+                self.push_binary(BinaryOp::BitAnd, dst, x, mask);
+            }
+            BinaryOp::Div => {
+                let dividend = self.push_signed_dividend(type_, x, shift);
+                let count = self.push_const(type_, shift as u64);
+                self.push_binary(BinaryOp::Shr, dst, dividend, count);
+            }
+            _ => {
+                let dividend = self.push_signed_dividend(type_, x, shift);
+                let mask = self.push_const(type_, (1u64 << shift).wrapping_neg());
+                let truncated = self.new_vreg(type_);
+                self.push_binary(BinaryOp::BitAnd, truncated, dividend, mask);
+                self.push_binary(BinaryOp::Sub, dst, x, truncated);
+            }
+        }
+    }
+
+    fn simplify_block(&mut self, block: usize) -> bool {
+        let mut changed = false;
+        let instructions = std::mem::take(&mut self.func.blocks[block].instructions);
+        for instruction in instructions {
+            if let Some(folded) = fold_instruction(&instruction, self.func, &self.constants) {
+                self.instructions.push(folded);
+                changed = true;
+                continue;
+            }
+            if let Instruction::Binary {
+                op,
+                dst,
+                left,
+                right,
+            } = instruction
+            {
+                if self.simplify_binary(op, dst, left, right) {
+                    changed = true;
+                    continue;
+                }
+            }
+            self.instructions.push(instruction);
+        }
+        self.func.blocks[block].instructions = std::mem::take(&mut self.instructions);
+        changed
+    }
+}
+
+/// 定数畳み込みと代数的簡約
+///
+/// 定数同士の演算を計算し, 片方が定数の演算を恒等式やシフトで置き換える
+/// load, storeは扱わないので, volatileなアクセスは削除も統合もしない
+pub fn fold_constants(func: &mut IrFunction) {
+    loop {
+        let constants = func.get_constants();
+        let mut simplifier = Simplifier {
+            func,
+            constants,
+            instructions: vec![],
+        };
+        let mut changed = false;
+        for block in 0..simplifier.func.blocks.len() {
+            changed |= simplifier.simplify_block(block);
+        }
+        if !changed {
+            break;
+        }
+    }
+}
@@ -0,0 +1,536 @@
+use std::fmt;
+
+use crate::definition::linkage::Linkage;
+use crate::definition::types::{PrimitiveType, Type};
+
+/// 仮想レジスタ
+///
+/// 関数内で通し番号を振り, 型は関数ごとの表(IrFunction::vreg_types)で管理する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VReg(pub usize);
+
+/// 基本ブロックの番号(IrFunction::blocksのインデックス)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockId(pub usize);
+
+/// 仮想レジスタの型
+///
+/// 仮想レジスタは8バイトで, 8バイト未満の整数は型に合わせて符号拡張またはゼロ拡張した値を持つ
+/// 配列, 構造体はアドレスを値とするのでポインタ型として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrType {
+    Bool,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    Ptr,
+}
+
+impl IrType {
+    // void型(と浮動小数点型)は仮想レジスタで扱わない
+    pub fn from_type(type_: &Type) -> Option<IrType> {
+        if type_.is_pointer() || type_.is_array() || type_.is_struct() {
+            return Some(IrType::Ptr);
+        }
+        match type_.primitive.as_ref()? {
+            PrimitiveType::Bool => Some(IrType::Bool),
+            PrimitiveType::I8 => Some(IrType::I8),
+            PrimitiveType::U8 => Some(IrType::U8),
+            PrimitiveType::I16 => Some(IrType::I16),
+            PrimitiveType::U16 => Some(IrType::U16),
+            PrimitiveType::I32 => Some(IrType::I32),
+            PrimitiveType::U32 => Some(IrType::U32),
+            PrimitiveType::I64 => Some(IrType::I64),
+            PrimitiveType::U64 => Some(IrType::U64),
+            PrimitiveType::Void | PrimitiveType::F32 | PrimitiveType::F64 => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            IrType::Bool | IrType::I8 | IrType::U8 => 1,
+            IrType::I16 | IrType::U16 => 2,
+            IrType::I32 | IrType::U32 => 4,
+            IrType::I64 | IrType::U64 | IrType::Ptr => 8,
+        }
+    }
+
+    // アドレスは符号なし整数として扱う
+    pub fn is_unsigned(&self) -> bool {
+        !matches!(self, IrType::I8 | IrType::I16 | IrType::I32 | IrType::I64)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        *self != IrType::Ptr
+    }
+}
+
+impl fmt::Display for IrType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            IrType::Bool => "bool",
+            IrType::I8 => "i8",
+            IrType::U8 => "u8",
+            IrType::I16 => "i16",
+            IrType::U16 => "u16",
+            IrType::I32 => "i32",
+            IrType::U32 => "u32",
+            IrType::I64 => "i64",
+            IrType::U64 => "u64",
+            IrType::Ptr => "ptr",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for VReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+// 二項演算
+// 8バイトで計算した結果を結果の型に切り詰める. Div, Remは結果の型が符号なしなら符号なし除算とする
+// Shrは結果の型が符号なしなら論理シフト, 符号ありなら算術シフトとする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+// 比較演算
+// 結果は0か1. U付きは8バイトの値を符号なし整数として比較する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    ULt,
+    ULe,
+    UGt,
+    UGe,
+}
+
+// 単項演算
+// Notは値が0なら1, それ以外は0とする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    BitNot,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    Direct(String),
+    Indirect(VReg),
+}
+
+/// 命令
+///
+/// 結果を持つ命令はdstに結果を設定する. 変数の読み書きは必ずLoad, Storeで行う
+/// - Cast - srcの値をdstの型に変換する(切り詰め, 拡張, _Bool型への正規化)
+/// - LocalAddress - rbp - offset のローカル変数のアドレス
+/// - Param - index番目の引数(関数の入口でのみ使用する)
+/// - VaArg - va_listから次の引数が置かれたアドレスを取り出す
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Const {
+        dst: VReg,
+        value: u64,
+    },
+    Copy {
+        dst: VReg,
+        src: VReg,
+    },
+    Binary {
+        op: BinaryOp,
+        dst: VReg,
+        left: VReg,
+        right: VReg,
+    },
+    Unary {
+        op: UnaryOp,
+        dst: VReg,
+        src: VReg,
+    },
+    Compare {
+        op: CompareOp,
+        dst: VReg,
+        left: VReg,
+        right: VReg,
+    },
+    Cast {
+        dst: VReg,
+        src: VReg,
+    },
+    LocalAddress {
+        dst: VReg,
+        offset: usize,
+    },
+    GlobalAddress {
+        dst: VReg,
+        label: String,
+    },
+    FunctionAddress {
+        dst: VReg,
+        name: String,
+    },
+    Load {
+        dst: VReg,
+        address: VReg,
+        volatile: bool,
+    },
+    Store {
+        type_: IrType,
+        address: VReg,
+        src: VReg,
+        volatile: bool,
+    },
+    Param {
+        dst: VReg,
+        index: usize,
+    },
+    Call {
+        dst: Option<VReg>,
+        callee: Callee,
+        args: Vec<VReg>,
+        variadic: bool,
+    },
+    VaStart {
+        va_list: VReg,
+        reg_save_area: VReg,
+        named_arg_count: usize,
+    },
+    VaArg {
+        dst: VReg,
+        va_list: VReg,
+    },
+}
+
+impl Instruction {
+    // 結果を設定する仮想レジスタ
+    pub fn get_dst(&self) -> Option<VReg> {
+        match self {
+            Instruction::Const { dst, .. }
+            | Instruction::Copy { dst, .. }
+            | Instruction::Binary { dst, .. }
+            | Instruction::Unary { dst, .. }
+            | Instruction::Compare { dst, .. }
+            | Instruction::Cast { dst, .. }
+            | Instruction::LocalAddress { dst, .. }
+            | Instruction::GlobalAddress { dst, .. }
+            | Instruction::FunctionAddress { dst, .. }
+            | Instruction::Load { dst, .. }
+            | Instruction::Param { dst, .. }
+            | Instruction::VaArg { dst, .. } => Some(*dst),
+            Instruction::Call { dst, .. } => *dst,
+            Instruction::Store { .. } | Instruction::VaStart { .. } => None,
+        }
+    }
+
+    // 値を読み出す仮想レジスタ
+    pub fn get_uses(&self) -> Vec<VReg> {
+        match self {
+            Instruction::Const { .. }
+            | Instruction::LocalAddress { .. }
+            | Instruction::GlobalAddress { .. }
+            | Instruction::FunctionAddress { .. }
+            | Instruction::Param { .. } => vec![],
+            Instruction::Copy { src, .. }
+            | Instruction::Unary { src, .. }
+            | Instruction::Cast { src, .. } => vec![*src],
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                vec![*left, *right]
+            }
+            Instruction::Load { address, .. } => vec![*address],
+            Instruction::Store { address, src, .. } => vec![*address, *src],
+            Instruction::Call { callee, args, .. } => {
+                let mut uses = vec![];
+                if let Callee::Indirect(callee) = callee {
+                    uses.push(*callee);
+                }
+                uses.extend(args.iter());
+                uses
+            }
+            Instruction::VaStart {
+                va_list,
+                reg_save_area,
+                ..
+            } => vec![*va_list, *reg_save_area],
+            Instruction::VaArg { va_list, .. } => vec![*va_list],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        cond: VReg,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Return(Option<VReg>),
+}
+
+impl Terminator {
+    pub fn get_succs(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    pub fn get_uses(&self) -> Vec<VReg> {
+        match self {
+            Terminator::Branch { cond, .. } => vec![*cond],
+            Terminator::Return(Some(value)) => vec![*value],
+            Terminator::Jump(_) | Terminator::Return(None) => vec![],
+        }
+    }
+}
+
+// 基本ブロック
+// 構築中以外のブロックは必ず終端命令を持つ
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Option<Terminator>,
+}
+
+/// 関数の中間表現
+///
+/// member
+/// - frame_size - ローカル変数の領域(VariableDeclarationsで計算したスタックフレームのサイズ)
+/// - reg_save_area - 可変長引数関数のレジスタ退避領域のフレームオフセット
+/// - vreg_types - 仮想レジスタの型(仮想レジスタの番号をインデックスとする)
+/// - blocks - 基本ブロック. 先頭のブロックを入口とし, 出力はこの順序で行う
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrFunction {
+    pub name: String,
+    pub linkage: Linkage,
+    pub return_type: Option<IrType>,
+    pub frame_size: usize,
+    pub reg_save_area: Option<usize>,
+    pub vreg_types: Vec<IrType>,
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl IrFunction {
+    pub fn get_vreg_type(&self, vreg: VReg) -> IrType {
+        self.vreg_types[vreg.0]
+    }
+
+    // 各ブロックの先行ブロックを求める
+    pub fn get_preds(&self) -> Vec<Vec<BlockId>> {
+        let mut preds = vec![vec![]; self.blocks.len()];
+        for (index, block) in self.blocks.iter().enumerate() {
+            if let Some(terminator) = &block.terminator {
+                for succ in terminator.get_succs() {
+                    preds[succ.0].push(BlockId(index));
+                }
+            }
+        }
+        preds
+    }
+
+    // 定数命令1つでのみ書き込まれる仮想レジスタの値
+    // 読み出しの前に必ず書き込まれるので, 全ての読み出しでこの値を持つ
+    pub fn get_constants(&self) -> Vec<Option<u64>> {
+        let mut def_counts = vec![0; self.vreg_types.len()];
+        let mut constants = vec![None; self.vreg_types.len()];
+        for instruction in self.blocks.iter().flat_map(|block| &block.instructions) {
+            if let Some(dst) = instruction.get_dst() {
+                def_counts[dst.0] += 1;
+            }
+            if let Instruction::Const { dst, value } = instruction {
+                constants[dst.0] = Some(*value);
+            }
+        }
+        constants
+            .into_iter()
+            .zip(def_counts)
+            .map(|(value, count)| value.filter(|_| count == 1))
+            .collect()
+    }
+}
+
+fn binary_op_name(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Sub => "sub",
+        BinaryOp::Mul => "mul",
+        BinaryOp::Div => "div",
+        BinaryOp::Rem => "rem",
+        BinaryOp::BitAnd => "and",
+        BinaryOp::BitOr => "or",
+        BinaryOp::BitXor => "xor",
+        BinaryOp::Shl => "shl",
+        BinaryOp::Shr => "shr",
+    }
+}
+
+fn compare_op_name(op: CompareOp) -> &'static str {
+    match op {
+        CompareOp::Eq => "eq",
+        CompareOp::Ne => "ne",
+        CompareOp::Lt => "lt",
+        CompareOp::Le => "le",
+        CompareOp::Gt => "gt",
+        CompareOp::Ge => "ge",
+        CompareOp::ULt => "ult",
+        CompareOp::ULe => "ule",
+        CompareOp::UGt => "ugt",
+        CompareOp::UGe => "uge",
+    }
+}
+
+fn format_args(args: &[VReg]) -> String {
+    args.iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// 命令のテキスト表現
+// 結果を持つ命令は "%dst: 型 = " を先頭に付ける
+fn format_instruction(func: &IrFunction, instruction: &Instruction) -> String {
+    let volatile_prefix = |volatile: bool| if volatile { "volatile " } else { "" };
+    let body = match instruction {
+        // 符号ありの型の定数は符号付きで表示する
+        Instruction::Const { dst, value } if !func.get_vreg_type(*dst).is_unsigned() => {
+            format!("const {}", *value as i64)
+        }
+        Instruction::Const { value, .. } => format!("const {}", value),
+        Instruction::Copy { src, .. } => format!("copy {}", src),
+        Instruction::Binary {
+            op, left, right, ..
+        } => format!("{} {}, {}", binary_op_name(*op), left, right),
+        Instruction::Unary { op, src, .. } => match op {
+            UnaryOp::BitNot => format!("bitnot {}", src),
+            UnaryOp::Not => format!("not {}", src),
+        },
+        Instruction::Compare {
+            op, left, right, ..
+        } => format!("cmp {} {}, {}", compare_op_name(*op), left, right),
+        Instruction::Cast { src, .. } => {
+            format!("cast {} {}", func.get_vreg_type(*src), src)
+        }
+        Instruction::LocalAddress { offset, .. } => format!("local {}", offset),
+        Instruction::GlobalAddress { label, .. } => format!("global @{}", label),
+        Instruction::FunctionAddress { name, .. } => format!("function @{}", name),
+        Instruction::Load {
+            address, volatile, ..
+        } => format!("load {}{}", volatile_prefix(*volatile), address),
+        Instruction::Store {
+            type_,
+            address,
+            src,
+            volatile,
+        } => format!(
+            "store {}{} {}, {}",
+            volatile_prefix(*volatile),
+            type_,
+            src,
+            address
+        ),
+        Instruction::Param { index, .. } => format!("param {}", index),
+        Instruction::Call {
+            callee,
+            args,
+            variadic,
+            ..
+        } => {
+            let callee = match callee {
+                Callee::Direct(name) => format!("@{}", name),
+                Callee::Indirect(address) => address.to_string(),
+            };
+            let variadic = if *variadic { ", ..." } else { "" };
+            format!("call {}({}{})", callee, format_args(args), variadic)
+        }
+        Instruction::VaStart {
+            va_list,
+            reg_save_area,
+            named_arg_count,
+        } => format!(
+            "va_start {}, {}, {}",
+            va_list, reg_save_area, named_arg_count
+        ),
+        Instruction::VaArg { va_list, .. } => format!("va_arg {}", va_list),
+    };
+    match instruction.get_dst() {
+        Some(dst) => format!("{}: {} = {}", dst, func.get_vreg_type(dst), body),
+        None => body,
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => write!(f, "br {}, {}, {}", cond, then_block, else_block),
+            Terminator::Return(Some(value)) => write!(f, "ret {}", value),
+            Terminator::Return(None) => write!(f, "ret"),
+        }
+    }
+}
+
+impl fmt::Display for IrFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let return_type = match &self.return_type {
+            Some(type_) => type_.to_string(),
+            None => "void".to_string(),
+        };
+        write!(
+            f,
+            "function @{}: {}, frame {}",
+            self.name, return_type, self.frame_size
+        )?;
+        if let Some(offset) = self.reg_save_area {
+            write!(f, ", reg_save_area {}", offset)?;
+        }
+        writeln!(f)?;
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(index))?;
+            for instruction in &block.instructions {
+                writeln!(f, "    {}", format_instruction(self, instruction))?;
+            }
+            match &block.terminator {
+                Some(terminator) => writeln!(f, "    {}", terminator)?,
+                None => writeln!(f, "    <no terminator>")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,680 @@
+use crate::ast::ast::*;
+use crate::ast::error::*;
+use crate::definition::number::Number;
+use crate::definition::variables::Variable;
+use crate::ir::ir::*;
+use crate::ir::verify::verify_function;
+
+// 関数の中間表現を組み立てる
+// currentは命令を追加中のブロック. layoutにはブロックを追加し始めた順序を記録し,
+// 最後にこの順序でブロックを並べ替える(ソースコード順の配置にする)
+struct FunctionBuilder {
+    func: IrFunction,
+    current: BlockId,
+    layout: Vec<BlockId>,
+    break_targets: Vec<BlockId>,
+}
+
+impl FunctionBuilder {
+    fn new_vreg(&mut self, type_: IrType) -> VReg {
+        self.func.vreg_types.push(type_);
+        VReg(self.func.vreg_types.len() - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.func.blocks.push(BasicBlock {
+            instructions: vec![],
+            terminator: None,
+        });
+        BlockId(self.func.blocks.len() - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+        self.layout.push(block);
+    }
+
+    fn push(&mut self, instruction: Instruction) {
+        self.func.blocks[self.current.0]
+            .instructions
+            .push(instruction);
+    }
+
+    // 現在のブロックを終端し, 以降の命令は新しい(どこからも到達しない)ブロックに追加する
+    fn terminate(&mut self, terminator: Terminator) {
+        self.func.blocks[self.current.0].terminator = Some(terminator);
+        let block = self.new_block();
+        self.switch_to(block);
+    }
+
+    // 現在のブロックを終端し, 続きを指定したブロックに追加する
+    fn terminate_and_switch(&mut self, terminator: Terminator, next: BlockId) {
+        self.func.blocks[self.current.0].terminator = Some(terminator);
+        self.switch_to(next);
+    }
+
+    fn push_const(&mut self, type_: IrType, value: u64) -> VReg {
+        let dst = self.new_vreg(type_);
+        self.push(Instruction::Const { dst, value });
+        dst
+    }
+
+    fn lower_variable_address(&mut self, ast: &Ast) -> VReg {
+        let dst = self.new_vreg(IrType::Ptr);
+        match &ast.kind {
+            AstKind::Variable(Variable::LocalVal(local_val)) => {
+                self.push(Instruction::LocalAddress {
+                    dst,
+                    offset: local_val.frame_offset,
+                });
+            }
+            AstKind::Variable(Variable::GlobalVal(global_val)) => {
+                self.push(Instruction::GlobalAddress {
+                    dst,
+                    label: global_val.label.clone(),
+                });
+            }
+            _ => unexpected_ast_err(ast, "variable"),
+        }
+        dst
+    }
+
+    // アドレスにバイト単位のオフセットを足す
+    fn push_offset_address(&mut self, address: VReg, offset: usize) -> VReg {
+        if offset == 0 {
+            return address;
+        }
+        let offset = self.push_const(IrType::I64, offset as u64);
+        let dst = self.new_vreg(IrType::Ptr);
+        self.push(Instruction::Binary {
+            op: BinaryOp::Add,
+            dst,
+            left: address,
+            right: offset,
+        });
+        dst
+    }
+
+    // 構造体のアドレスにメンバーのオフセットを足す
+    fn lower_member_address(&mut self, ast: &Ast) -> VReg {
+        if let AstKind::Member(offset) = ast.kind {
+            let struct_address = self.lower_lvalue_address(ast.operand.as_ref().unwrap());
+            self.push_offset_address(struct_address, offset)
+        } else {
+            unexpected_ast_err(ast, "struct member");
+        }
+    }
+
+    // 左辺値のアドレスを求める
+    // ポインタの参照先のアドレスはポインタ型の式の値となる
+    fn lower_lvalue_address(&mut self, ast: &Ast) -> VReg {
+        match &ast.kind {
+            AstKind::Variable(_) => self.lower_variable_address(ast),
+            AstKind::Deref => self.lower_value(ast.operand.as_ref().unwrap()),
+            AstKind::Member(_) => self.lower_member_address(ast),
+            _ => unaddressable_ast_err(ast),
+        }
+    }
+
+    // アドレスから型に合わせて値を読み込む
+    // 配列, 構造体はアドレスをそのまま値とする
+    fn lower_load(&mut self, ast: &Ast, address: VReg) -> VReg {
+        if ast.type_.is_array() || ast.type_.is_struct() {
+            return address;
+        }
+        let dst = self.new_vreg(self.get_type(ast));
+        self.push(Instruction::Load {
+            dst,
+            address,
+            volatile: ast.type_.is_volatile,
+        });
+        dst
+    }
+
+    fn get_type(&self, ast: &Ast) -> IrType {
+        match IrType::from_type(&ast.type_) {
+            Some(type_) => type_,
+            None => unexpected_ast_err(ast, "value"),
+        }
+    }
+
+    // 値を持つ式を変換する
+    fn lower_value(&mut self, ast: &Ast) -> VReg {
+        match self.lower_expr(ast) {
+            Some(value) => value,
+            None => unexpected_ast_err(ast, "value"),
+        }
+    }
+
+    fn lower_binary_operation(&mut self, op: BinaryOp, ast: &Ast) -> VReg {
+        let left = self.lower_value(ast.left.as_ref().unwrap());
+        let right = self.lower_value(ast.right.as_ref().unwrap());
+        let dst = self.new_vreg(self.get_type(ast));
+        self.push(Instruction::Binary {
+            op,
+            dst,
+            left,
+            right,
+        });
+        dst
+    }
+
+    // ポインタの比較はアドレスを符号なし整数として比較する
+    // 整数同士の場合は通常の算術型変換により両辺とも同じ型になっている
+    fn lower_comparison(&mut self, operation: &Operation, ast: &Ast) -> VReg {
+        let left_ast = ast.left.as_ref().unwrap();
+        let right_ast = ast.right.as_ref().unwrap();
+        let unsigned = [left_ast, right_ast]
+            .iter()
+            .any(|ast| ast.type_.is_pointer() || ast.type_.is_unsigned_integer());
+        let op = match operation {
+            Operation::Eq => CompareOp::Eq,
+            Operation::NotEq => CompareOp::Ne,
+            Operation::Lt if unsigned => CompareOp::ULt,
+            Operation::Le if unsigned => CompareOp::ULe,
+            Operation::Gt if unsigned => CompareOp::UGt,
+            Operation::Ge if unsigned => CompareOp::UGe,
+            Operation::Lt => CompareOp::Lt,
+            Operation::Le => CompareOp::Le,
+            Operation::Gt => CompareOp::Gt,
+            Operation::Ge => CompareOp::Ge,
+            _ => unexpected_ast_err(ast, "comparison"),
+        };
+        let left = self.lower_value(left_ast);
+        let right = self.lower_value(right_ast);
+        let dst = self.new_vreg(self.get_type(ast));
+        self.push(Instruction::Compare {
+            op,
+            dst,
+            left,
+            right,
+        });
+        dst
+    }
+
+    fn lower_unary_operation(&mut self, op: UnaryOp, ast: &Ast) -> VReg {
+        let src = self.lower_value(ast.operand.as_ref().unwrap());
+        let dst = self.new_vreg(self.get_type(ast));
+        self.push(Instruction::Unary { op, dst, src });
+        dst
+    }
+
+    // 構造体の代入はsrcが指す構造体をaddressへ先頭から8, 4, 2, 1バイトずつコピーする
+    fn lower_struct_copy(&mut self, left_ast: &Ast, right_ast: &Ast, address: VReg, src: VReg) {
+        let size = left_ast.type_.size;
+        let mut offset = 0;
+        for (chunk_size, type_) in [
+            (8, IrType::U64),
+            (4, IrType::U32),
+            (2, IrType::U16),
+            (1, IrType::U8),
+        ] {
+            while offset + chunk_size <= size {
+                let src_address = self.push_offset_address(src, offset);
+                let value = self.new_vreg(type_);
+                self.push(Instruction::Load {
+                    dst: value,
+                    address: src_address,
+                    volatile: right_ast.type_.is_volatile,
+                });
+                let dst_address = self.push_offset_address(address, offset);
+                self.push(Instruction::Store {
+                    type_,
+                    address: dst_address,
+                    src: value,
+                    volatile: left_ast.type_.is_volatile,
+                });
+                offset += chunk_size;
+            }
+        }
+    }
+
+    // 代入式の値は代入した値とする
+    // 構造体の代入式の値は代入先の構造体のアドレスとする
+    fn lower_assign(&mut self, ast: &Ast) -> VReg {
+        let left_ast = ast.left.as_ref().unwrap();
+        if !matches!(
+            &left_ast.kind,
+            AstKind::Variable(_) | AstKind::Deref | AstKind::Member(_)
+        ) {
+            unassignable_ast_err(ast);
+        }
+        let address = self.lower_lvalue_address(left_ast);
+        let right_ast = ast.right.as_ref().unwrap();
+        let src = self.lower_value(right_ast);
+        if left_ast.type_.is_struct() {
+            self.lower_struct_copy(left_ast, right_ast, address, src);
+            return address;
+        }
+        self.push(Instruction::Store {
+            type_: self.get_type(left_ast),
+            address,
+            src,
+            volatile: left_ast.type_.is_volatile,
+        });
+        src
+    }
+
+    // 論理演算は左辺で結果が決まる場合は右辺を評価しない
+    // 結果の仮想レジスタはtrue, falseそれぞれのブロックで設定する
+    fn lower_logical_operation(&mut self, operation: &Operation, ast: &Ast) -> VReg {
+        let dst = self.new_vreg(self.get_type(ast));
+        let right_block = self.new_block();
+        let true_block = self.new_block();
+        let false_block = self.new_block();
+        let end_block = self.new_block();
+
+        let left = self.lower_value(ast.left.as_ref().unwrap());
+        let (then_block, else_block) = if *operation == Operation::And {
+            (right_block, false_block)
+        } else {
+            (true_block, right_block)
+        };
+        self.terminate_and_switch(
+            Terminator::Branch {
+                cond: left,
+                then_block,
+                else_block,
+            },
+            right_block,
+        );
+        let right = self.lower_value(ast.right.as_ref().unwrap());
+        self.terminate_and_switch(
+            Terminator::Branch {
+                cond: right,
+                then_block: true_block,
+                else_block: false_block,
+            },
+            true_block,
+        );
+        self.push(Instruction::Const { dst, value: 1 });
+        self.terminate_and_switch(Terminator::Jump(end_block), false_block);
+        self.push(Instruction::Const { dst, value: 0 });
+        self.terminate_and_switch(Terminator::Jump(end_block), end_block);
+        dst
+    }
+
+    // 演算結果の仮想レジスタを式の型にする
+    // 代入式, インクリメントの値は代入した値なので, 式の型と異なる場合は変換する
+    fn convert_to_ast_type(&mut self, ast: &Ast, src: VReg) -> VReg {
+        let type_ = self.get_type(ast);
+        if self.func.get_vreg_type(src) == type_ {
+            return src;
+        }
+        let dst = self.new_vreg(type_);
+        self.push(Instruction::Cast { dst, src });
+        dst
+    }
+
+    fn lower_operation(&mut self, operation: &Operation, ast: &Ast) -> VReg {
+        let value = match operation {
+            Operation::Add => self.lower_binary_operation(BinaryOp::Add, ast),
+            Operation::Sub => self.lower_binary_operation(BinaryOp::Sub, ast),
+            Operation::Mul => self.lower_binary_operation(BinaryOp::Mul, ast),
+            Operation::Div => self.lower_binary_operation(BinaryOp::Div, ast),
+            Operation::Rem => self.lower_binary_operation(BinaryOp::Rem, ast),
+            Operation::BitAnd => self.lower_binary_operation(BinaryOp::BitAnd, ast),
+            Operation::BitOr => self.lower_binary_operation(BinaryOp::BitOr, ast),
+            Operation::BitXor => self.lower_binary_operation(BinaryOp::BitXor, ast),
+            Operation::Eq
+            | Operation::NotEq
+            | Operation::Lt
+            | Operation::Le
+            | Operation::Gt
+            | Operation::Ge => self.lower_comparison(operation, ast),
+            Operation::Not => self.lower_unary_operation(UnaryOp::Not, ast),
+            Operation::BitNot => self.lower_unary_operation(UnaryOp::BitNot, ast),
+            Operation::Assign => self.lower_assign(ast),
+            Operation::And | Operation::Or => self.lower_logical_operation(operation, ast),
+            // 前置インクリメントはoperandの代入式の値
+            Operation::ForwardIncrement => self.lower_value(ast.operand.as_ref().unwrap()),
+            // 後置インクリメントは代入前に読み出した値
+            Operation::BackwardIncrement => {
+                let value = self.lower_value(ast.right.as_ref().unwrap());
+                self.lower_value(ast.left.as_ref().unwrap());
+                value
+            }
+            Operation::LeftShift | Operation::RightShift => unsupported_ast_err(ast),
+        };
+        self.convert_to_ast_type(ast, value)
+    }
+
+    // キャスト
+    // void型へのキャストは値を捨てる
+    fn lower_cast(&mut self, ast: &Ast) -> Option<VReg> {
+        let src = self.lower_expr(ast.operand.as_ref().unwrap());
+        let type_ = IrType::from_type(&ast.type_)?;
+        let src = match src {
+            Some(src) => src,
+            None => unexpected_ast_err(ast, "value"),
+        };
+        let dst = self.new_vreg(type_);
+        self.push(Instruction::Cast { dst, src });
+        Some(dst)
+    }
+
+    // 関数呼び出し
+    // 関数ポインタによる呼び出しでは呼び出し先のアドレスを引数より先に求める
+    fn lower_funccall(&mut self, ast: &Ast) -> Option<VReg> {
+        let (callee, functype) = match &ast.kind {
+            AstKind::FuncionCall(func_name, functype) => {
+                (Callee::Direct(func_name.clone()), functype)
+            }
+            AstKind::IndirectCall(functype) => {
+                let address = self.lower_value(ast.operand.as_ref().unwrap());
+                (Callee::Indirect(address), functype)
+            }
+            _ => invalid_direction_err(ast, "call function"),
+        };
+        let args = ast
+            .exprs
+            .iter()
+            .flatten()
+            .map(|arg_ast| self.lower_value(arg_ast))
+            .collect();
+        let dst = IrType::from_type(&ast.type_).map(|type_| self.new_vreg(type_));
+        self.push(Instruction::Call {
+            dst,
+            callee,
+            args,
+            variadic: functype.function.as_ref().unwrap().variadic,
+        });
+        dst
+    }
+
+    // va_listのアドレス
+    // 関数内で宣言したva_listは配列なので変数のアドレス,
+    // 引数として受け取ったva_listはポインタなので変数の値となる
+    fn lower_va_list_address(&mut self, ast: &Ast) -> VReg {
+        if ast.type_.is_array() {
+            self.lower_variable_address(ast)
+        } else {
+            self.lower_value(ast)
+        }
+    }
+
+    fn lower_va_start(&mut self, ast: &Ast) {
+        if let AstKind::VaStart(named_arg_count) = ast.kind {
+            let va_list = self.lower_va_list_address(ast.operand.as_ref().unwrap());
+            let reg_save_area = self.lower_variable_address(ast.right.as_ref().unwrap());
+            self.push(Instruction::VaStart {
+                va_list,
+                reg_save_area,
+                named_arg_count,
+            });
+        } else {
+            unexpected_ast_err(ast, "va_start");
+        }
+    }
+
+    fn lower_va_arg(&mut self, ast: &Ast) -> VReg {
+        let va_list = self.lower_va_list_address(ast.operand.as_ref().unwrap());
+        let address = self.new_vreg(IrType::Ptr);
+        self.push(Instruction::VaArg {
+            dst: address,
+            va_list,
+        });
+        self.lower_load(ast, address)
+    }
+
+    // 式を変換し, 式の値を持つ仮想レジスタを返す
+    // 値を持たない式(void型の式, 文)はNoneを返す
+    fn lower_expr(&mut self, ast: &Ast) -> Option<VReg> {
+        match &ast.kind {
+            AstKind::Operation(operation) => Some(self.lower_operation(operation, ast)),
+            AstKind::Control(_) => {
+                self.lower_control(ast);
+                None
+            }
+            AstKind::ImmidiateInterger(Number::U64(num)) => {
+                Some(self.push_const(self.get_type(ast), *num))
+            }
+            AstKind::Variable(_) => {
+                let address = self.lower_variable_address(ast);
+                Some(self.lower_load(ast, address))
+            }
+            AstKind::Address => Some(self.lower_lvalue_address(ast.operand.as_ref().unwrap())),
+            AstKind::Deref => {
+                let address = self.lower_value(ast.operand.as_ref().unwrap());
+                Some(self.lower_load(ast, address))
+            }
+            AstKind::Member(_) => {
+                let address = self.lower_member_address(ast);
+                Some(self.lower_load(ast, address))
+            }
+            AstKind::Cast => self.lower_cast(ast),
+            AstKind::Expressions => {
+                for expr_ast in ast.exprs.iter().flatten() {
+                    self.lower_expr(expr_ast);
+                }
+                None
+            }
+            AstKind::FuncionCall(_, _) | AstKind::IndirectCall(_) => self.lower_funccall(ast),
+            AstKind::FunctionAddress(func_name) => {
+                let dst = self.new_vreg(IrType::Ptr);
+                self.push(Instruction::FunctionAddress {
+                    dst,
+                    name: func_name.clone(),
+                });
+                Some(dst)
+            }
+            AstKind::VaStart(_) => {
+                self.lower_va_start(ast);
+                None
+            }
+            AstKind::VaArg => Some(self.lower_va_arg(ast)),
+            _ => unsupported_ast_err(ast),
+        }
+    }
+
+    // return文
+    // returnする値のastはexprs[0]
+    fn lower_return(&mut self, ast: &Ast) {
+        let value = ast
+            .exprs
+            .as_ref()
+            .map(|return_expr| self.lower_value(&return_expr[0]));
+        self.terminate(Terminator::Return(value));
+    }
+
+    // if文
+    // if文の条件はcontext, true時の文はother[0], elseがある場合はelse時の文はother[1]にある
+    fn lower_if(&mut self, ast: &Ast) {
+        let if_context = ast.other.as_ref().unwrap();
+        let cond = self.lower_value(ast.context.as_ref().unwrap());
+        let then_block = self.new_block();
+        let else_block = if_context[1].as_ref().map(|_| self.new_block());
+        let end_block = self.new_block();
+        self.terminate_and_switch(
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block: else_block.unwrap_or(end_block),
+            },
+            then_block,
+        );
+        self.lower_expr(if_context[0].as_ref().unwrap());
+        if let Some(else_block) = else_block {
+            self.terminate_and_switch(Terminator::Jump(end_block), else_block);
+            self.lower_expr(if_context[1].as_ref().unwrap());
+        }
+        self.terminate_and_switch(Terminator::Jump(end_block), end_block);
+    }
+
+    // ループ
+    // 条件式ブロック, ループ内容ブロック, ループ後のブロックを作成する. 条件式の省略は常に真とする
+    fn lower_loop(&mut self, condition: Option<&Ast>, context: &Ast, update: Option<&Ast>) {
+        let cond_block = self.new_block();
+        let body_block = self.new_block();
+        let end_block = self.new_block();
+        self.terminate_and_switch(Terminator::Jump(cond_block), cond_block);
+        match condition {
+            Some(condition) => {
+                let cond = self.lower_value(condition);
+                self.terminate_and_switch(
+                    Terminator::Branch {
+                        cond,
+                        then_block: body_block,
+                        else_block: end_block,
+                    },
+                    body_block,
+                );
+            }
+            None => self.terminate_and_switch(Terminator::Jump(body_block), body_block),
+        }
+        self.break_targets.push(end_block);
+        self.lower_expr(context);
+        self.break_targets.pop();
+        if let Some(update) = update {
+            self.lower_expr(update);
+        }
+        self.terminate_and_switch(Terminator::Jump(cond_block), end_block);
+    }
+
+    // for文はother[0]に初期化式, other[1]に条件式, other[2]に更新式, contextにfor内容がある
+    fn lower_for(&mut self, ast: &Ast) {
+        let for_conditions = ast.other.as_ref().unwrap();
+        if let Some(initialize_ast) = &for_conditions[0] {
+            self.lower_expr(initialize_ast);
+        }
+        self.lower_loop(
+            for_conditions[1].as_ref(),
+            ast.context.as_ref().unwrap(),
+            for_conditions[2].as_ref(),
+        );
+    }
+
+    // while文はcontextに条件式, exprs[0]にwhile内容がある
+    fn lower_while(&mut self, ast: &Ast) {
+        let context = &ast.exprs.as_ref().unwrap()[0];
+        self.lower_loop(Some(ast.context.as_ref().unwrap()), context, None);
+    }
+
+    fn lower_break(&mut self, ast: &Ast) {
+        if let Some(&target) = self.break_targets.last() {
+            self.terminate(Terminator::Jump(target));
+        } else {
+            invalid_direction_err(ast, "break");
+        }
+    }
+
+    fn lower_control(&mut self, ast: &Ast) {
+        match &ast.kind {
+            AstKind::Control(Control::Return) => self.lower_return(ast),
+            AstKind::Control(Control::If) => self.lower_if(ast),
+            AstKind::Control(Control::For) => self.lower_for(ast),
+            AstKind::Control(Control::While) => self.lower_while(ast),
+            AstKind::Control(Control::Break) => self.lower_break(ast),
+            _ => unsupported_ast_err(ast),
+        }
+    }
+
+    // 引数を受け取り, ローカル変数に格納する
+    // 引数レジスタを上書きする前に全ての引数を受け取る
+    fn lower_params(&mut self, args_ast: &[Ast]) {
+        let params: Vec<VReg> = args_ast
+            .iter()
+            .enumerate()
+            .map(|(index, arg_ast)| {
+                let dst = self.new_vreg(self.get_type(arg_ast));
+                self.push(Instruction::Param { dst, index });
+                dst
+            })
+            .collect();
+        for (arg_ast, src) in args_ast.iter().zip(params) {
+            let address = self.lower_variable_address(arg_ast);
+            self.push(Instruction::Store {
+                type_: self.get_type(arg_ast),
+                address,
+                src,
+                volatile: arg_ast.type_.is_volatile,
+            });
+        }
+    }
+
+    // 追加し始めた順にブロックを並べ替え, 分岐先の番号を付け替える
+    fn finish(mut self) -> IrFunction {
+        let mut new_index = vec![0; self.func.blocks.len()];
+        for (index, block) in self.layout.iter().enumerate() {
+            new_index[block.0] = index;
+        }
+        let mut blocks: Vec<Option<BasicBlock>> = self.func.blocks.drain(..).map(Some).collect();
+        for block_id in &self.layout {
+            let mut block = blocks[block_id.0].take().unwrap();
+            let renumber = |block: &mut BlockId| *block = BlockId(new_index[block.0]);
+            match &mut block.terminator {
+                Some(Terminator::Jump(target)) => renumber(target),
+                Some(Terminator::Branch {
+                    then_block,
+                    else_block,
+                    ..
+                }) => {
+                    renumber(then_block);
+                    renumber(else_block);
+                }
+                _ => {}
+            }
+            self.func.blocks.push(block);
+        }
+        self.func
+    }
+}
+
+/// 関数実装のASTを中間表現に変換する
+///
+/// 関数の末尾に到達した場合は値を返さずにreturnする(mainは0を返す)
+pub fn lower_function(ast: &Ast) -> IrFunction {
+    let (func_name, frame_size, linkage) = match &ast.kind {
+        AstKind::FunctionImplementation((func_name, frame_size, linkage)) => {
+            (func_name, *frame_size, *linkage)
+        }
+        _ => unexpected_ast_err(ast, "function implementation"),
+    };
+    let return_type = ast.type_.function.as_ref().unwrap().ret.as_ref();
+    let reg_save_area = ast.operand.as_ref().map(|reg_save_area| {
+        if let AstKind::Variable(Variable::LocalVal(local_val)) = &reg_save_area.kind {
+            local_val.frame_offset
+        } else {
+            unexpected_ast_err(reg_save_area, "local variable");
+        }
+    });
+    let mut builder = FunctionBuilder {
+        func: IrFunction {
+            name: func_name.clone(),
+            linkage,
+            return_type: return_type.and_then(IrType::from_type),
+            frame_size,
+            reg_save_area,
+            vreg_types: vec![],
+            blocks: vec![],
+        },
+        current: BlockId(0),
+        layout: vec![],
+        break_targets: vec![],
+    };
+    let entry = builder.new_block();
+    builder.switch_to(entry);
+    if let Some(args_ast) = &ast.exprs {
+        builder.lower_params(args_ast);
+    }
+    builder.lower_expr(ast.context.as_ref().unwrap());
+
+    let return_value = match builder.func.return_type {
+        Some(type_) if func_name == "main" => Some(builder.push_const(type_, 0)),
+        _ => None,
+    };
+    builder.func.blocks[builder.current.0].terminator = Some(Terminator::Return(return_value));
+    builder.finish()
+}
+
+/// 翻訳単位内の関数実装を中間表現に変換し, 検証する
+pub fn lower_asts(asts: &[Ast]) -> Vec<IrFunction> {
+    asts.iter()
+        .filter(|ast| matches!(ast.kind, AstKind::FunctionImplementation(_)))
+        .map(|ast| {
+            let func = lower_function(ast);
+            verify_function(&func);
+            func
+        })
+        .collect()
+}
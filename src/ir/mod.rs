@@ -0,0 +1,6 @@
+pub mod dce;
+pub mod fold;
+pub mod ir;
+pub mod lower;
+pub mod pass;
+pub mod verify;
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+
+use crate::ir::dce::eliminate_dead_code;
+use crate::ir::fold::fold_constants;
+use crate::ir::ir::*;
+use crate::ir::verify::verify_function;
+
+/// 最適化パス
+///
+/// member
+/// - name - --passes, --print-after で指定するパス名
+/// - run - 関数ごとに中間表現を書き換える処理
+struct Pass {
+    name: &'static str,
+    run: fn(&mut IrFunction),
+}
+
+// 中間表現の検証
+// パスの間に挟むことで, どのパスが不正な中間表現を作ったのか確認できる
+fn run_verify(func: &mut IrFunction) {
+    verify_function(func);
+}
+
+const PASSES: [Pass; 3] = [
+    Pass {
+        name: "verify",
+        run: run_verify,
+    },
+    Pass {
+        name: "fold",
+        run: fold_constants,
+    },
+    Pass {
+        name: "dce",
+        run: eliminate_dead_code,
+    },
+];
+
+// 最適化レベルごとのパイプライン
+// -O2は不要なコードの削除で定数になった値を再度畳み込む
+const O1_PIPELINE: [&str; 2] = ["fold", "dce"];
+const O2_PIPELINE: [&str; 4] = ["fold", "dce", "fold", "dce"];
+
+fn find_pass(name: &str) -> Option<&'static Pass> {
+    PASSES.iter().find(|pass| pass.name == name)
+}
+
+/// 最適化オプション
+///
+/// member
+/// - level - 最適化レベル(-O0, -O1, -O2)
+/// - passes - 実行するパスの列(--passes=a,b). 指定された場合は最適化レベルのパイプラインの代わりに実行する
+/// - print_after - 実行後に中間表現を標準エラー出力に出力するパス(--print-after=a)
+#[derive(Debug, Clone, Default)]
+pub struct OptimizationOptions {
+    pub level: usize,
+    pub passes: Option<Vec<String>>,
+    pub print_after: Vec<String>,
+}
+
+impl OptimizationOptions {
+    // "-O", "--passes=", "--print-after="で始まるオプションを解釈する
+    // 不明なオプションやパス名の場合はErrを返す
+    pub fn parse_option(&mut self, option: &str) -> Result<(), ()> {
+        if let Some(passes) = option.strip_prefix("--passes=") {
+            let passes: Vec<String> = passes
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect();
+            if passes.iter().any(|name| find_pass(name).is_none()) {
+                return Err(());
+            }
+            self.passes = Some(passes);
+        } else if let Some(pass) = option.strip_prefix("--print-after=") {
+            if find_pass(pass).is_none() {
+                return Err(());
+            }
+            self.print_after.push(pass.to_string());
+        } else {
+            self.level = match option {
+                "-O0" => 0,
+                "-O" | "-O1" => 1,
+                "-O2" => 2,
+                _ => return Err(()),
+            };
+        }
+        Ok(())
+    }
+
+    // 実行するパス名の列
+    fn get_pipeline(&self) -> Vec<String> {
+        if let Some(passes) = &self.passes {
+            return passes.clone();
+        }
+        let pipeline: &[&str] = match self.level {
+            0 => &[],
+            1 => &O1_PIPELINE,
+            _ => &O2_PIPELINE,
+        };
+        pipeline.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+// 最適化オプションはコンパイル全体で共有する
+thread_local! {
+    static OPTIMIZATION_OPTIONS: RefCell<OptimizationOptions> =
+        RefCell::new(OptimizationOptions::default());
+}
+
+pub fn set_optimization_options(options: OptimizationOptions) {
+    OPTIMIZATION_OPTIONS.with(|optimization_options| *optimization_options.borrow_mut() = options);
+}
+
+fn get_optimization_options() -> OptimizationOptions {
+    OPTIMIZATION_OPTIONS.with(|optimization_options| optimization_options.borrow().clone())
+}
+
+/// 最適化オプションに従って各関数にパスを順に実行する
+///
+/// パスの実行後は毎回中間表現を検証し, 不正な中間表現を出力しないようにする
+pub fn run_passes(funcs: &mut [IrFunction]) {
+    let options = get_optimization_options();
+    let pipeline = options.get_pipeline();
+    for func in funcs.iter_mut() {
+        for name in &pipeline {
+            let pass = find_pass(name).unwrap();
+            (pass.run)(func);
+            verify_function(func);
+            if options.print_after.contains(name) {
+                eprintln!("; IR after {}", name);
+                eprint!("{}", func);
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::process::exit;
+
+use crate::ir::ir::*;
+
+pub enum VerifyError {
+    NoBlock,
+    NoTerminator(BlockId),
+    InvalidBlock(BlockId, BlockId),
+    InvalidVReg(BlockId, VReg),
+    UndefinedVReg(BlockId, VReg),
+    TypeMismatch(BlockId, VReg, &'static str),
+    MisplacedParam(BlockId),
+    InvalidLocalAddress(BlockId, usize),
+    InvalidReturn(BlockId),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::NoBlock => write!(f, "function has no block"),
+            VerifyError::NoTerminator(block) => {
+                write!(f, "{} has no terminator", block)
+            }
+            VerifyError::InvalidBlock(block, target) => {
+                write!(f, "{} jumps to nonexistent block {}", block, target)
+            }
+            VerifyError::InvalidVReg(block, vreg) => {
+                write!(f, "{} refers to nonexistent register {}", block, vreg)
+            }
+            VerifyError::UndefinedVReg(block, vreg) => {
+                write!(f, "{} may read {} before it is defined", block, vreg)
+            }
+            VerifyError::TypeMismatch(block, vreg, expected) => {
+                write!(f, "{} expects {} to be {}", block, vreg, expected)
+            }
+            VerifyError::MisplacedParam(block) => {
+                write!(f, "{} receives a parameter outside the entry block", block)
+            }
+            VerifyError::InvalidLocalAddress(block, offset) => {
+                write!(f, "{} refers to local {} outside the frame", block, offset)
+            }
+            VerifyError::InvalidReturn(block) => {
+                write!(
+                    f,
+                    "{} returns a value that does not match the function",
+                    block
+                )
+            }
+        }
+    }
+}
+
+struct Verifier<'a> {
+    func: &'a IrFunction,
+    errors: Vec<VerifyError>,
+}
+
+impl<'a> Verifier<'a> {
+    fn check_vreg(&mut self, block: BlockId, vreg: VReg) -> bool {
+        if vreg.0 < self.func.vreg_types.len() {
+            true
+        } else {
+            self.errors.push(VerifyError::InvalidVReg(block, vreg));
+            false
+        }
+    }
+
+    fn check_pointer(&mut self, block: BlockId, vreg: VReg) {
+        if self.func.get_vreg_type(vreg) != IrType::Ptr {
+            self.errors
+                .push(VerifyError::TypeMismatch(block, vreg, "a pointer"));
+        }
+    }
+
+    fn check_integer(&mut self, block: BlockId, vreg: VReg) {
+        if !self.func.get_vreg_type(vreg).is_integer() {
+            self.errors
+                .push(VerifyError::TypeMismatch(block, vreg, "an integer"));
+        }
+    }
+
+    fn check_same_type(&mut self, block: BlockId, vreg: VReg, dst: VReg) {
+        if self.func.get_vreg_type(vreg) != self.func.get_vreg_type(dst) {
+            self.errors.push(VerifyError::TypeMismatch(
+                block,
+                vreg,
+                "the same type as the destination",
+            ));
+        }
+    }
+
+    // storeは値の下位から型のサイズ分を書き込むので, 整数型同士であれば型が異なってもよい
+    // _Bool型は0か1に正規化した値, ポインタ型はアドレスのみ書き込める
+    fn check_store_value(&mut self, block: BlockId, type_: IrType, src: VReg) {
+        let src_type = self.func.get_vreg_type(src);
+        let expected = match type_ {
+            IrType::Bool if src_type != IrType::Bool => "a _Bool value",
+            IrType::Ptr if src_type != IrType::Ptr => "a pointer",
+            _ if type_.is_integer() && !src_type.is_integer() => "an integer",
+            _ => return,
+        };
+        self.errors
+            .push(VerifyError::TypeMismatch(block, src, expected));
+    }
+
+    // 命令ごとのオペランドの型を確認する
+    fn check_instruction(&mut self, block: BlockId, instruction: &Instruction) {
+        let mut is_valid = true;
+        for vreg in instruction
+            .get_uses()
+            .into_iter()
+            .chain(instruction.get_dst())
+        {
+            is_valid &= self.check_vreg(block, vreg);
+        }
+        // 存在しない仮想レジスタがある場合は型を確認しない
+        if !is_valid {
+            return;
+        }
+        match instruction {
+            Instruction::Copy { dst, src }
+                if self.func.get_vreg_type(*dst) != self.func.get_vreg_type(*src) =>
+            {
+                self.errors.push(VerifyError::TypeMismatch(
+                    block,
+                    *src,
+                    "the same type as the destination",
+                ));
+            }
+            Instruction::Binary {
+                op: BinaryOp::Add | BinaryOp::Sub,
+                dst,
+                left,
+                right,
+            } if self.func.get_vreg_type(*dst) == IrType::Ptr => {
+                self.check_pointer(block, *left);
+                self.check_integer(block, *right);
+            }
+            // ポインタ同士の減算はアドレスの差を整数として返す
+            Instruction::Binary {
+                op: BinaryOp::Sub,
+                dst,
+                left,
+                right,
+            } if self.func.get_vreg_type(*left) == IrType::Ptr => {
+                self.check_integer(block, *dst);
+                self.check_pointer(block, *right);
+            }
+            Instruction::Binary {
+                op:
+                    BinaryOp::Add
+                    | BinaryOp::Sub
+                    | BinaryOp::BitAnd
+                    | BinaryOp::BitOr
+                    | BinaryOp::BitXor,
+                dst,
+                left,
+                right,
+            } => {
+                self.check_integer(block, *dst);
+                self.check_same_type(block, *left, *dst);
+                self.check_same_type(block, *right, *dst);
+            }
+            Instruction::Binary {
+                op: BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem | BinaryOp::Shl | BinaryOp::Shr,
+                dst,
+                ..
+            }
+            | Instruction::Compare { dst, .. } => self.check_integer(block, *dst),
+            Instruction::LocalAddress { dst, offset } => {
+                self.check_pointer(block, *dst);
+                if *offset > self.func.frame_size {
+                    self.errors
+                        .push(VerifyError::InvalidLocalAddress(block, *offset));
+                }
+            }
+            Instruction::GlobalAddress { dst, .. }
+            | Instruction::FunctionAddress { dst, .. }
+            | Instruction::VaArg { dst, .. } => self.check_pointer(block, *dst),
+            Instruction::Load { address, .. } => self.check_pointer(block, *address),
+            Instruction::Store {
+                type_,
+                address,
+                src,
+                ..
+            } => {
+                self.check_pointer(block, *address);
+                self.check_store_value(block, *type_, *src);
+            }
+            Instruction::Param { .. } if block != BlockId(0) => {
+                self.errors.push(VerifyError::MisplacedParam(block));
+            }
+            Instruction::Call {
+                callee: Callee::Indirect(address),
+                ..
+            } => self.check_pointer(block, *address),
+            Instruction::VaStart {
+                va_list,
+                reg_save_area,
+                ..
+            } => {
+                self.check_pointer(block, *va_list);
+                self.check_pointer(block, *reg_save_area);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_terminator(&mut self, block: BlockId, terminator: &Terminator) {
+        for target in terminator.get_succs() {
+            if target.0 >= self.func.blocks.len() {
+                self.errors.push(VerifyError::InvalidBlock(block, target));
+            }
+        }
+        for vreg in terminator.get_uses() {
+            self.check_vreg(block, vreg);
+        }
+        if let Terminator::Return(Some(value)) = terminator {
+            let matches_type = value.0 < self.func.vreg_types.len()
+                && self.func.return_type == Some(self.func.get_vreg_type(*value));
+            if !matches_type {
+                self.errors.push(VerifyError::InvalidReturn(block));
+            }
+        }
+    }
+
+    // 入口から到達する全ての経路で, 仮想レジスタが読み出し前に設定されているか確認する
+    // ブロックの入口で設定済みの仮想レジスタの集合を, 先行ブロックの出口の集合の積として求める
+    fn check_definitions(&mut self) {
+        let func = self.func;
+        let vreg_count = func.vreg_types.len();
+        let preds = func.get_preds();
+        let reachable = get_reachable_blocks(func);
+        let mut out_sets: Vec<Vec<bool>> = vec![vec![true; vreg_count]; func.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in func.blocks.iter().enumerate() {
+                if !reachable[index] {
+                    continue;
+                }
+                let mut defined = self.get_in_set(index, &preds[index], &reachable, &out_sets);
+                for instruction in &block.instructions {
+                    if let Some(dst) = instruction.get_dst() {
+                        defined[dst.0] = true;
+                    }
+                }
+                if defined != out_sets[index] {
+                    out_sets[index] = defined;
+                    changed = true;
+                }
+            }
+        }
+
+        for (index, block) in func.blocks.iter().enumerate() {
+            if !reachable[index] {
+                continue;
+            }
+            let mut defined = self.get_in_set(index, &preds[index], &reachable, &out_sets);
+            let check_use = |vreg: VReg, defined: &[bool], errors: &mut Vec<VerifyError>| {
+                if !defined[vreg.0] {
+                    errors.push(VerifyError::UndefinedVReg(BlockId(index), vreg));
+                }
+            };
+            for instruction in &block.instructions {
+                for vreg in instruction.get_uses() {
+                    check_use(vreg, &defined, &mut self.errors);
+                }
+                if let Some(dst) = instruction.get_dst() {
+                    defined[dst.0] = true;
+                }
+            }
+            for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+                check_use(vreg, &defined, &mut self.errors);
+            }
+        }
+    }
+
+    fn get_in_set(
+        &self,
+        block: usize,
+        preds: &[BlockId],
+        reachable: &[bool],
+        out_sets: &[Vec<bool>],
+    ) -> Vec<bool> {
+        let vreg_count = self.func.vreg_types.len();
+        if block == 0 {
+            return vec![false; vreg_count];
+        }
+        let mut defined = vec![true; vreg_count];
+        for pred in preds.iter().filter(|pred| reachable[pred.0]) {
+            for (elem, pred_elem) in defined.iter_mut().zip(out_sets[pred.0].iter()) {
+                *elem &= *pred_elem;
+            }
+        }
+        defined
+    }
+}
+
+/// 入口から到達可能なブロックを求める
+pub fn get_reachable_blocks(func: &IrFunction) -> Vec<bool> {
+    let mut reachable = vec![false; func.blocks.len()];
+    let mut stack = vec![BlockId(0)];
+    while let Some(block) = stack.pop() {
+        if block.0 >= func.blocks.len() || reachable[block.0] {
+            continue;
+        }
+        reachable[block.0] = true;
+        if let Some(terminator) = &func.blocks[block.0].terminator {
+            stack.extend(terminator.get_succs());
+        }
+    }
+    reachable
+}
+
+/// 中間表現の整合性を確認する
+///
+/// 全てのブロックが終端命令を持ち, 分岐先, 仮想レジスタが存在すること,
+/// 仮想レジスタが読み出し前に設定されていること, オペランドの型が命令に合うことを確認する
+pub fn check_function(func: &IrFunction) -> Vec<VerifyError> {
+    let mut verifier = Verifier {
+        func,
+        errors: vec![],
+    };
+    if func.blocks.is_empty() {
+        return vec![VerifyError::NoBlock];
+    }
+    for (index, block) in func.blocks.iter().enumerate() {
+        for instruction in &block.instructions {
+            verifier.check_instruction(BlockId(index), instruction);
+        }
+        match &block.terminator {
+            Some(terminator) => verifier.check_terminator(BlockId(index), terminator),
+            None => verifier
+                .errors
+                .push(VerifyError::NoTerminator(BlockId(index))),
+        }
+    }
+    // 構造が壊れている場合はデータフロー解析を行わない
+    if verifier.errors.is_empty() {
+        verifier.check_definitions();
+    }
+    verifier.errors
+}
+
+// 検証に失敗した中間表現はコンパイラの誤りなので, 内容を表示して終了する
+pub fn verify_function(func: &IrFunction) {
+    let errors = check_function(func);
+    if errors.is_empty() {
+        return;
+    }
+    eprint!("{}", func);
+    for error in errors {
+        eprintln!("internal error: invalid IR in '{}': {}", func.name, error);
+    }
+    exit(-1);
+}
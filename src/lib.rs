@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 #![allow(clippy::result_unit_err, clippy::module_inception)]
+pub mod analysis;
 pub mod ast;
 mod definition;
+pub mod ir;
 pub mod output;
 pub mod token;
 
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 #![allow(clippy::result_unit_err, clippy::module_inception)]
+mod analysis;
 mod ast;
 mod definition;
+mod ir;
 mod output;
 mod token;
 
@@ -11,15 +13,55 @@ use std::path::Path;
 static mut SOURCE_TXT: Vec<String> = vec![];
 fn main() {
     let args = env::args().collect::<Vec<String>>();
-    if args.len() == 1 {
+    let mut warning_options = analysis::warning::WarningOptions::default();
+    let mut optimization_options = ir::pass::OptimizationOptions::default();
+    let mut sources = vec![];
+    let mut emit_ir = false;
+    for arg in args.iter().skip(1) {
+        if let Some(emit) = arg.strip_prefix("--emit=") {
+            // 中間表現を出力する場合はアセンブリを出力しない
+            match emit {
+                "asm" => emit_ir = false,
+                "ir" => emit_ir = true,
+                _ => {
+                    eprintln!("error: unknown emit kind {}", emit);
+                    std::process::exit(-1);
+                }
+            }
+        } else if arg.starts_with("-W") {
+            if warning_options.parse_option(arg).is_err() {
+                eprintln!("error: unknown option {}", arg);
+                std::process::exit(-1);
+            }
+        } else if arg.starts_with("-O")
+            || arg.starts_with("--passes=")
+            || arg.starts_with("--print-after=")
+        {
+            if optimization_options.parse_option(arg).is_err() {
+                eprintln!("error: unknown option {}", arg);
+                std::process::exit(-1);
+            }
+        } else {
+            sources.push(arg);
+        }
+    }
+    if sources.is_empty() {
         eprintln!("error: no input files");
         std::process::exit(-1);
     }
-    for arg in args.iter().skip(1) {
-        let path = Path::new(arg);
+    analysis::warning::set_warning_options(warning_options);
+    ir::pass::set_optimization_options(optimization_options);
+    for source in sources {
+        let path = Path::new(source);
         let rawtokens = token::parser::parse_file(path);
         let tokens = token::token::make_tokens(rawtokens);
         let asts = ast::ast::make_asts(tokens);
+        analysis::analysis::analyze_asts(&asts);
+        analysis::warning::exit_if_warnings_are_errors();
+        if emit_ir {
+            output::output::output_ir(&asts, &mut std::io::stdout());
+            continue;
+        }
         let outputpath = Path::new("./tmp.s");
         output::output::output_assembly(asts, outputpath);
     }
@@ -1,147 +1,184 @@
 use std::io::Write;
 
-use crate::ast::ast::*;
-use crate::ast::error::*;
+use crate::ir::ir::*;
 use crate::output::output::*;
+use crate::output::regalloc::*;
 
-// return文のコンパイル
-// returnする値のastはexprs[0]
-pub fn execute_return<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let Some(mut return_expr) = ast.exprs.take() {
-        output_ast(return_expr.swap_remove(0), buf);
-        buf.output_pop("rax");
+// 次に出力するブロックへのジャンプは省略する
+fn write_jump<T: Write>(target: BlockId, current: BlockId, buf: &mut OutputBuffer<T>) {
+    if target.0 != current.0 + 1 {
+        let label = buf.get_block_label(target);
+        buf.output(&format!("    jmp {}", label));
     }
-    output_function_epilogue(buf);
 }
 
-// if文のコンパイル
-// if文の条件はcontext, true時の条件はother[0], elseがある場合はelse時の条件はother[1]にある
-pub fn execute_if<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let label_index = buf.get_label_index();
+// 条件分岐
+// 条件が0ならelse側, それ以外はthen側へ進む
+fn write_branch<T: Write>(
+    cond: VReg,
+    then_block: BlockId,
+    else_block: BlockId,
+    current: BlockId,
+    buf: &mut OutputBuffer<T>,
+) {
+    let location = buf.get_vreg(cond);
+    buf.output(&format!("    cmp {}, 0", location));
+    let else_label = buf.get_block_label(else_block);
+    buf.output(&format!("    je {}", else_label));
+    write_jump(then_block, current, buf);
+}
 
-    // 条件式のコンパイル
-    let condition = ast.context.take().unwrap();
-    let mut if_context = ast.other.take().unwrap();
-    let has_else = if_context[1].is_some();
-    output_ast(*condition, buf);
-    buf.output_pop("rax");
-    buf.output("    cmp rax, 0");
-    if has_else {
-        buf.output(&format!("    je .LabelElse{}", label_index));
-    } else {
-        buf.output(&format!("    je .LabelIfEnd{}", label_index));
+// 関数の戻り値はraxに設定する
+fn write_return<T: Write>(value: Option<VReg>, buf: &mut OutputBuffer<T>) {
+    if let Some(value) = value {
+        output_read_vreg("rax", value, buf);
     }
+    output_function_epilogue(buf);
+}
 
-    output_formula_ast(if_context[0].take().unwrap(), buf);
-    // else文がある場合
-    if has_else {
-        buf.output(&format!(".LabelElse{}:", label_index));
-        output_formula_ast(if_context[1].take().unwrap(), buf);
-    } else {
-        buf.output(&format!(".LabelIfEnd{}:", label_index));
+pub fn output_terminator<T: Write>(
+    terminator: &Terminator,
+    current: BlockId,
+    buf: &mut OutputBuffer<T>,
+) {
+    match terminator {
+        Terminator::Jump(target) => write_jump(*target, current, buf),
+        Terminator::Branch {
+            cond,
+            then_block,
+            else_block,
+        } => write_branch(*cond, *then_block, *else_block, current, buf),
+        Terminator::Return(value) => write_return(*value, buf),
     }
-    buf.increment_label();
 }
 
-// for文のコンパイル
-// for文はexprs[0]に初期化式, exprs[1]に条件式, exprs[2]に更新式がある
-pub fn execute_for<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // ループ情報の作成
-    buf.enter_loop_control(LoopKind::For);
-    let label_index = buf.get_label_index();
-    let mut for_conditions = ast.other.take().unwrap();
-    let for_context = ast.context.take().unwrap();
-    // 初期化式
-    if let Some(initialize_ast) = for_conditions[0].take() {
-        output_formula_ast(initialize_ast, buf);
-    }
-    // ループ開始ラベル
-    buf.output(&format!(".LabelForBegin{}:", label_index));
-    // 条件式
-    if let Some(condition_ast) = for_conditions[1].take() {
-        output_formula_ast(condition_ast, buf);
-        // 条件式が成立しない場合はif文のEndまでジャンプ
-        buf.output("    cmp rax, 0");
-        buf.output(&format!("    je .LabelForEnd{}", label_index));
+// 引数を受け取る
+// 引数を受け取る仮想レジスタに引数レジスタが割り当てられることがあるため, 全ての引数をまとめて受け取る
+// 7番目以降の引数はスタックで渡され, リターンアドレスと退避したrbpの上, [rbp + 16]から順に並んでいる
+pub fn write_params<T: Write>(func: &IrFunction, buf: &mut OutputBuffer<T>) {
+    let mut register_moves = vec![];
+    let mut stack_params = vec![];
+    for instruction in &func.blocks[0].instructions {
+        if let Instruction::Param { dst, index } = instruction {
+            if *index < FUNC_ARG_REGISTERS.len() {
+                let src = Location::Register(FUNC_ARG_REGISTERS[*index]);
+                register_moves.push((buf.get_vreg(*dst), src));
+            } else {
+                stack_params.push((*dst, *index));
+            }
+        }
     }
-    // for内容
-    output_formula_ast(*for_context, buf);
-    // 更新式
-    if let Some(condition_ast) = for_conditions[2].take() {
-        output_formula_ast(condition_ast, buf);
+    output_parallel_move(&register_moves, buf);
+    for (dst, index) in stack_params {
+        let offset = 16 + 8 * (index - FUNC_ARG_REGISTERS.len());
+        buf.output(&format!("    mov rax, [rbp + {}]", offset));
+        output_write_vreg(dst, "rax", buf);
     }
-    buf.output(&format!("    jmp .LabelForBegin{}", label_index));
-    buf.output(&format!(".LabelForEnd{}:", label_index));
-    // ループ情報の削除
-    buf.exit_loop_control();
-    buf.increment_label();
-}
-
-// while文のコンパイル
-// while文はcontextに条件式,
-// expr[0]にwhile内容がある
-pub fn execute_while<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // ループ情報の作成
-    buf.enter_loop_control(LoopKind::While);
-    let label_index = buf.get_label_index();
-
-    let while_condition = ast.context.take().unwrap();
-    let while_context = ast.exprs.take().unwrap().swap_remove(0);
-
-    buf.output(&format!(".LabelWhileBegin{}:", label_index));
-    // 条件式
-    output_ast(*while_condition, buf);
-    // 条件式が成立しない場合はWhile文のEndまでジャンプ
-    buf.output("    cmp rax, 0");
-    buf.output(&format!("    je .LabelWhileEnd{}", label_index));
-    // while内容
-    output_formula_ast(while_context, buf);
-    buf.output(&format!("    jmp .LabelWhileBegin{}", label_index));
-    buf.output(&format!(".LabelWhileEnd{}:", label_index));
-    // ループ情報の削除
-    buf.exit_loop_control();
-    buf.increment_label();
 }
 
-// break文のコンパイル
-pub fn execute_break<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let Ok(break_dist_label) = buf.get_break_label() {
-        buf.output(&break_dist_label);
-    } else {
-        invalid_direction_err(&ast, "break");
+// 関数呼び出し
+// 7番目以降の引数はスタック渡しとなるため, 後ろの引数から順にスタックに積む
+// 戻り値は型に合わせて切り詰め(拡張し直し)てから書き込む
+pub fn write_funccall<T: Write>(
+    func: &IrFunction,
+    dst: Option<VReg>,
+    callee: &Callee,
+    args: &[VReg],
+    variadic: bool,
+    buf: &mut OutputBuffer<T>,
+) {
+    let stack_args = args.get(FUNC_ARG_REGISTERS.len()..).unwrap_or(&[]);
+    let stack_arg_size = 8 * stack_args.len();
+    // call時にrspが16バイト境界となるように, スタック渡しの引数を積む前にパディングを入れる
+    let padding = buf.get_call_padding(stack_arg_size);
+    if padding != 0 {
+        buf.output_sub_rsp(padding);
     }
-}
-
-pub fn execute_funccall<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let AstKind::FuncionCall(fucname, functype) = &ast.kind {
-        // push args
-        if let Some(args_ast) = ast.exprs {
-            let arg_count = args_ast.len();
-            for arg_ast in args_ast {
-                output_ast(arg_ast, buf);
-            }
-            // set args in register
-            for register in FUNC_ARG_REGISTERS.iter().take(arg_count) {
-                buf.output_pop(*register);
-            }
-        }
-        buf.output(&format!("    call {}", fucname));
-        // push ret
-        if let Some(_rettype) = &functype.function.as_ref().unwrap().ret {
-            buf.output_push("rax");
+    for arg in stack_args.iter().rev() {
+        let location = buf.get_vreg(*arg).to_string();
+        buf.output_push(&location);
+    }
+    // 引数レジスタには他の引数の値が割り当てられていることがあるため, 呼び出し先のアドレスを先に読み込む
+    if let Callee::Indirect(address) = callee {
+        output_read_vreg("r11", *address, buf);
+    }
+    let register_moves: Vec<(Location, Location)> = FUNC_ARG_REGISTERS
+        .iter()
+        .zip(args)
+        .map(|(register, arg)| (Location::Register(register), buf.get_vreg(*arg)))
+        .collect();
+    output_parallel_move(&register_moves, buf);
+    // 可変長引数関数の呼び出しではalにベクタレジスタで渡した引数の数を設定する
+    // 浮動小数点数の引数はまだ扱わないので常に0となる
+    if variadic {
+        buf.output("    mov eax, 0");
+    }
+    match callee {
+        Callee::Direct(func_name) => {
+            let call_target = buf.get_call_target(func_name);
+            buf.output(&format!("    call {}", call_target));
         }
-    } else {
-        invalid_direction_err(&ast, "call function");
+        Callee::Indirect(_) => buf.output("    call r11"),
+    }
+    // スタック渡しした引数とパディングを取り除く
+    if stack_arg_size + padding != 0 {
+        buf.output_add_rsp(stack_arg_size + padding);
+    }
+    if let Some(dst) = dst {
+        output_cast(func.get_vreg_type(dst), buf);
+        output_write_vreg(dst, "rax", buf);
     }
 }
 
-pub fn output_control_ast<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    match &ast.kind {
-        AstKind::Control(Control::Return) => execute_return(ast, buf),
-        AstKind::Control(Control::If) => execute_if(ast, buf),
-        AstKind::Control(Control::For) => execute_for(ast, buf),
-        AstKind::Control(Control::While) => execute_while(ast, buf),
-        AstKind::Control(Control::Break) => execute_break(ast, buf),
-        _ => unsupported_ast_err(&ast),
-    }
+// va_startのコンパイル
+// va_listの各メンバーを初期化する
+// - gp_offset: レジスタ退避領域内の次の引数のオフセット
+// - fp_offset: ベクタレジスタは退避していないので使い切った値(176)とする
+// - overflow_arg_area: 次のスタック渡し引数のアドレス
+// - reg_save_area: レジスタ退避領域のアドレス
+pub fn write_va_start<T: Write>(
+    va_list: VReg,
+    reg_save_area: VReg,
+    named_arg_count: usize,
+    buf: &mut OutputBuffer<T>,
+) {
+    let register_arg_count = std::cmp::min(named_arg_count, FUNC_ARG_REGISTERS.len());
+    let stack_arg_count = named_arg_count.saturating_sub(FUNC_ARG_REGISTERS.len());
+    output_read_vreg("rdi", va_list, buf);
+    output_read_vreg("rax", reg_save_area, buf);
+    buf.output(&format!(
+        "    mov DWORD PTR [rdi], {}",
+        8 * register_arg_count
+    ));
+    buf.output("    mov DWORD PTR [rdi + 4], 176");
+    buf.output(&format!(
+        "    lea rdx, [rbp + {}]",
+        16 + 8 * stack_arg_count
+    ));
+    buf.output("    mov [rdi + 8], rdx");
+    buf.output("    mov [rdi + 16], rax");
+}
+
+// va_argのコンパイル
+// レジスタ退避領域に残りがあればそこから, なければスタック渡しの領域から次の引数のアドレスを取り出す
+pub fn write_va_arg<T: Write>(dst: VReg, va_list: VReg, buf: &mut OutputBuffer<T>) {
+    let label_index = buf.get_label_index();
+    buf.increment_label();
+    output_read_vreg("rdi", va_list, buf);
+    buf.output("    mov eax, DWORD PTR [rdi]");
+    buf.output(&format!("    cmp eax, {}", 8 * FUNC_ARG_REGISTERS.len()));
+    buf.output(&format!("    jae .LabelVaArgStack{}", label_index));
+    // レジスタ退避領域から取り出す
+    buf.output("    mov rdx, [rdi + 16]");
+    buf.output("    add rdx, rax");
+    buf.output("    add eax, 8");
+    buf.output("    mov DWORD PTR [rdi], eax");
+    buf.output(&format!("    jmp .LabelVaArgEnd{}", label_index));
+    // スタック渡しの領域から取り出す
+    buf.output(&format!(".LabelVaArgStack{}:", label_index));
+    buf.output("    mov rdx, [rdi + 8]");
+    buf.output("    lea rax, [rdx + 8]");
+    buf.output("    mov [rdi + 8], rax");
+    buf.output(&format!(".LabelVaArgEnd{}:", label_index));
+    output_write_vreg(dst, "rdx", buf);
 }
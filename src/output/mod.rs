@@ -1,3 +1,4 @@
 mod controls;
 mod operations;
 pub mod output;
+mod regalloc;
@@ -1,272 +1,298 @@
 use std::io::Write;
 
-use crate::ast::ast::*;
-use crate::ast::error::*;
+use crate::ir::ir::*;
+use crate::output::controls::*;
 use crate::output::output::*;
+use crate::output::regalloc::*;
 
-pub fn write_operation<T: Write>(buf: &mut OutputBuffer<T>, ope: &str) {
-    write_pop_two_values(buf);
-    let instruction = format!("    {} rax, rdi", ope);
-    buf.output(&instruction);
-    buf.output_push("rax");
-}
-
-fn write_compararison<T: Write>(buf: &mut OutputBuffer<T>, comp_type: &str) {
-    write_pop_two_values(buf);
-    let comp_output = format!("    {} al", comp_type);
-    buf.output("    cmp rax, rdi");
-    buf.output(&comp_output);
-    buf.output("    movzb rax, al");
-    buf.output_push("rax");
-}
-
-fn write_value_compararison<T: Write>(buf: &mut OutputBuffer<T>, comp_type: &str, num: usize) {
-    write_pop_one_value(buf);
-    let comp_instruction = format!("    cmp rax, {}", num);
-    let comp_output = format!("    {} al", comp_type);
-    buf.output(&comp_instruction);
-    buf.output(&comp_output);
-    buf.output("    movzb rax, al");
-    buf.output_push("rax");
-}
-
-fn write_assignment<T: Write>(buf: &mut OutputBuffer<T>) {
-    write_pop_two_values(buf);
-    buf.output("    mov [rdi], rax");
-    buf.output_push("rax");
-}
-
-fn exetute_mul<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let AstKind::Operation(Operation::Mul) = ast.kind {
-        output_ast(*ast.right.take().unwrap(), buf);
-        output_ast(*ast.left.take().unwrap(), buf);
-        write_operation(buf, "imul");
+// 定数を仮想レジスタに設定する
+// メモリへのmovの即値は32ビットの符号拡張なので, 収まらない値はraxを経由する
+fn write_const<T: Write>(dst: VReg, value: u64, buf: &mut OutputBuffer<T>) {
+    let signed_value = value as i64;
+    if buf.get_vreg(dst).is_register()
+        || (i32::MIN as i64..=i32::MAX as i64).contains(&signed_value)
+    {
+        let location = buf.get_vreg(dst);
+        buf.output(&format!("    mov {}, {}", location, signed_value));
     } else {
-        unexpected_ast_err(&ast, "operation *");
+        buf.output(&format!("    mov rax, {}", value));
+        output_write_vreg(dst, "rax", buf);
     }
 }
 
-fn exetute_div<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let AstKind::Operation(Operation::Div | Operation::Rem) = ast.kind {
-        output_ast(*ast.right.take().unwrap(), buf);
-        output_ast(*ast.left.take().unwrap(), buf);
-        write_pop_two_values(buf);
-        buf.output("    cqo");
-        buf.output("    idiv rdi");
-        if ast.kind == AstKind::Operation(Operation::Div) {
-            buf.output_push("rax");
-        } else {
-            buf.output_push("rdx");
-        }
-    } else {
-        unexpected_ast_err(&ast, "operation / or %");
+// 演算結果は8バイトで計算されるため, 結果の型に合わせて切り詰め(拡張し直し)てから書き込む
+fn write_operation_result<T: Write>(
+    func: &IrFunction,
+    dst: VReg,
+    buf: &mut OutputBuffer<T>,
+    register: &str,
+) {
+    // 8バイトの型は切り詰めが不要なのでそのまま書き込む
+    if func.get_vreg_type(dst).size() == 8 {
+        output_write_vreg(dst, register, buf);
+        return;
     }
+    if register != "rax" {
+        buf.output(&format!("    mov rax, {}", register));
+    }
+    output_cast(func.get_vreg_type(dst), buf);
+    output_write_vreg(dst, "rax", buf);
 }
 
-fn exetute_add<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let operation = match ast.kind {
-        AstKind::Operation(Operation::Add) => "add",
-        AstKind::Operation(Operation::Sub) => "sub",
-        _ => unexpected_ast_err(&ast, "operation + or -"),
+// rax = left として, rightは割り当てられた場所から直接読み出して計算する
+// 結果が8バイトの型でdstがレジスタに割り当てられていれば, dstのレジスタで計算する
+fn write_binary_operation<T: Write>(
+    func: &IrFunction,
+    op: BinaryOp,
+    dst: VReg,
+    left: VReg,
+    right: VReg,
+    buf: &mut OutputBuffer<T>,
+) {
+    let right_location = buf.get_vreg(right);
+    let shift_by_register =
+        matches!(op, BinaryOp::Shl | BinaryOp::Shr) && !right_location.is_immediate();
+    let register = match buf.get_vreg(dst) {
+        Location::Register(register)
+            if !matches!(op, BinaryOp::Div | BinaryOp::Rem)
+                && !shift_by_register
+                && func.get_vreg_type(dst).size() == 8
+                && right_location != buf.get_vreg(dst) =>
+        {
+            register
+        }
+        _ => "rax",
     };
-
-    output_ast(*ast.right.take().unwrap(), buf);
-    output_ast(*ast.left.take().unwrap(), buf);
-    write_operation(buf, operation);
+    output_read_vreg(register, left, buf);
+    let instruction = match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Sub => "sub",
+        BinaryOp::Mul => "imul",
+        BinaryOp::BitAnd => "and",
+        BinaryOp::BitOr => "or",
+        BinaryOp::BitXor => "xor",
+        BinaryOp::Shl => "shl",
+        // 結果の型が符号ありなら算術シフト, 符号なしなら論理シフト
+        BinaryOp::Shr if func.get_vreg_type(dst).is_unsigned() => "shr",
+        BinaryOp::Shr => "sar",
+        BinaryOp::Div | BinaryOp::Rem => {
+            // 符号なしの除算は上位64bitを0にしてdivを使う
+            if func.get_vreg_type(dst).is_unsigned() {
+                buf.output("    xor edx, edx");
+                buf.output(&format!("    div {}", right_location));
+            } else {
+                buf.output("    cqo");
+                buf.output(&format!("    idiv {}", right_location));
+            }
+            let register = if op == BinaryOp::Div { "rax" } else { "rdx" };
+            write_operation_result(func, dst, buf, register);
+            return;
+        }
+    };
+    if shift_by_register {
+        // シフト数はclで指定するため, rcxをrdiに退避してから使用する
+        buf.output("    mov rdi, rcx");
+        output_read_vreg("rcx", right, buf);
+        buf.output(&format!("    {} rax, cl", instruction));
+        buf.output("    mov rcx, rdi");
+    } else {
+        buf.output(&format!(
+            "    {} {}, {}",
+            instruction, register, right_location
+        ));
+    }
+    write_operation_result(func, dst, buf, register);
 }
 
-fn exetute_eq<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let euality = match ast.kind {
-        AstKind::Operation(Operation::Eq) => "sete",
-        AstKind::Operation(Operation::NotEq) => "setne",
-        _ => unexpected_ast_err(&ast, "operation == or !="),
+fn write_comparison<T: Write>(
+    op: CompareOp,
+    dst: VReg,
+    left: VReg,
+    right: VReg,
+    buf: &mut OutputBuffer<T>,
+) {
+    let comparison = match op {
+        CompareOp::Eq => "sete",
+        CompareOp::Ne => "setne",
+        CompareOp::Lt => "setl",
+        CompareOp::Le => "setle",
+        CompareOp::Gt => "setg",
+        CompareOp::Ge => "setge",
+        CompareOp::ULt => "setb",
+        CompareOp::ULe => "setbe",
+        CompareOp::UGt => "seta",
+        CompareOp::UGe => "setae",
     };
-    output_ast(*ast.right.take().unwrap(), buf);
-    output_ast(*ast.left.take().unwrap(), buf);
-    write_compararison(buf, euality);
+    output_read_vreg("rax", left, buf);
+    let right_location = buf.get_vreg(right);
+    buf.output(&format!("    cmp rax, {}", right_location));
+    buf.output(&format!("    {} al", comparison));
+    buf.output("    movzx eax, al");
+    output_write_vreg(dst, "rax", buf);
 }
 
-fn exetute_comp<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // Gt, Geは右辺と左辺を反転させたLt, Leとして扱う
-    if let AstKind::Operation(Operation::Gt | Operation::Ge) = ast.kind {
-        std::mem::swap(&mut ast.right, &mut ast.left);
-        if let AstKind::Operation(Operation::Gt) = ast.kind {
-            ast.kind = AstKind::Operation(Operation::Lt);
-        } else {
-            ast.kind = AstKind::Operation(Operation::Le);
+fn write_unary_operation<T: Write>(
+    func: &IrFunction,
+    op: UnaryOp,
+    dst: VReg,
+    src: VReg,
+    buf: &mut OutputBuffer<T>,
+) {
+    output_read_vreg("rax", src, buf);
+    match op {
+        UnaryOp::BitNot => {
+            buf.output("    not rax");
+            write_operation_result(func, dst, buf, "rax");
+        }
+        UnaryOp::Not => {
+            buf.output("    cmp rax, 0");
+            buf.output("    sete al");
+            buf.output("    movzx eax, al");
+            output_write_vreg(dst, "rax", buf);
         }
     }
-
-    let comparison = match ast.kind {
-        AstKind::Operation(Operation::Lt) => "setl",
-        AstKind::Operation(Operation::Le) => "setle",
-        _ => unexpected_ast_err(&ast, "operation >, <, >= or <="),
-    };
-    output_ast(*ast.right.take().unwrap(), buf);
-    output_ast(*ast.left.take().unwrap(), buf);
-    write_compararison(buf, comparison);
 }
 
-fn exetute_not<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    output_ast(*ast.operand.take().unwrap(), buf);
-    write_value_compararison(buf, "sete", 0);
+// 値が変わらない変換はコピーとする
+fn write_cast<T: Write>(
+    instruction: &Instruction,
+    func: &IrFunction,
+    dst: VReg,
+    src: VReg,
+    buf: &mut OutputBuffer<T>,
+) {
+    if get_move(instruction, func).is_some() {
+        write_copy(dst, src, buf);
+        return;
+    }
+    output_read_vreg("rax", src, buf);
+    write_operation_result(func, dst, buf, "rax");
 }
 
-fn exetute_bitnot<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let instruction = "    not rax";
-    output_ast(*ast.operand.take().unwrap(), buf);
-    buf.output_pop("rax");
-    buf.output(instruction);
-    buf.output_push("rax");
+// 関数のアドレス
+// 翻訳単位内で実装されていない関数はGOT経由でアドレスを取得する
+fn write_function_address<T: Write>(dst: VReg, func_name: &str, buf: &mut OutputBuffer<T>) {
+    let register = get_dst_register(dst, buf);
+    if buf.is_defined_function(func_name) {
+        buf.output(&format!("    lea {}, [rip + {}]", register, func_name));
+    } else {
+        buf.output(&format!(
+            "    mov {}, [rip + {}@GOTPCREL]",
+            register, func_name
+        ));
+    }
+    output_write_vreg(dst, register, buf);
 }
 
-fn exetute_assign<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // 左辺値が被代入可能か確認
-    let left_ast = ast.left.take().unwrap();
-    match &left_ast.kind {
-        AstKind::Variable(_val) => {
-            push_variable_address(*left_ast, buf);
-        }
-        AstKind::Deref => {
-            push_pointer_address(*left_ast, buf);
-        }
-        AstKind::Index => {
-            push_array_elem_address(*left_ast, buf);
-        }
-        _ => unassignable_ast_err(&ast),
+// 結果を直接書き込めるレジスタ
+// スタックに退避された仮想レジスタはraxを経由して書き込む
+fn get_dst_register<T: Write>(dst: VReg, buf: &OutputBuffer<T>) -> &'static str {
+    match buf.get_vreg(dst) {
+        Location::Register(register) => register,
+        _ => "rax",
     }
-    output_ast(*ast.right.take().unwrap(), buf);
-    write_assignment(buf);
 }
 
-fn exetute_bit_operation<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let bit_operation = match ast.kind {
-        AstKind::Operation(Operation::BitAnd) => "and",
-        AstKind::Operation(Operation::BitOr) => "or",
-        AstKind::Operation(Operation::BitXor) => "xor",
-        _ => unexpected_ast_err(&ast, "operation &, | or ^"),
-    };
-
-    output_ast(*ast.left.take().unwrap(), buf);
-    output_ast(*ast.right.take().unwrap(), buf);
-    write_operation(buf, bit_operation);
+// アドレスの計算
+fn write_address<T: Write>(dst: VReg, address: &str, buf: &mut OutputBuffer<T>) {
+    let register = get_dst_register(dst, buf);
+    buf.output(&format!("    lea {}, {}", register, address));
+    output_write_vreg(dst, register, buf);
 }
 
-fn exetute_logical_and<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // andのネストがある場合(A && B && C ), Aが0の時点でB, Cの比較をせずにネストを抜けることが可能.
-    // これを行うにはAndのネスト判定とジャンプ先ラベルを保持している必要がある.
-    // これを行うには状態管理が必要だが, 実装の手間が増えるので今回は逐次比較を行う
-    let false_label_index = buf.label_index;
-    let end_label_index = buf.label_index + 2;
-    let false_label = format!("Label{}:", false_label_index);
-    let end_label = format!("Label{}:", end_label_index);
-    let jump_false = format!("    je Label{}", false_label_index);
-    let jump_end = format!("    jmp Label{}", end_label_index);
-    buf.label_index += 2;
-
-    let comp_zero = "    cmp rax, 0";
-    // 左側の値を計算
-    output_ast(*ast.left.take().unwrap(), buf);
-    buf.output_pop("rax");
-    // 0と比較
-    buf.output(comp_zero);
-    // 0ならばFalse時の処理を行う
-    buf.output(&jump_false);
-    // 右側の値を計算
-    output_ast(*ast.right.take().unwrap(), buf);
-    buf.output_pop("rax");
-    // 0と比較
-    buf.output(comp_zero);
-    // 0ならばFalse時の処理を行う
-    buf.output(&jump_false);
-
-    // True時の処理を記載
-    buf.output_push_num(1);
-    // Endへジャンプ
-    buf.output(&jump_end);
-
-    // Falseラベルを貼り, False時の処理を記載
-    buf.output(&false_label);
-    buf.output_push_num(0);
-    buf.output(&end_label);
+fn write_load<T: Write>(func: &IrFunction, dst: VReg, address: VReg, buf: &mut OutputBuffer<T>) {
+    let address = output_vreg_address(address, "rax", buf);
+    let register = get_dst_register(dst, buf);
+    output_load(func.get_vreg_type(dst), register, &address, buf);
+    output_write_vreg(dst, register, buf);
 }
 
-fn exetute_logical_or<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // ORがネストされた状態((A || B || C)内のどれかで1になったらネストを抜けた先までジャンプできる
-    // これにより高速化できるが, ジャンプ先管理の手間が増えるので今回はネストごとに逐次比較する
-    let true_label_index = buf.label_index;
-    let false_label_index = buf.label_index + 1;
-    let end_label_index = buf.label_index + 2;
-    let true_label = format!("Label{}:", true_label_index);
-    let false_label = format!("Label{}:", false_label_index);
-    let end_label = format!("Label{}:", end_label_index);
-    let jump_true = format!("    jne Label{}", true_label_index);
-    let jump_false = format!("    je Label{}", false_label_index);
-    let jump_end = format!("    jmp Label{}", end_label_index);
-    buf.label_index += 3;
-
-    let comp_zero = "    cmp rax, 0";
-    // 左側の値を計算
-    output_ast(*ast.left.take().unwrap(), buf);
-    buf.output_pop("rax");
-    // 0と比較
-    buf.output(comp_zero);
-    // 1ならば(0でないならば)True時の処理を行う
-    buf.output(&jump_true);
-    // 右側の値を計算
-    output_ast(*ast.right.take().unwrap(), buf);
-    buf.output_pop("rax");
-    // 0と比較
-    buf.output(comp_zero);
-    // 0ならばFalse時の処理に飛ぶ
-    // 1のときはそのままtrueに行く
-    buf.output(&jump_false);
-
-    // True時の処理
-    buf.output(&true_label);
-    buf.output_push_num(1);
-    // End文へ飛ぶ
-    buf.output(&jump_end);
-
-    // False時の処理
-    buf.output(&false_label);
-    buf.output_push_num(0);
-    // Endラベルを挿入
-    buf.output(&end_label);
+// 書き込む値がレジスタに割り当てられていればそのレジスタから直接書き込む
+fn write_store<T: Write>(type_: IrType, address: VReg, src: VReg, buf: &mut OutputBuffer<T>) {
+    let address = output_vreg_address(address, "rdi", buf);
+    let register = match buf.get_vreg(src) {
+        Location::Register(register) => register,
+        Location::Immediate(value) => {
+            output_store_immediate(type_, &address, value, buf);
+            return;
+        }
+        _ => {
+            output_read_vreg("rax", src, buf);
+            "rax"
+        }
+    };
+    output_store(type_, &address, register, buf);
 }
 
-fn exetute_increment<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    // 前置インクリメント -> val = val + 1 を実行してスタックに積む
-    // 後置インクリメント -> valをスタックに積み, val=val + 1を実行してスタックに積む. そしてスタックから削除
-    if AstKind::Operation(Operation::ForwardIncrement) == ast.kind {
-        output_ast(*ast.operand.take().unwrap(), buf);
+// どちらかがレジスタならば直接コピーし, スタック同士のコピーはraxを経由する
+fn write_copy<T: Write>(dst: VReg, src: VReg, buf: &mut OutputBuffer<T>) {
+    let dst_location = buf.get_vreg(dst);
+    let src_location = buf.get_vreg(src);
+    if dst_location == src_location {
+        return;
+    }
+    if dst_location.is_register() || src_location.is_register() {
+        buf.output(&format!("    mov {}, {}", dst_location, src_location));
     } else {
-        output_ast(*ast.right.take().unwrap(), buf);
-        output_ast(*ast.left.take().unwrap(), buf);
-        buf.output_pop("rax");
+        output_read_vreg("rax", src, buf);
+        output_write_vreg(dst, "rax", buf);
     }
 }
 
-pub fn output_operation_ast<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    match &ast.kind {
-        AstKind::Operation(Operation::Add | Operation::Sub) => exetute_add(ast, buf),
-        AstKind::Operation(Operation::Mul) => exetute_mul(ast, buf),
-        AstKind::Operation(Operation::Div | Operation::Rem) => exetute_div(ast, buf),
-        AstKind::Operation(Operation::Eq | Operation::NotEq) => exetute_eq(ast, buf),
-        AstKind::Operation(Operation::Gt | Operation::Lt | Operation::Ge | Operation::Le) => {
-            exetute_comp(ast, buf)
-        }
-        AstKind::Operation(Operation::Not) => exetute_not(ast, buf),
-        AstKind::Operation(Operation::Assign) => exetute_assign(ast, buf),
-        AstKind::Operation(Operation::BitAnd | Operation::BitOr | Operation::BitXor) => {
-            exetute_bit_operation(ast, buf)
+pub fn output_instruction<T: Write>(
+    instruction: &Instruction,
+    func: &IrFunction,
+    buf: &mut OutputBuffer<T>,
+) {
+    match instruction {
+        // 即値として埋め込む定数は書き込まない
+        Instruction::Const { dst, .. } if buf.get_vreg(*dst).is_immediate() => {}
+        Instruction::Const { dst, value } => write_const(*dst, *value, buf),
+        Instruction::Copy { dst, src } => write_copy(*dst, *src, buf),
+        Instruction::Binary {
+            op,
+            dst,
+            left,
+            right,
+        } => write_binary_operation(func, *op, *dst, *left, *right, buf),
+        Instruction::Unary { op, dst, src } => write_unary_operation(func, *op, *dst, *src, buf),
+        Instruction::Compare {
+            op,
+            dst,
+            left,
+            right,
+        } => write_comparison(*op, *dst, *left, *right, buf),
+        Instruction::Cast { dst, src } => write_cast(instruction, func, *dst, *src, buf),
+        // アドレス指定に埋め込むローカル変数のアドレスは計算しない
+        Instruction::LocalAddress { dst, .. }
+            if matches!(buf.get_vreg(*dst), Location::LocalAddress(_)) => {}
+        Instruction::LocalAddress { dst, offset } => {
+            write_address(*dst, &format!("[rbp - {}]", offset), buf)
         }
-        AstKind::Operation(Operation::BitNot) => exetute_bitnot(ast, buf),
-        AstKind::Operation(Operation::And) => exetute_logical_and(ast, buf),
-        AstKind::Operation(Operation::Or) => exetute_logical_or(ast, buf),
-        AstKind::Operation(Operation::ForwardIncrement | Operation::BackwardIncrement) => {
-            exetute_increment(ast, buf)
+        Instruction::GlobalAddress { dst, label } => {
+            write_address(*dst, &format!("[rip + {}]", label), buf)
         }
-        _ => unsupported_ast_err(&ast),
+        Instruction::FunctionAddress { dst, name } => write_function_address(*dst, name, buf),
+        Instruction::Load { dst, address, .. } => write_load(func, *dst, *address, buf),
+        Instruction::Store {
+            type_,
+            address,
+            src,
+            ..
+        } => write_store(*type_, *address, *src, buf),
+        // 引数は関数の先頭でまとめて受け取る(write_params)
+        Instruction::Param { .. } => {}
+        Instruction::Call {
+            dst,
+            callee,
+            args,
+            variadic,
+        } => write_funccall(func, *dst, callee, args, *variadic, buf),
+        Instruction::VaStart {
+            va_list,
+            reg_save_area,
+            named_arg_count,
+        } => write_va_start(*va_list, *reg_save_area, *named_arg_count, buf),
+        Instruction::VaArg { dst, va_list } => write_va_arg(*dst, *va_list, buf),
     }
 }
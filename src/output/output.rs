@@ -1,27 +1,30 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use crate::ast::ast::*;
 use crate::ast::error::*;
-use crate::definition::number::Number;
+use crate::definition::linkage::Linkage;
+use crate::definition::types::align_to;
 use crate::definition::variables::*;
+use crate::ir::ir::*;
+use crate::ir::lower::lower_asts;
+use crate::ir::pass::run_passes;
 use crate::output::controls::*;
 use crate::output::operations::*;
+use crate::output::regalloc::*;
 
-pub const FUNC_ARG_REGISTERS: [&str; 5] = ["rdi", "rdx", "rcx", "r8", "r9"];
-
-#[derive(PartialEq, Debug)]
-pub enum LoopKind {
-    For,
-    While,
-}
+pub const FUNC_ARG_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
 
 pub struct OutputBuffer<T: Write> {
     buf: T,
     pub label_index: usize,
-    stack_alignment: i32,
-    break_info: Vec<(usize, LoopKind)>,
+    stack_depth: usize,
+    defined_functions: HashSet<String>,
+    vreg_locations: Vec<Location>,
+    saved_registers: Vec<(&'static str, usize)>,
+    block_label_index: usize,
 }
 
 impl<T: Write> OutputBuffer<T> {
@@ -29,32 +32,36 @@ impl<T: Write> OutputBuffer<T> {
         Self {
             buf,
             label_index: 0,
-            stack_alignment: 0, // 関数呼び出し用に使用中のスタックサイズを把握する
-            // 関数呼び出し時はスタックのアライメントが16バイトである必要があるため,
-            // 16バイトアライメントでの位置を記録する
-            // stack_alignment = 4　ならば, 関数呼び出し時は 16 -4 = 12 バイト,
+            stack_depth: 0, // 関数呼び出し用に使用中のスタックサイズを把握する
+            // 関数のプレリュードでrbpは16バイト境界に置かれるため,
+            // rbpからrspまでのバイト数を記録しておく
+            // stack_depth = 24　ならば, 関数呼び出し時は 8 バイト,
             // スタックを増やす必要がある
-            break_info: vec![],
+            defined_functions: HashSet::new(),
+            vreg_locations: vec![],
+            saved_registers: vec![],
+            block_label_index: 0,
         }
     }
 
-    pub fn enter_loop_control(&mut self, type_: LoopKind) {
-        self.break_info.push((self.label_index, type_));
+    // 翻訳単位内で実装された関数を登録する
+    pub fn set_defined_functions(&mut self, funcs: &[IrFunction]) {
+        for func in funcs {
+            self.defined_functions.insert(func.name.clone());
+        }
     }
 
-    pub fn exit_loop_control(&mut self) {
-        self.break_info.pop();
+    pub fn is_defined_function(&self, func_name: &str) -> bool {
+        self.defined_functions.contains(func_name)
     }
 
-    pub fn get_break_label(&mut self) -> Result<String, ()> {
-        if let Some((label_index, type_)) = self.break_info.last() {
-            if *type_ == LoopKind::For {
-                Ok(format!("    jmp .LabelForEnd{}", label_index))
-            } else {
-                Ok(format!("    jmp .LabelWhileEnd{}", label_index))
-            }
+    // 呼び出し先のシンボル
+    // 翻訳単位内で実装されていない関数は外部(共有ライブラリ等)のシンボルとしてPLT経由で呼び出す
+    pub fn get_call_target(&self, func_name: &str) -> String {
+        if self.is_defined_function(func_name) {
+            func_name.to_string()
         } else {
-            Err(())
+            format!("{}@PLT", func_name)
         }
     }
 
@@ -66,6 +73,15 @@ impl<T: Write> OutputBuffer<T> {
         self.label_index += 1;
     }
 
+    // 仮想レジスタの値を置く場所(レジスタまたはスタック)
+    pub fn get_vreg(&self, vreg: VReg) -> Location {
+        self.vreg_locations[vreg.0]
+    }
+
+    pub fn get_block_label(&self, block: BlockId) -> String {
+        format!(".LabelBlock{}", self.block_label_index + block.0)
+    }
+
     #[inline]
     pub fn output(&mut self, line: &str) {
         writeln!(self.buf, "{}", line).unwrap();
@@ -74,25 +90,34 @@ impl<T: Write> OutputBuffer<T> {
     #[inline]
     pub fn output_push(&mut self, register: &str) {
         writeln!(self.buf, "    push {}", register).unwrap();
-        self.stack_alignment = (self.stack_alignment + 8) / 16;
+        self.stack_depth += 8;
     }
 
     #[inline]
-    pub fn output_push_num(&mut self, num: u64) {
-        // 即値は4ビット
-        writeln!(self.buf, "    push {}", num).unwrap();
-        self.stack_alignment = (self.stack_alignment + 4) / 16;
+    pub fn output_sub_rsp(&mut self, size: usize) {
+        writeln!(self.buf, "    sub rsp, {}", size).unwrap();
+        self.stack_depth += size;
     }
 
     #[inline]
-    pub fn output_pop(&mut self, register: &str) {
-        writeln!(self.buf, "    pop {}", register).unwrap();
-        // 16バイトアライメントなので正の値とする
-        let mut rem = self.stack_alignment - 8;
-        if rem < 0 {
-            rem += 16;
+    pub fn output_add_rsp(&mut self, size: usize) {
+        writeln!(self.buf, "    add rsp, {}", size).unwrap();
+        self.stack_depth -= size;
+    }
+
+    // 関数のプレリュードでrbpを設定した時点でスタック使用量をリセットする
+    pub fn enter_function_frame(&mut self) {
+        self.stack_depth = 0;
+    }
+
+    // スタックにsizeバイト積んだ後でrspを16バイト境界にするために必要なパディングを返す
+    pub fn get_call_padding(&self, size: usize) -> usize {
+        let misalignment = (self.stack_depth + size) % 16;
+        if misalignment == 0 {
+            0
+        } else {
+            16 - misalignment
         }
-        self.stack_alignment = rem;
     }
 }
 
@@ -110,272 +135,319 @@ impl<T: Write> Write for OutputBuffer<T> {
 pub fn output_function_prelude<T: Write>(
     func_name: &str,
     local_val_size: &usize,
+    linkage: &Linkage,
     buf: &mut OutputBuffer<T>,
 ) {
     let func_label = &format!("{}:", func_name);
     buf.output("");
+    // 外部リンケージの関数は他のオブジェクトから呼び出せるようにエクスポートする
+    if *linkage == Linkage::External {
+        buf.output(&format!(".globl {}", func_name));
+    }
+    buf.output(&format!(".type {}, @function", func_name));
     buf.output(func_label);
-    buf.output_push("rbp");
+    buf.output("    push rbp");
     buf.output("    mov rbp, rsp");
+    // call直後のrspは16バイト境界から8バイトずれているので, rbpの退避後のrbpは16バイト境界にある
+    buf.enter_function_frame();
     // ローカル変数を使用するときのみ
     // rbpの退避分でスタックは8バイト使用している
     if *local_val_size > 8 {
-        let sub_rsp_instruction = &format!("    sub rsp, {}", local_val_size - 8);
-        buf.output(sub_rsp_instruction);
+        buf.output_sub_rsp(local_val_size - 8);
+    }
+    // 使用する呼び出し先保存レジスタをスタックフレーム内に退避する
+    for (register, offset) in buf.saved_registers.clone() {
+        buf.output(&format!("    mov [rbp - {}], {}", offset, register));
     }
 }
 
+// return文からも呼ばれるため, 記録しているスタック使用量は変更しない
 pub fn output_function_epilogue<T: Write>(buf: &mut OutputBuffer<T>) {
+    for (register, offset) in buf.saved_registers.clone() {
+        buf.output(&format!("    mov {}, [rbp - {}]", register, offset));
+    }
     buf.output("    mov rsp, rbp");
-    buf.output_pop("rbp");
+    buf.output("    pop rbp");
     buf.output("    ret");
 }
 
-fn push_number<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let AstKind::ImmidiateInterger(Number::U64(num)) = ast.kind {
-        buf.output_push_num(num)
-    } else {
-        unexpected_ast_err(&ast, "imidiate number");
-    }
+// addressが指すアドレスから型のサイズ分の値をregisterに読み込む
+// 8バイト未満の整数は符号に合わせて拡張する
+pub fn output_load<T: Write>(
+    type_: IrType,
+    register: &str,
+    address: &str,
+    buf: &mut OutputBuffer<T>,
+) {
+    let unsigned = type_.is_unsigned();
+    let dword = get_register_name(register, 4);
+    let instruction = match type_.size() {
+        1 if unsigned => format!("movzx {}, BYTE PTR", dword),
+        1 => format!("movsx {}, BYTE PTR", register),
+        2 if unsigned => format!("movzx {}, WORD PTR", dword),
+        2 => format!("movsx {}, WORD PTR", register),
+        4 if unsigned => format!("mov {}, DWORD PTR", dword),
+        4 => format!("movsxd {}, DWORD PTR", register),
+        _ => format!("mov {}, QWORD PTR", register),
+    };
+    buf.output(&format!("    {} [{}]", instruction, address));
 }
 
-pub fn push_variable_value<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let AstKind::Variable(Variable::LocalVal(local_val)) = &ast.kind {
-        let offset = local_val.frame_offset;
-        buf.output(&format!("    push [rbp - {}]", offset));
+// raxの値を型のサイズに切り詰め, 符号拡張またはゼロ拡張する
+// _Bool型への変換は0以外の値を1とする
+pub fn output_cast<T: Write>(type_: IrType, buf: &mut OutputBuffer<T>) {
+    if type_ == IrType::Bool {
+        buf.output("    cmp rax, 0");
+        buf.output("    setne al");
+        buf.output("    movzx eax, al");
+        return;
     }
+    let unsigned = type_.is_unsigned();
+    let instruction = match type_.size() {
+        1 if unsigned => "    movzx eax, al",
+        1 => "    movsx rax, al",
+        2 if unsigned => "    movzx eax, ax",
+        2 => "    movsx rax, ax",
+        4 if unsigned => "    mov eax, eax",
+        4 => "    movsxd rax, eax",
+        _ => return,
+    };
+    buf.output(instruction);
 }
 
-pub fn push_variable_address<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    // 現在はローカル変数のみ対応
-    if let AstKind::Variable(Variable::LocalVal(local_val)) = &ast.kind {
-        let lea_instruction = format!("    lea rax, [rbp - {}]", local_val.frame_offset);
-        buf.output(&lea_instruction);
-        buf.output_push("rax");
-    } else {
-        unexpected_ast_err(&ast, "local variable");
-    }
-}
-
-// ポインターが指すアドレスを求める
-// long **a に対して,
-// **aのアドレスは
-// aのアドレスを積み,
-// aのアドレスの指す値を取り出すを2回繰り返す
-pub fn push_pointer_address<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    let mut deref_ast = ast;
-    let mut deref_count = 0;
-    while let AstKind::Deref = &deref_ast.kind {
-        deref_ast = *deref_ast.operand.unwrap();
-        deref_count += 1;
-    }
-    let val_ast = deref_ast;
-    push_variable_address(val_ast, buf);
-    while deref_count != 0 {
-        buf.output_pop("rax");
-        buf.output("    mov rax, [rax]");
-        buf.output_push("rax");
-        deref_count -= 1;
-    }
+// registerの値をaddressが指すアドレスに型のサイズ分書き込む
+pub fn output_store<T: Write>(
+    type_: IrType,
+    address: &str,
+    register: &str,
+    buf: &mut OutputBuffer<T>,
+) {
+    let register = get_register_name(register, type_.size());
+    buf.output(&format!("    mov [{}], {}", address, register));
 }
 
-// ポインターが指すアドレスの値を求める
-// ポインタが指すアドレスの値を取る
-pub fn push_deref_value<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    push_pointer_address(ast, buf);
-    buf.output_pop("rax");
-    buf.output("    mov rax, [rax]");
-    buf.output_push("rax");
+// 即値をaddressが指すアドレスに型のサイズ分書き込む
+pub fn output_store_immediate<T: Write>(
+    type_: IrType,
+    address: &str,
+    value: i64,
+    buf: &mut OutputBuffer<T>,
+) {
+    // 定数は書き込む型より広い値を持つことがあるので, 書き込む幅に切り詰める
+    let (size, value) = match type_.size() {
+        1 => ("BYTE", value as i8 as i64),
+        2 => ("WORD", value as i16 as i64),
+        4 => ("DWORD", value as i32 as i64),
+        _ => ("QWORD", value),
+    };
+    buf.output(&format!("    mov {} PTR [{}], {}", size, address, value));
 }
 
-// 配列が指すアドレスを求める
-// long a[5][10]の場合,
-// a[2][3]へのアクセスでは
-// deref(deref(a, 2, size=80), 3, size=8)となっている
-// そこで 3 * 8をスタックに積み,
-// 2*80をスタックに積む,
-// 最後aのアドレスをスタックに積んで,
-// a + 160 をスタックに積む
-// そして a + 24 をスタックに積む
-pub fn push_array_elem_address<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    let mut index_ast = ast;
-    let mut indexing_times = 0;
-    while let AstKind::Index = &index_ast.kind {
-        // index番号をスタックに積む
-        let index_num_ast = index_ast.right.take().unwrap();
-        output_ast(*index_num_ast, buf);
-        // その配列のサイズをスタックに積む
-        buf.output_push_num(index_ast.type_.size as u64);
-        // サイズ×index番号でオフセットを求めスタックに積む
-        write_operation(buf, "imul");
-        index_ast = *index_ast.left.unwrap();
-        indexing_times += 1;
-    }
-    //  indexのあとはindex_astは変数なので, この変数のアドレスを取得する
-    let val_ast = index_ast;
-    push_variable_address(val_ast, buf);
-    // あとはオフセットを引く
-    for _ in 0..indexing_times {
-        write_operation(buf, "sub");
+// 仮想レジスタの値をレジスタに読み込む
+#[inline]
+pub fn output_read_vreg<T: Write>(register: &str, vreg: VReg, buf: &mut OutputBuffer<T>) {
+    let location = buf.get_vreg(vreg);
+    if !matches!(location, Location::Register(name) if name == register) {
+        buf.output(&format!("    mov {}, {}", register, location));
     }
 }
 
-pub fn push_array_elem_value<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    push_array_elem_address(ast, buf);
-    buf.output_pop("rax");
-    buf.output("    mov rax, [rax]");
-    buf.output_push("rax");
+// レジスタの値を仮想レジスタに書き込む
+#[inline]
+pub fn output_write_vreg<T: Write>(vreg: VReg, register: &str, buf: &mut OutputBuffer<T>) {
+    let location = buf.get_vreg(vreg);
+    if !matches!(location, Location::Register(name) if name == register) {
+        buf.output(&format!("    mov {}, {}", location, register));
+    }
 }
 
-// アドレスを取得する
-// アドレスを取得できるのは変数型と, プリミティブ型を返す演算のみ
-// AST作成時にチェック済み
-pub fn push_address<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let address_ast = ast.operand.take().unwrap();
-    match &address_ast.kind {
-        AstKind::Address => {
-            unaddressable_ast_err(&address_ast);
-        }
-        AstKind::Variable(_val) => {
-            push_variable_address(*address_ast, buf);
-        }
-        AstKind::Deref => {
-            push_pointer_address(*address_ast, buf);
-        }
-        AstKind::Index => {
-            push_array_elem_address(*address_ast, buf);
+// 仮想レジスタが持つアドレスのアドレス指定
+// レジスタに割り当てられていればそのレジスタ, ローカル変数のアドレスであればrbpからのオフセットとし,
+// スタックに退避されていればscratchレジスタに読み込んで使用する
+pub fn output_vreg_address<T: Write>(
+    vreg: VReg,
+    scratch: &str,
+    buf: &mut OutputBuffer<T>,
+) -> String {
+    match buf.get_vreg(vreg) {
+        Location::Stack(_) => {
+            output_read_vreg(scratch, vreg, buf);
+            scratch.to_string()
         }
-        _ => output_ast(ast, buf),
+        location => location.to_string(),
     }
 }
 
-#[inline]
-pub fn write_pop_one_value<T: Write>(buf: &mut OutputBuffer<T>) {
-    buf.output_pop("rax");
-}
-
-#[inline]
-pub fn write_pop_two_values<T: Write>(buf: &mut OutputBuffer<T>) {
-    buf.output_pop("rax");
-    buf.output_pop("rdi");
+// 値の場所の間のコピー
+// スタック同士のコピーはraxを経由する
+fn output_move<T: Write>(dst: Location, src: Location, buf: &mut OutputBuffer<T>) {
+    if dst == src {
+        return;
+    }
+    if dst.is_register() || src.is_register() {
+        buf.output(&format!("    mov {}, {}", dst, src));
+    } else {
+        buf.output(&format!("    mov rax, {}", src));
+        buf.output(&format!("    mov {}, rax", dst));
+    }
 }
 
-// 複文のコンパイル
-#[allow(clippy::branches_sharing_code)]
-fn excute_exprs<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    let expr_ast_vec = ast.exprs.take().unwrap();
-    for expr_ast in expr_ast_vec {
-        // 複文側の最後, 各制御文側でpopしているのでこちらではpopしない
-        // if文やfor文の{}後も複文の制御構文側でpopしているのでこちらでは行わない
-        if matches!(
-            &expr_ast.kind,
-            AstKind::Expressions | AstKind::Control(Control::For | Control::If | Control::While)
-        ) {
-            output_ast(expr_ast, buf);
+// 同時に行うコピーの列を順番に出力する
+// 他のコピーの読み出し元を上書きしないように, 読み出されない場所への書き込みから行う
+// 書き込み先が全て読み出し元となっている(循環している)場合は, 1つの読み出し元をraxに逃がして循環を解く
+// 循環はレジスタ同士のコピーでのみ起こるので, rax経由のスタック同士のコピーとは重ならない
+pub fn output_parallel_move<T: Write>(moves: &[(Location, Location)], buf: &mut OutputBuffer<T>) {
+    let mut moves: Vec<(Location, Location)> = moves
+        .iter()
+        .filter(|(dst, src)| dst != src)
+        .copied()
+        .collect();
+    while !moves.is_empty() {
+        let ready = moves
+            .iter()
+            .position(|(dst, _)| !moves.iter().any(|(_, src)| src == dst));
+        if let Some(index) = ready {
+            let (dst, src) = moves.remove(index);
+            output_move(dst, src, buf);
         } else {
-            output_ast(expr_ast, buf);
-            buf.output_pop("rax");
+            let blocked = moves[0].1;
+            output_move(Location::Register("rax"), blocked, buf);
+            for (_, src) in moves.iter_mut() {
+                if *src == blocked {
+                    *src = Location::Register("rax");
+                }
+            }
         }
     }
 }
 
-// 式のコンパイル
-// スタックには値を積まない
-pub fn output_formula_ast<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    match &ast.kind {
-        AstKind::Operation(_) => {
-            output_operation_ast(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Control(_) => output_control_ast(ast, buf),
-        AstKind::ImmidiateInterger(_num) => {
-            push_number(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Variable(_val) => {
-            push_variable_value(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Address => {
-            push_address(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Deref => {
-            push_deref_value(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Index => {
-            push_array_elem_value(ast, buf);
-            buf.output_pop("rax");
-        }
-        AstKind::Expressions => excute_exprs(ast, buf),
-        AstKind::FuncionCall(_func, _type) => {
-            execute_funccall(ast, buf);
-            buf.output_pop("rax");
-        }
-        _ => unsupported_ast_err(&ast),
+// 可変長引数関数では, va_argで参照できるように全ての引数レジスタをレジスタ退避領域に格納する
+fn output_save_arg_registers<T: Write>(reg_save_area: usize, buf: &mut OutputBuffer<T>) {
+    for (count, register) in FUNC_ARG_REGISTERS.iter().enumerate() {
+        let offset = reg_save_area - 8 * count;
+        buf.output(&format!("    mov [rbp - {}], {}", offset, register));
     }
 }
 
-pub fn output_ast<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    match &ast.kind {
-        AstKind::Operation(_) => output_operation_ast(ast, buf),
-        AstKind::Control(_) => output_control_ast(ast, buf),
-        AstKind::ImmidiateInterger(_num) => push_number(ast, buf),
-        AstKind::Variable(_val) => push_variable_value(ast, buf),
-        AstKind::Address => push_address(ast, buf),
-        AstKind::Deref => push_deref_value(ast, buf),
-        AstKind::Index => push_array_elem_value(ast, buf),
-        AstKind::Expressions => excute_exprs(ast, buf),
-        AstKind::FuncionCall(_func, _type) => execute_funccall(ast, buf),
-        _ => unsupported_ast_err(&ast),
-    }
+// 仮想レジスタをレジスタに割り当て, 割り当てられなかった仮想レジスタをローカル変数の領域の下に8バイトずつ配置する
+// 使用する呼び出し先保存レジスタの退避領域はさらにその下に配置する
+// 関数呼び出し時のスタック調整を減らすため, スタックフレームは16バイト単位で確保する
+fn allocate_vreg_frame<T: Write>(func: &IrFunction, buf: &mut OutputBuffer<T>) -> usize {
+    let allocation = allocate_registers(func, func.frame_size);
+    let save_offset = func.frame_size + 8 * allocation.spill_count;
+    buf.saved_registers = allocation
+        .callee_saved
+        .iter()
+        .enumerate()
+        .map(|(index, register)| (*register, save_offset + 8 * index))
+        .collect();
+    buf.vreg_locations = allocation.locations;
+    let slot_count = allocation.spill_count + allocation.callee_saved.len();
+    let used_size = func.frame_size - 8 + 8 * slot_count;
+    align_to(used_size, 16) + 8
 }
 
-fn output_push_arg_to_stack<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    push_variable_address(ast, buf);
-    write_pop_two_values(buf);
-    buf.output("    mov [rax], rdi");
+fn output_function<T: Write>(func: &IrFunction, buf: &mut OutputBuffer<T>) {
+    let frame_size = allocate_vreg_frame(func, buf);
+    buf.block_label_index = buf.label_index;
+    buf.label_index += func.blocks.len();
+    output_function_prelude(&func.name, &frame_size, &func.linkage, buf);
+    if let Some(reg_save_area) = func.reg_save_area {
+        output_save_arg_registers(reg_save_area, buf);
+    }
+    write_params(func, buf);
+    for (index, block) in func.blocks.iter().enumerate() {
+        let block_id = BlockId(index);
+        let label = buf.get_block_label(block_id);
+        buf.output(&format!("{}:", label));
+        for instruction in &block.instructions {
+            output_instruction(instruction, func, buf);
+        }
+        output_terminator(block.terminator.as_ref().unwrap(), block_id, buf);
+    }
+    buf.output(&format!(".size {}, .-{}", func.name, func.name));
 }
 
-// 引数をローカルスタックに格納する
-pub fn output_push_args_to_stack<T: Write>(ast: Ast, buf: &mut OutputBuffer<T>) {
-    if let Some(args_ast) = ast.exprs {
-        let argcount = args_ast.len();
-        // 後ろの引数からスタックに積んでいく
-        for register in FUNC_ARG_REGISTERS[0..argcount].iter().rev() {
-            buf.output_push(*register);
-        }
-        for arg_ast in args_ast {
-            output_push_arg_to_stack(arg_ast, buf);
+fn output_static_data<T: Write>(data: &StaticData, buf: &mut OutputBuffer<T>) {
+    match data {
+        StaticData::Integer(1, value) => buf.output(&format!("    .byte {}", *value as u8)),
+        StaticData::Integer(2, value) => buf.output(&format!("    .short {}", *value as u16)),
+        StaticData::Integer(4, value) => buf.output(&format!("    .long {}", *value as u32)),
+        StaticData::Integer(_, value) => buf.output(&format!("    .quad {}", value)),
+        StaticData::Zero(size) => buf.output(&format!("    .zero {}", size)),
+        StaticData::Address(label, 0) => buf.output(&format!("    .quad {}", label)),
+        StaticData::Address(label, offset) => {
+            buf.output(&format!("    .quad {}{:+}", label, offset))
         }
     }
 }
 
-fn output_function<T: Write>(mut ast: Ast, buf: &mut OutputBuffer<T>) {
-    match &ast.kind {
-        AstKind::FunctionImplementation((func_name, local_val_size)) => {
-            output_function_prelude(func_name, local_val_size, buf);
-            let func_context_ast = ast.context.take().unwrap();
-            // 引数をスタックフレームに格納
-            output_push_args_to_stack(ast, buf);
-            output_ast(*func_context_ast, buf);
-            output_function_epilogue(buf);
+// 静的記憶域期間を持つ変数の領域を確保する
+// 初期値があれば.data, なければ.bssに配置する
+fn output_global_variable<T: Write>(ast: &Ast, buf: &mut OutputBuffer<T>) {
+    if let AstKind::GlobalVariableDefinition(Variable::GlobalVal(global_val)) = &ast.kind {
+        let label = &global_val.label;
+        buf.output("");
+        if global_val.linkage == Linkage::External {
+            buf.output(&format!(".globl {}", label));
+        }
+        if global_val.init.is_some() {
+            buf.output(".data");
+        } else {
+            buf.output(".bss");
         }
-        _ => unsupported_ast_err(&ast),
+        buf.output(&format!(".align {}", global_val.type_.get_align()));
+        buf.output(&format!(".type {}, @object", label));
+        buf.output(&format!(".size {}, {}", label, global_val.type_.size));
+        buf.output(&format!("{}:", label));
+        if let Some(init) = &global_val.init {
+            for data in init {
+                output_static_data(data, buf);
+            }
+        } else {
+            output_static_data(&StaticData::Zero(global_val.type_.size), buf);
+        }
+        buf.output(".text");
+    } else {
+        unexpected_ast_err(ast, "global variable");
     }
 }
 
 fn write_assembly_header<T: Write>(buf: &mut OutputBuffer<T>) {
     buf.output(".intel_syntax noprefix");
-    buf.output(".globl main");
+    buf.output(".text");
 }
 
+// 実行可能スタックを必要としないことをリンカに伝える
+fn write_assembly_footer<T: Write>(buf: &mut OutputBuffer<T>) {
+    buf.output("");
+    buf.output(".section .note.GNU-stack,\"\",@progbits");
+}
+
+// 関数実装は中間表現に変換し, 最適化パスを実行してから出力する
 pub fn output_assembly(asts: Vec<Ast>, output_file: &Path) {
     let buf = BufWriter::new(fs::File::create(output_file).unwrap());
     let mut outputbuf = OutputBuffer::new(buf);
+    let mut funcs = lower_asts(&asts);
+    run_passes(&mut funcs);
+    outputbuf.set_defined_functions(&funcs);
     write_assembly_header(&mut outputbuf);
-    for ast in asts {
-        output_function(ast, &mut outputbuf);
+    for ast in &asts {
+        if let AstKind::GlobalVariableDefinition(_) = &ast.kind {
+            output_global_variable(ast, &mut outputbuf);
+        }
+    }
+    for func in &funcs {
+        output_function(func, &mut outputbuf);
+    }
+    write_assembly_footer(&mut outputbuf);
+}
+
+// 関数実装の最適化後の中間表現をテキストで出力する
+pub fn output_ir<W: Write>(asts: &[Ast], buf: &mut W) {
+    let mut funcs = lower_asts(asts);
+    run_passes(&mut funcs);
+    for func in funcs {
+        writeln!(buf, "{}", func).unwrap();
     }
 }
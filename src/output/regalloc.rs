@@ -0,0 +1,443 @@
+use std::fmt;
+
+use crate::ir::ir::*;
+
+// 割り当て対象のレジスタ
+// rax, rdi, rdx, r11は命令の出力で一時的に使用するので割り当てない
+// 呼び出し元保存レジスタは関数呼び出しで破壊されるので, 呼び出しをまたいで生存する値には割り当てない
+pub const CALLER_SAVED_REGISTERS: [&str; 5] = ["rsi", "rcx", "r8", "r9", "r10"];
+pub const CALLEE_SAVED_REGISTERS: [&str; 5] = ["rbx", "r12", "r13", "r14", "r15"];
+
+// 各レジスタの8, 4, 2, 1バイトの名前
+const REGISTER_NAMES: [[&str; 4]; 14] = [
+    ["rax", "eax", "ax", "al"],
+    ["rdi", "edi", "di", "dil"],
+    ["rdx", "edx", "dx", "dl"],
+    ["r11", "r11d", "r11w", "r11b"],
+    ["rsi", "esi", "si", "sil"],
+    ["rcx", "ecx", "cx", "cl"],
+    ["r8", "r8d", "r8w", "r8b"],
+    ["r9", "r9d", "r9w", "r9b"],
+    ["r10", "r10d", "r10w", "r10b"],
+    ["rbx", "ebx", "bx", "bl"],
+    ["r12", "r12d", "r12w", "r12b"],
+    ["r13", "r13d", "r13w", "r13b"],
+    ["r14", "r14d", "r14w", "r14b"],
+    ["r15", "r15d", "r15w", "r15b"],
+];
+
+/// 8バイトのレジスタ名から指定したサイズのレジスタ名を返す
+pub fn get_register_name(register: &str, size: usize) -> &'static str {
+    let names = REGISTER_NAMES
+        .iter()
+        .find(|names| names[0] == register)
+        .unwrap();
+    match size {
+        1 => names[3],
+        2 => names[2],
+        4 => names[1],
+        _ => names[0],
+    }
+}
+
+/// 仮想レジスタの値を置く場所
+///
+/// - Register - 物理レジスタ
+/// - Stack - rbp - offset のスタック領域(8バイト)
+/// - LocalAddress - ローカル変数のアドレス rbp - offset
+///   (load/storeのアドレスとしてのみ使われるため, 値を持たずにアドレス指定に埋め込む)
+/// - Immediate - 32ビットに収まる定数(即値として命令に埋め込む)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Register(&'static str),
+    Stack(usize),
+    LocalAddress(usize),
+    Immediate(i64),
+}
+
+impl Location {
+    pub fn is_register(&self) -> bool {
+        matches!(self, Location::Register(_))
+    }
+
+    pub fn is_immediate(&self) -> bool {
+        matches!(self, Location::Immediate(_))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Register(register) => write!(f, "{}", register),
+            Location::Stack(offset) => write!(f, "QWORD PTR [rbp - {}]", offset),
+            Location::LocalAddress(offset) => write!(f, "rbp - {}", offset),
+            Location::Immediate(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// レジスタ割り当ての結果
+///
+/// member
+/// - locations - 仮想レジスタの番号をインデックスとした値の場所
+/// - spill_count - スタックに退避した仮想レジスタの数(スタック領域は8バイトずつ確保する)
+/// - callee_saved - 使用した呼び出し先保存レジスタ(プレリュードで退避, エピローグで復元する)
+pub struct Allocation {
+    pub locations: Vec<Location>,
+    pub spill_count: usize,
+    pub callee_saved: Vec<&'static str>,
+}
+
+// load/storeのアドレスとしてのみ使われる, ローカル変数のアドレスを持つ仮想レジスタを探す
+// 書き込みが1回だけであれば値は関数内で変わらないので, レジスタを割り当てずにアドレス指定に埋め込む
+fn find_local_addresses(func: &IrFunction) -> Vec<Option<usize>> {
+    let vreg_count = func.vreg_types.len();
+    let mut offsets = vec![None; vreg_count];
+    let mut def_counts = vec![0; vreg_count];
+    let mut other_uses = vec![false; vreg_count];
+    for block in &func.blocks {
+        for instruction in &block.instructions {
+            if let Some(dst) = instruction.get_dst() {
+                def_counts[dst.0] += 1;
+            }
+            match instruction {
+                Instruction::LocalAddress { dst, offset } => offsets[dst.0] = Some(*offset),
+                Instruction::Load { .. } => {}
+                Instruction::Store { src, .. } => other_uses[src.0] = true,
+                _ => {
+                    for vreg in instruction.get_uses() {
+                        other_uses[vreg.0] = true;
+                    }
+                }
+            }
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            other_uses[vreg.0] = true;
+        }
+    }
+    (0..vreg_count)
+        .map(|vreg| offsets[vreg].filter(|_| def_counts[vreg] == 1 && !other_uses[vreg]))
+        .collect()
+}
+
+// 即値として命令に埋め込める定数を持つ仮想レジスタを探す
+// 二項演算(除算, 剰余以外)と比較の右辺, storeの値としてのみ読み出される32ビットに収まる定数は,
+// レジスタを割り当てずに即値とする
+fn find_immediates(func: &IrFunction) -> Vec<Option<i64>> {
+    let mut other_uses = vec![false; func.vreg_types.len()];
+    for block in &func.blocks {
+        for instruction in &block.instructions {
+            match instruction {
+                Instruction::Binary { op, left, .. }
+                    if !matches!(op, BinaryOp::Div | BinaryOp::Rem) =>
+                {
+                    other_uses[left.0] = true
+                }
+                Instruction::Compare { left, .. } => other_uses[left.0] = true,
+                Instruction::Store { address, .. } => other_uses[address.0] = true,
+                _ => {
+                    for vreg in instruction.get_uses() {
+                        other_uses[vreg.0] = true;
+                    }
+                }
+            }
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            other_uses[vreg.0] = true;
+        }
+    }
+    func.get_constants()
+        .into_iter()
+        .zip(other_uses)
+        .map(|(value, other_use)| {
+            value
+                .map(|value| value as i64)
+                .filter(|value| !other_use && (i32::MIN as i64..=i32::MAX as i64).contains(value))
+        })
+        .collect()
+}
+
+/// 値を変えずにコピーするだけの命令であれば(書き込み先, 読み出し元)を返す
+///
+/// 仮想レジスタの値は常に型に合わせて拡張された8バイトの値なので,
+/// 同じ型への変換と8バイトの型への変換はコピーとなる
+pub fn get_move(instruction: &Instruction, func: &IrFunction) -> Option<(VReg, VReg)> {
+    match instruction {
+        Instruction::Copy { dst, src } => Some((*dst, *src)),
+        Instruction::Cast { dst, src } => {
+            let dst_type = func.get_vreg_type(*dst);
+            if dst_type == func.get_vreg_type(*src) || dst_type.size() == 8 {
+                Some((*dst, *src))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// 仮想レジスタの生存区間
+// 命令iの読み出しを位置2i, 書き込みを位置2i+1とし, 最初の書き込みから最後の読み出しまでを区間とする
+// ブロックの入口で生存する場合はブロックの先頭まで, 出口で生存する場合は末尾まで区間を広げる
+#[derive(Debug, Clone, Copy)]
+struct LiveInterval {
+    vreg: VReg,
+    start: usize,
+    end: usize,
+}
+
+// ブロックごとの生存解析
+// live_outはブロックの出口で生存している(後続のブロックで読み出される)仮想レジスタの集合
+fn analyze_liveness(func: &IrFunction) -> Vec<Vec<bool>> {
+    let vreg_count = func.vreg_types.len();
+    let block_count = func.blocks.len();
+    // ブロック内で書き込み前に読み出す仮想レジスタと, 書き込む仮想レジスタ
+    let mut uses = vec![vec![false; vreg_count]; block_count];
+    let mut defs = vec![vec![false; vreg_count]; block_count];
+    for (index, block) in func.blocks.iter().enumerate() {
+        for instruction in &block.instructions {
+            for vreg in instruction.get_uses() {
+                if !defs[index][vreg.0] {
+                    uses[index][vreg.0] = true;
+                }
+            }
+            if let Some(dst) = instruction.get_dst() {
+                defs[index][dst.0] = true;
+            }
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            if !defs[index][vreg.0] {
+                uses[index][vreg.0] = true;
+            }
+        }
+    }
+
+    let mut live_in = vec![vec![false; vreg_count]; block_count];
+    let mut live_out = vec![vec![false; vreg_count]; block_count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, block) in func.blocks.iter().enumerate().rev() {
+            let mut out = vec![false; vreg_count];
+            for succ in block.terminator.iter().flat_map(|term| term.get_succs()) {
+                for (elem, succ_elem) in out.iter_mut().zip(live_in[succ.0].iter()) {
+                    *elem |= *succ_elem;
+                }
+            }
+            let input: Vec<bool> = (0..vreg_count)
+                .map(|vreg| uses[index][vreg] || (out[vreg] && !defs[index][vreg]))
+                .collect();
+            if input != live_in[index] || out != live_out[index] {
+                live_in[index] = input;
+                live_out[index] = out;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+// 生存区間と関数呼び出しの位置を求める
+fn build_intervals(func: &IrFunction) -> (Vec<LiveInterval>, Vec<usize>) {
+    let live_out = analyze_liveness(func);
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; func.vreg_types.len()];
+    let mut extend = |vreg: VReg, position: usize| {
+        let range = ranges[vreg.0].get_or_insert((position, position));
+        range.0 = range.0.min(position);
+        range.1 = range.1.max(position);
+    };
+    let mut call_positions = vec![];
+    let mut index = 0;
+    for (block_index, block) in func.blocks.iter().enumerate() {
+        let block_start = 2 * index;
+        for instruction in &block.instructions {
+            for vreg in instruction.get_uses() {
+                extend(vreg, 2 * index);
+            }
+            if let Some(dst) = instruction.get_dst() {
+                extend(dst, 2 * index + 1);
+            }
+            if matches!(instruction, Instruction::Call { .. }) {
+                call_positions.push(2 * index);
+            }
+            index += 1;
+        }
+        for vreg in block.terminator.iter().flat_map(|term| term.get_uses()) {
+            extend(vreg, 2 * index);
+        }
+        let block_end = 2 * index + 1;
+        index += 1;
+        // ブロックの出口で生存する値は, ブロック全体で生存する(ループの先頭に戻る場合を含む)
+        for (vreg, is_live) in live_out[block_index].iter().enumerate() {
+            if *is_live {
+                extend(VReg(vreg), block_start);
+                extend(VReg(vreg), block_end);
+            }
+        }
+    }
+    let intervals = ranges
+        .into_iter()
+        .enumerate()
+        .filter_map(|(vreg, range)| {
+            range.map(|(start, end)| LiveInterval {
+                vreg: VReg(vreg),
+                start,
+                end,
+            })
+        })
+        .collect();
+    (intervals, call_positions)
+}
+
+struct LinearScan {
+    locations: Vec<Option<Location>>,
+    // 割り当て中の区間と割り当てたレジスタ
+    active: Vec<(LiveInterval, &'static str)>,
+    free_registers: Vec<&'static str>,
+    spill_count: usize,
+    spill_offset: usize,
+    // コピー元の仮想レジスタ
+    // コピー元の区間がコピーで終わる場合は同じレジスタを割り当ててコピーを省略する
+    hints: Vec<Option<VReg>>,
+}
+
+impl LinearScan {
+    // 区間の開始より前に終わった区間のレジスタを解放する
+    fn expire(&mut self, start: usize) {
+        let (expired, active): (Vec<_>, Vec<_>) = self
+            .active
+            .drain(..)
+            .partition(|(interval, _)| interval.end < start);
+        self.active = active;
+        self.free_registers
+            .extend(expired.into_iter().map(|(_, register)| register));
+    }
+
+    fn spill(&mut self, vreg: VReg) {
+        self.locations[vreg.0] = Some(Location::Stack(self.spill_offset + 8 * self.spill_count));
+        self.spill_count += 1;
+    }
+
+    // コピー元のレジスタが空いていればそれを使用し, それ以外は呼び出し元保存レジスタを優先して使用する
+    fn take_free_register(&mut self, vreg: VReg, crosses_call: bool) -> Option<&'static str> {
+        let hint = match self.hints[vreg.0].and_then(|src| self.locations[src.0]) {
+            Some(Location::Register(register))
+                if !crosses_call || CALLEE_SAVED_REGISTERS.contains(&register) =>
+            {
+                self.free_registers
+                    .iter()
+                    .position(|free| *free == register)
+            }
+            _ => None,
+        };
+        if let Some(position) = hint {
+            return Some(self.free_registers.swap_remove(position));
+        }
+        let candidates = if crosses_call {
+            &CALLEE_SAVED_REGISTERS[..]
+        } else {
+            &CALLER_SAVED_REGISTERS[..]
+        };
+        let position = self
+            .free_registers
+            .iter()
+            .position(|register| candidates.contains(register))
+            .or_else(|| {
+                self.free_registers
+                    .iter()
+                    .position(|register| CALLEE_SAVED_REGISTERS.contains(register))
+            })?;
+        Some(self.free_registers.swap_remove(position))
+    }
+
+    // 空きレジスタがない場合は, 使用できるレジスタを持つ区間のうち最も後まで生存する区間を退避する
+    fn allocate(&mut self, interval: LiveInterval, crosses_call: bool) {
+        if let Some(register) = self.take_free_register(interval.vreg, crosses_call) {
+            self.locations[interval.vreg.0] = Some(Location::Register(register));
+            self.active.push((interval, register));
+            return;
+        }
+        let victim = self
+            .active
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, register))| !crosses_call || CALLEE_SAVED_REGISTERS.contains(register))
+            .max_by_key(|(_, (active_interval, _))| active_interval.end)
+            .map(|(position, (active_interval, _))| (position, active_interval.end));
+        match victim {
+            Some((position, end)) if end > interval.end => {
+                let (victim_interval, register) = self.active.swap_remove(position);
+                self.spill(victim_interval.vreg);
+                self.locations[interval.vreg.0] = Some(Location::Register(register));
+                self.active.push((interval, register));
+            }
+            _ => self.spill(interval.vreg),
+        }
+    }
+}
+
+/// 線形走査によるレジスタ割り当て
+///
+/// 生存区間の開始順に空いているレジスタを割り当て, 割り当てられない仮想レジスタは
+/// spill_offset以降のスタック領域(ローカル変数の領域の下)に退避する
+pub fn allocate_registers(func: &IrFunction, spill_offset: usize) -> Allocation {
+    let local_addresses = find_local_addresses(func);
+    let immediates = find_immediates(func);
+    let (mut intervals, call_positions) = build_intervals(func);
+    intervals.retain(|interval| {
+        local_addresses[interval.vreg.0].is_none() && immediates[interval.vreg.0].is_none()
+    });
+    intervals.sort_by_key(|interval| (interval.start, interval.vreg.0));
+    let mut scan = LinearScan {
+        locations: vec![None; func.vreg_types.len()],
+        active: vec![],
+        free_registers: CALLER_SAVED_REGISTERS
+            .iter()
+            .chain(CALLEE_SAVED_REGISTERS.iter())
+            .rev()
+            .copied()
+            .collect(),
+        spill_count: 0,
+        spill_offset,
+        hints: vec![None; func.vreg_types.len()],
+    };
+    // 二項演算は左辺のレジスタで計算するため, 左辺と同じレジスタを割り当てる
+    for instruction in func.blocks.iter().flat_map(|block| &block.instructions) {
+        if let Some((dst, src)) = get_move(instruction, func) {
+            scan.hints[dst.0] = Some(src);
+        } else if let Instruction::Binary { dst, left, .. } = instruction {
+            scan.hints[dst.0] = Some(*left);
+        }
+    }
+    for interval in intervals {
+        scan.expire(interval.start);
+        // 呼び出しの前に書き込まれ, 呼び出しの後に読み出される値
+        let crosses_call = call_positions
+            .iter()
+            .any(|&position| interval.start < position && position + 1 < interval.end);
+        scan.allocate(interval, crosses_call);
+    }
+
+    let callee_saved: Vec<&'static str> = CALLEE_SAVED_REGISTERS
+        .iter()
+        .filter(|register| scan.locations.contains(&Some(Location::Register(register))))
+        .copied()
+        .collect();
+    // 使用されない仮想レジスタ(書き込みも読み出しもない)はスタック領域に置く
+    let mut locations = vec![];
+    for (vreg, local_address) in local_addresses.iter().enumerate() {
+        if let Some(offset) = local_address {
+            scan.locations[vreg] = Some(Location::LocalAddress(*offset));
+        } else if let Some(value) = immediates[vreg] {
+            scan.locations[vreg] = Some(Location::Immediate(value));
+        } else if scan.locations[vreg].is_none() {
+            scan.spill(VReg(vreg));
+        }
+        locations.push(scan.locations[vreg].unwrap());
+    }
+    Allocation {
+        locations,
+        spill_count: scan.spill_count,
+        callee_saved,
+    }
+}
@@ -169,3 +169,93 @@ pub fn output_different_returntype_err(info: &TokenInfo) -> ! {
     print_token_error_info(TokenError::DifferentReturnType, info);
     exit(-1);
 }
+
+pub fn output_notvariadicfunction_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::NotVariadicFunction, info);
+    exit(-1);
+}
+
+pub fn output_conflictinglinkage_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::ConflictingLinkage, info);
+    exit(-1);
+}
+
+pub fn output_conflictingtype_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::ConflictingType, info);
+    exit(-1);
+}
+
+pub fn output_redefinition_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::Redefinition, info);
+    exit(-1);
+}
+
+pub fn output_notconstant_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::NotConstant, info);
+    exit(-1);
+}
+
+pub fn output_nomember_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::NoMember, info);
+    exit(-1);
+}
+
+pub fn output_excessinitializer_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::ExcessInitializer, info);
+    exit(-1);
+}
+
+pub fn output_notcastable_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::NotCastable, info);
+    exit(-1);
+}
+
+pub fn output_readonly_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::ReadOnly, info);
+    exit(-1);
+}
+
+pub fn output_discardedqualifier_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::DiscardedQualifier, info);
+    exit(-1);
+}
+
+pub fn output_incompatibleassignment_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::IncompatibleAssignment, info);
+    exit(-1);
+}
+
+pub fn output_invalidtypespecifier_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::InvalidTypeSpecifier, info);
+    exit(-1);
+}
+
+pub fn output_divisionbyzero_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::DivisionByZero, info);
+    exit(-1);
+}
+
+pub fn output_constantoverflow_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::ConstantOverflow, info);
+    exit(-1);
+}
+
+pub fn output_notintegerconstant_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::NotIntegerConstant, info);
+    exit(-1);
+}
+
+pub fn output_invalidarraysize_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::InvalidArraySize, info);
+    exit(-1);
+}
+
+pub fn output_incompletetype_err(info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::IncompleteType, info);
+    exit(-1);
+}
+
+pub fn output_staticassertfailed_err(message: &str, info: &TokenInfo) -> ! {
+    print_token_error_info(TokenError::StaticAssertFailed(message.to_string()), info);
+    exit(-1);
+}
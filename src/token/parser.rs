@@ -322,7 +322,17 @@ fn get_mult_symbol(parse_line: &mut LineParser, rawtoken_chars: &mut Vec<char>)
 
 // 記号トークン作成
 // <<のような複数記号のトークンも作成する(<トークン2つにしない)
+// 可変長引数の"..."は2文字の記号を経由しないので個別に判定する
 fn get_symbol(parse_line: &mut LineParser, token_chars: &mut Vec<char>) {
+    if token_chars[0] == '.'
+        && parse_line.peek_nextchar(0) == Some('.')
+        && parse_line.peek_nextchar(1) == Some('.')
+    {
+        token_chars.push('.');
+        token_chars.push('.');
+        parse_line.advance(2);
+        return;
+    }
     get_mult_symbol(parse_line, token_chars);
 }
 
@@ -360,8 +370,8 @@ pub fn parse_file(filepath: &Path) -> Vec<RawToken> {
 
     let source_txt: &mut Vec<String>;
     unsafe {
-        SOURCE_TXT.clear();
-        source_txt = &mut SOURCE_TXT;
+        source_txt = &mut *std::ptr::addr_of_mut!(SOURCE_TXT);
+        source_txt.clear();
     }
 
     for (line_num, line) in reader.lines().enumerate() {
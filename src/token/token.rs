@@ -1,7 +1,6 @@
 use crate::definition::number::{string_to_number, Number};
 use crate::definition::reservedwords::*;
 use crate::definition::symbols::{get_token_symbol, Symbol};
-use crate::definition::types::{PrimitiveType, PrimitiveTypeError};
 use crate::token::error::*;
 use crate::token::parser::{RawToken, RawTokenKind};
 use std::fmt;
@@ -15,7 +14,7 @@ pub enum TokenKind {
     RawString(String),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TokenInfo {
     pub line: usize,
     pub pos: usize,
@@ -118,10 +117,10 @@ impl Token {
         }
     }
 
-    pub fn get_primitivetypename(&self) -> Result<PrimitiveType, PrimitiveTypeError> {
+    pub fn get_reserved(&self) -> Option<&Reserved> {
         match &self.kind {
-            TokenKind::Reserved(reserved) => get_primitivetype_reserved_word(reserved),
-            _ => Err(PrimitiveTypeError::NotPrimitiveTypeErr),
+            TokenKind::Reserved(reserved) => Some(reserved),
+            _ => None,
         }
     }
 }
@@ -145,6 +144,24 @@ pub enum TokenError {
     UnIndexiable,
     UnExecutable,
     DifferentReturnType,
+    NotVariadicFunction,
+    ConflictingLinkage,
+    ConflictingType,
+    Redefinition,
+    NotConstant,
+    NoMember,
+    ExcessInitializer,
+    NotCastable,
+    ReadOnly,
+    DiscardedQualifier,
+    IncompatibleAssignment,
+    InvalidTypeSpecifier,
+    DivisionByZero,
+    ConstantOverflow,
+    NotIntegerConstant,
+    InvalidArraySize,
+    IncompleteType,
+    StaticAssertFailed(String),
 }
 
 impl fmt::Display for TokenError {
@@ -204,6 +221,63 @@ impl fmt::Display for TokenError {
             TokenError::DifferentReturnType => {
                 write!(f, "diffrent return type")
             }
+            TokenError::NotVariadicFunction => {
+                write!(f, "va_start used in function with fixed arguments")
+            }
+            TokenError::ConflictingLinkage => {
+                write!(f, "conflicting linkage with previous declaration")
+            }
+            TokenError::ConflictingType => {
+                write!(f, "conflicting type with previous declaration")
+            }
+            TokenError::Redefinition => {
+                write!(f, "redefinition")
+            }
+            TokenError::NotConstant => {
+                write!(f, "initializer is not constant")
+            }
+            TokenError::NoMember => {
+                write!(f, "no such member")
+            }
+            TokenError::ExcessInitializer => {
+                write!(f, "excess elements in initializer")
+            }
+            TokenError::NotCastable => {
+                write!(f, "invalid cast")
+            }
+            TokenError::ReadOnly => {
+                write!(f, "assignment of read-only location")
+            }
+            TokenError::DiscardedQualifier => {
+                write!(f, "conversion discards qualifiers from pointer target type")
+            }
+            TokenError::IncompatibleAssignment => {
+                write!(f, "incompatible types in assignment")
+            }
+            TokenError::InvalidTypeSpecifier => {
+                write!(f, "invalid combination of type specifiers")
+            }
+            TokenError::DivisionByZero => {
+                write!(f, "division by zero in constant expression")
+            }
+            TokenError::ConstantOverflow => {
+                write!(f, "integer overflow in constant expression")
+            }
+            TokenError::NotIntegerConstant => {
+                write!(f, "expression is not an integer constant expression")
+            }
+            TokenError::InvalidArraySize => {
+                write!(f, "size of array is not positive")
+            }
+            TokenError::IncompleteType => {
+                write!(f, "invalid use of incomplete type")
+            }
+            TokenError::StaticAssertFailed(message) if message.is_empty() => {
+                write!(f, "static assertion failed")
+            }
+            TokenError::StaticAssertFailed(message) => {
+                write!(f, "static assertion failed: {}", message)
+            }
         }
     }
 }
@@ -372,6 +446,19 @@ impl Tokens {
         }
     }
 
+    pub fn consume_rawstring(&mut self) -> (String, TokenInfo) {
+        if let Some(token) = self.vec.get(self.cur) {
+            if let Some(rawstring) = token.get_rawstring() {
+                self.cur += 1;
+                (rawstring.clone(), token.info)
+            } else {
+                output_unexpected_token_err(self)
+            }
+        } else {
+            output_unexpected_token_err(self)
+        }
+    }
+
     pub fn expect_reserved(&self, reserved: Reserved) -> bool {
         if let Some(token) = self.vec.get(self.cur) {
             token.expect_reserved(reserved)
@@ -401,40 +488,4 @@ impl Tokens {
         }
         false
     }
-
-    pub fn get_primitivetype(&mut self) -> Result<PrimitiveType, ()> {
-        if let Some(token) = self.vec.get(self.cur) {
-            let primitive_type = token.get_primitivetypename();
-            match primitive_type {
-                Ok(type_) => {
-                    self.cur += 1;
-                    Ok(type_)
-                }
-                Err(PrimitiveTypeError::UnsignedError) => {
-                    self.cur += 1;
-                    if let Some(token) = self.vec.get(self.cur) {
-                        if let Ok(primitive_type) = token.get_primitivetypename() {
-                            self.cur += 1;
-                            let type_ = match primitive_type {
-                                PrimitiveType::I8 => PrimitiveType::U8,
-                                PrimitiveType::I16 => PrimitiveType::U16,
-                                PrimitiveType::I32 => PrimitiveType::U32,
-                                PrimitiveType::I64 => PrimitiveType::U64,
-                                _ => return Err(()),
-                            };
-                            self.cur += 1;
-                            Ok(type_)
-                        } else {
-                            Err(())
-                        }
-                    } else {
-                        Err(())
-                    }
-                }
-                Err(PrimitiveTypeError::NotPrimitiveTypeErr) => Err(()),
-            }
-        } else {
-            Err(())
-        }
-    }
 }
@@ -0,0 +1,5 @@
+long add_ten(long x);
+
+long ext_twice(long x) {
+    return add_ten(x) * 2;
+}
@@ -0,0 +1,3 @@
+long weighted_sum(long a, long b, long c, long d, long e, long f, long g, long h) {
+    return a * 1 + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8;
+}
@@ -0,0 +1,14 @@
+// 呼び出し時にrspが16バイト境界であれば, 退避後のrbpも16バイト境界になる
+long stack_aligned(long x) {
+    if ((unsigned long)__builtin_frame_address(0) % 16 != 0) {
+        return 100;
+    }
+    return x;
+}
+
+long stack_aligned7(long a, long b, long c, long d, long e, long f, long g) {
+    if ((unsigned long)__builtin_frame_address(0) % 16 != 0) {
+        return 100;
+    }
+    return a + b + c + d + e + f + g;
+}
@@ -0,0 +1,11 @@
+long ext_counter = 7;
+
+static long hidden = 100;
+
+static long helper(void) {
+    return hidden;
+}
+
+long get_hidden(void) {
+    return helper();
+}
@@ -15,6 +15,18 @@ fn execute_binary(dir: &Path) -> i32 {
     status
 }
 
+// テストディレクトリ内のCソースファイル(外部関数定義)を取得する
+fn get_c_sources(dir: &Path) -> Vec<PathBuf> {
+    let mut c_sources: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect();
+    c_sources.sort();
+    c_sources
+}
+
+#[allow(clippy::needless_late_init)]
 fn make_binary(dir: &Path, assembley_path: &Path) {
     let command;
     if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
@@ -28,6 +40,7 @@ fn make_binary(dir: &Path, assembley_path: &Path) {
         .arg("-o")
         .arg(dir.join("a.out"))
         .arg(assembley_path)
+        .args(get_c_sources(dir))
         .status()
         .expect("failed to make binary")
         .code()
@@ -39,6 +52,8 @@ fn do_compile(dir: &Path, source: &Path, output: &Path) {
     let rawtokens = compiler::token::parser::parse_file(source);
     let tokens = compiler::token::token::make_tokens(rawtokens);
     let asts = compiler::ast::ast::make_asts(tokens);
+    compiler::analysis::analysis::analyze_asts(&asts);
+    compiler::analysis::warning::exit_if_warnings_are_errors();
     compiler::output::output::output_assembly(asts, output);
     make_binary(dir, output);
 }
@@ -64,6 +79,31 @@ fn do_test(test_type: &str) {
     }
 }
 
+// コンパイラのバイナリを実行し, 終了コードと警告, エラーのメッセージ行を返す
+// 中間表現を標準出力に出力させ, アセンブリファイルを作らないようにする
+fn run_compiler(source: &Path, options: &[&str]) -> (i32, Vec<String>) {
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .args(options)
+        .arg("--emit=ir")
+        .arg(source)
+        .output()
+        .expect("failed to execute compiler");
+    let messages = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("line") || line.starts_with("error"))
+        .map(|line| line.to_string())
+        .collect();
+    (output.status.code().unwrap(), messages)
+}
+
+// 診断のテスト用ソースファイルのパス
+fn get_diagnostic_source(test_type: &str, name: &str) -> PathBuf {
+    Path::new("tests")
+        .join(test_type)
+        .join(format!("{}.test", name))
+}
+
 #[test]
 fn add_test() {
     do_test("add");
@@ -200,3 +240,438 @@ fn multsentence_test() {
 fn initialize_declaration_test() {
     do_test("initialize_declaration");
 }
+
+#[test]
+fn funccall_stackargs_test() {
+    do_test("funccall_stackargs");
+}
+
+#[test]
+fn stack_alignment_test() {
+    do_test("stack_alignment");
+}
+
+#[test]
+fn variadic_test() {
+    do_test("variadic");
+}
+
+#[test]
+fn variadic_error_test() {
+    let source = get_diagnostic_source("variadic", "redefined");
+    assert_eq!(
+        run_compiler(&source, &[]),
+        (255, vec!["line2, pos6, error: redefinition".to_string()])
+    );
+}
+
+#[test]
+fn extern_funccall_test() {
+    do_test("extern_funccall");
+}
+
+#[test]
+fn static_extern_test() {
+    do_test("static_extern");
+}
+
+#[test]
+fn initializer_list_test() {
+    do_test("initializer_list");
+}
+
+#[test]
+fn declarator_test() {
+    do_test("declarator");
+}
+
+#[test]
+fn function_pointer_test() {
+    do_test("function_pointer");
+}
+
+#[test]
+fn pointer_arithmetic_test() {
+    do_test("pointer_arithmetic");
+}
+
+#[test]
+fn subscript_test() {
+    do_test("subscript");
+}
+
+#[test]
+fn lvalue_address_test() {
+    do_test("lvalue_address");
+}
+
+#[test]
+fn sizeof_type_test() {
+    do_test("sizeof_type");
+}
+
+#[test]
+fn sizeof_type_error_test() {
+    let source = get_diagnostic_source("sizeof_type", "incomplete");
+    assert_eq!(
+        run_compiler(&source, &[]),
+        (
+            255,
+            vec!["line5, pos12, error: invalid use of incomplete type".to_string()]
+        )
+    );
+}
+
+#[test]
+fn qualifier_test() {
+    do_test("qualifier");
+}
+
+#[test]
+fn qualifier_error_test() {
+    let source = get_diagnostic_source("qualifier", "discarded");
+    assert_eq!(
+        run_compiler(&source, &[]),
+        (
+            255,
+            vec![
+                "line6, pos8, error: conversion discards qualifiers from pointer target type"
+                    .to_string()
+            ]
+        )
+    );
+}
+
+#[test]
+fn bool_test() {
+    do_test("bool");
+}
+
+#[test]
+fn integer_types_test() {
+    do_test("integer_types");
+}
+
+#[test]
+fn return_type_test() {
+    do_test("return_type");
+}
+
+#[test]
+fn control_flow_test() {
+    do_test("control_flow");
+}
+
+#[test]
+fn control_flow_warning_test() {
+    let source = get_diagnostic_source("control_flow", "warned");
+    let (status, messages) = run_compiler(&source, &[]);
+    assert_eq!(status, 0);
+    assert_eq!(
+        messages,
+        [
+            "line7, pos1, warning: control reaches end of non-void function 'missing_return'",
+            "line12, pos5, warning: will never be executed",
+            "line20, pos9, warning: will never be executed",
+            "line30, pos5, warning: will never be executed",
+            "line38, pos5, warning: will never be executed",
+            "line46, pos1, warning: control reaches end of non-void function 'loop_fall_off'",
+        ]
+    );
+}
+
+#[test]
+fn warning_options_test() {
+    // 警告オプションはスレッドごとに保持されるので, 他のテストには影響しない
+    let mut options = compiler::analysis::warning::WarningOptions::default();
+    for option in [
+        "-Wunused-variable",
+        "-Wunused-parameter",
+        "-Wshadow",
+        "-Werror",
+    ] {
+        options.parse_option(option).unwrap();
+    }
+    compiler::analysis::warning::set_warning_options(options);
+    do_test("warning_options");
+}
+
+#[test]
+fn warning_options_warning_test() {
+    let source = get_diagnostic_source("warning_options", "warned");
+    let unused_variable =
+        "line9, pos10, warning: unused variable 'unused_local' [-Wunused-variable]";
+    let unused_parameter = "line4, pos28, warning: unused parameter 'unused' [-Wunused-parameter]";
+    let shadow_global =
+        "line10, pos10, warning: declaration of 'shadowed' shadows a previous declaration [-Wshadow]";
+    let shadow_local =
+        "line15, pos14, warning: declaration of 'count' shadows a previous declaration [-Wshadow]";
+
+    // オプションを指定しなければ警告しない
+    assert_eq!(run_compiler(&source, &[]), (0, vec![]));
+    assert_eq!(
+        run_compiler(&source, &["-Wunused-variable"]),
+        (0, vec![unused_variable.to_string()])
+    );
+    assert_eq!(
+        run_compiler(&source, &["-Wunused-parameter"]),
+        (0, vec![unused_parameter.to_string()])
+    );
+    assert_eq!(
+        run_compiler(&source, &["-Wshadow"]),
+        (0, vec![shadow_global.to_string(), shadow_local.to_string()])
+    );
+    assert_eq!(
+        run_compiler(
+            &source,
+            &["-Wunused-variable", "-Wunused-parameter", "-Wshadow"]
+        ),
+        (
+            0,
+            vec![
+                unused_parameter.to_string(),
+                unused_variable.to_string(),
+                shadow_global.to_string(),
+                shadow_local.to_string(),
+            ]
+        )
+    );
+
+    // -Werrorでは各警告がエラーとなり, 失敗する
+    for (option, count) in [
+        ("-Wunused-variable", 1),
+        ("-Wunused-parameter", 1),
+        ("-Wshadow", 2),
+    ] {
+        let (status, messages) = run_compiler(&source, &[option, "-Werror"]);
+        assert_eq!(status, 255);
+        assert_eq!(messages.len(), count + 1);
+        assert!(messages[..count]
+            .iter()
+            .all(|message| message.contains(", error: ")));
+        assert_eq!(
+            messages[count],
+            format!("error: {} warning(s) treated as errors", count)
+        );
+    }
+
+    // -Wno-は前に指定した警告を無効にする
+    assert_eq!(
+        run_compiler(
+            &source,
+            &["-Wunused-variable", "-Wshadow", "-Wno-shadow", "-Werror"]
+        ),
+        (
+            255,
+            vec![
+                "line9, pos10, error: unused variable 'unused_local' [-Wunused-variable]"
+                    .to_string(),
+                "error: 1 warning(s) treated as errors".to_string(),
+            ]
+        )
+    );
+    assert_eq!(
+        run_compiler(&source, &["-Wunused-variable", "-Werror", "-Wno-error"]),
+        (0, vec![unused_variable.to_string()])
+    );
+    let mut options = compiler::analysis::warning::WarningOptions::default();
+    assert!(options.parse_option("-Wno-unused-variable").is_ok());
+    assert!(options.parse_option("-Wno-unknown").is_err());
+}
+
+#[test]
+fn initialized_use_test() {
+    let mut options = compiler::analysis::warning::WarningOptions::default();
+    options.parse_option("-Werror").unwrap();
+    compiler::analysis::warning::set_warning_options(options);
+    do_test("initialized_use");
+}
+
+#[test]
+fn initialized_use_warning_test() {
+    let source = get_diagnostic_source("initialized_use", "warned");
+    let (status, messages) = run_compiler(&source, &[]);
+    assert_eq!(status, 0);
+    assert_eq!(
+        messages,
+        [
+            "line15, pos9, warning: 'x' may be used uninitialized",
+            "line26, pos12, warning: 'x' may be used uninitialized",
+            "line36, pos12, warning: 'x' may be used uninitialized",
+            "line44, pos13, warning: 'x' may be used uninitialized",
+        ]
+    );
+    // -Werrorでは失敗する
+    let (status, messages) = run_compiler(&source, &["-Werror"]);
+    assert_eq!(status, 255);
+    assert_eq!(
+        messages.last().unwrap(),
+        "error: 4 warning(s) treated as errors"
+    );
+}
+
+#[test]
+fn constant_expression_test() {
+    do_test("constant_expression");
+}
+
+#[test]
+fn intermediate_representation_test() {
+    do_test("intermediate_representation");
+}
+
+#[test]
+fn register_allocation_test() {
+    do_test("register_allocation");
+    // 定数の即値化は最適化パスで定数になった値に対して行われる
+    let mut options = compiler::ir::pass::OptimizationOptions::default();
+    options.parse_option("-O1").unwrap();
+    compiler::ir::pass::set_optimization_options(options);
+    do_test("register_allocation");
+}
+
+// 1ブロックの関数の中間表現を検証し, エラーメッセージを返す
+fn verify_instructions(
+    vreg_types: Vec<compiler::ir::ir::IrType>,
+    instructions: Vec<compiler::ir::ir::Instruction>,
+) -> Vec<String> {
+    use compiler::ir::ir::*;
+    // 既存のテストの関数を変換し, 命令列を差し替える
+    let source =
+        get_diagnostic_source("intermediate_representation", "intermediate_representation");
+    let rawtokens = compiler::token::parser::parse_file(&source);
+    let tokens = compiler::token::token::make_tokens(rawtokens);
+    let asts = compiler::ast::ast::make_asts(tokens);
+    let mut func = compiler::ir::lower::lower_asts(&asts).remove(0);
+    func.return_type = None;
+    func.frame_size = 8;
+    func.vreg_types = vreg_types;
+    func.blocks = vec![BasicBlock {
+        instructions,
+        terminator: Some(Terminator::Return(None)),
+    }];
+    compiler::ir::verify::check_function(&func)
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+#[test]
+fn verify_test() {
+    use compiler::ir::ir::*;
+    let local = Instruction::LocalAddress {
+        dst: VReg(0),
+        offset: 8,
+    };
+    let constant = Instruction::Const {
+        dst: VReg(1),
+        value: 1,
+    };
+    let binary = |op, dst, left, right| Instruction::Binary {
+        op,
+        dst: VReg(dst),
+        left: VReg(left),
+        right: VReg(right),
+    };
+    let store = |type_, src| Instruction::Store {
+        type_,
+        address: VReg(0),
+        src: VReg(src),
+        volatile: false,
+    };
+    let types = vec![IrType::Ptr, IrType::I64, IrType::Ptr];
+    // ポインタ ± 整数, ポインタ - ポインタ, 整数へのstoreは型が合う
+    let valid = vec![
+        local.clone(),
+        constant.clone(),
+        binary(BinaryOp::Add, 2, 0, 1),
+        store(IrType::I32, 1),
+        store(IrType::Ptr, 2),
+    ];
+    assert!(verify_instructions(types.clone(), valid).is_empty());
+    let types = vec![IrType::Ptr, IrType::I64, IrType::I64];
+    let valid = vec![local.clone(), binary(BinaryOp::Sub, 1, 0, 0)];
+    assert!(verify_instructions(types.clone(), valid).is_empty());
+
+    // 整数の加算にポインタは使えない
+    let invalid = vec![
+        local.clone(),
+        constant.clone(),
+        binary(BinaryOp::Add, 2, 1, 0),
+    ];
+    assert_eq!(
+        verify_instructions(types.clone(), invalid),
+        ["bb0 expects %0 to be the same type as the destination"]
+    );
+    // ポインタ + ポインタはできない
+    let types = vec![IrType::Ptr, IrType::I64, IrType::Ptr];
+    let invalid = vec![local.clone(), binary(BinaryOp::Add, 2, 0, 0)];
+    assert_eq!(
+        verify_instructions(types, invalid),
+        ["bb0 expects %0 to be an integer"]
+    );
+    // ビット演算の結果はポインタにできない
+    let types = vec![IrType::Ptr, IrType::I64, IrType::Ptr];
+    let invalid = vec![
+        local.clone(),
+        constant.clone(),
+        binary(BinaryOp::BitAnd, 2, 1, 1),
+    ];
+    assert_eq!(
+        verify_instructions(types, invalid),
+        [
+            "bb0 expects %2 to be an integer",
+            "bb0 expects %1 to be the same type as the destination",
+            "bb0 expects %1 to be the same type as the destination",
+        ]
+    );
+    // storeする値は書き込む型に合わなければならない
+    let types = vec![IrType::Ptr, IrType::I64];
+    let invalid = vec![
+        local,
+        constant,
+        store(IrType::I64, 0),
+        store(IrType::Ptr, 1),
+        store(IrType::Bool, 1),
+    ];
+    assert_eq!(
+        verify_instructions(types, invalid),
+        [
+            "bb0 expects %0 to be an integer",
+            "bb0 expects %1 to be a pointer",
+            "bb0 expects %1 to be a _Bool value",
+        ]
+    );
+}
+
+#[test]
+fn pass_manager_test() {
+    let mut options = compiler::ir::pass::OptimizationOptions::default();
+    options.parse_option("-O2").unwrap();
+    options.parse_option("--passes=verify,verify").unwrap();
+    options.parse_option("--print-after=verify").unwrap();
+    assert!(options.parse_option("--passes=unknown").is_err());
+    compiler::ir::pass::set_optimization_options(options);
+    do_test("pass_manager");
+}
+
+#[test]
+fn constant_folding_test() {
+    let mut options = compiler::ir::pass::OptimizationOptions::default();
+    options.parse_option("-O2").unwrap();
+    compiler::ir::pass::set_optimization_options(options);
+    do_test("constant_folding");
+}
+
+#[test]
+fn dead_code_test() {
+    let mut options = compiler::ir::pass::OptimizationOptions::default();
+    options.parse_option("-O1").unwrap();
+    compiler::ir::pass::set_optimization_options(options);
+    do_test("dead_code");
+}
+
+#[test]
+fn struct_assign_test() {
+    do_test("struct_assign");
+}
@@ -0,0 +1,21 @@
+#include <stdarg.h>
+
+long sum_c(long count, ...) {
+    va_list ap;
+    long total = 0;
+    va_start(ap, count);
+    for (long i = 0; i < count; i++) {
+        total += va_arg(ap, long);
+    }
+    va_end(ap);
+    return total;
+}
+
+long mixed(int count, ...);
+
+// 4バイトの整数型の可変長引数はレジスタの上位4バイトが0のまま渡される
+long call_mixed(void) {
+    int x = 7;
+    return mixed(3, -3, 4000000000u, 100L, &x, -4, 4000000000u, 200L, &x, -5, 4000000000u,
+                 300L, &x);
+}